@@ -153,23 +153,23 @@ fn connect_vdir(vdir_config: &crate::config::VdirConfig) -> Result<()> {
 
 /// Resolves the CardDAV context root and walks the principal +
 /// addressbook-home-set, proving the server address, TLS and
-/// authentication all work.
+/// authentication all work. The discovery cache is bypassed.
 #[cfg(feature = "carddav")]
 fn connect_carddav(carddav_config: &crate::config::CarddavConfig) -> Result<()> {
     use crate::carddav::client::open_carddav_client;
 
-    open_carddav_client(carddav_config.clone())?;
+    open_carddav_client(carddav_config.clone(), None)?;
 
     Ok(())
 }
 
 /// Establishes the JMAP session, proving the server address, TLS and
-/// authentication all work.
+/// authentication all work. The discovery cache is bypassed.
 #[cfg(feature = "jmap")]
 fn connect_jmap(jmap_config: &crate::config::JmapConfig) -> Result<()> {
-    use crate::jmap::backend::JmapBackend;
+    use crate::jmap::client::open_jmap_client;
 
-    JmapBackend::new(jmap_config.clone())?;
+    open_jmap_client(jmap_config.clone(), None)?;
 
     Ok(())
 }
@@ -5,21 +5,24 @@ use clap::Subcommand;
 use pimalaya_cli::printer::Printer;
 
 use crate::{
-    account::{check::AccountCheckCommand, list::AccountListCommand},
+    account::{
+        check::AccountCheckCommand, discover::AccountDiscoverCommand, list::AccountListCommand,
+    },
     backend::Backend,
 };
 
 /// Manage accounts defined in the TOML configuration file.
 ///
 /// An account is a named group of backend settings (vdir, carddav,
-/// jmap, msgraph, google). Use these subcommands to inspect them or
-/// validate their connection. To create a new account, run bare
-/// `cardamum`.
+/// jmap, msgraph, google). Use these subcommands to inspect them,
+/// validate their connection or resolve their discovery. To create a
+/// new account, run bare `cardamum`.
 #[derive(Debug, Subcommand)]
 pub enum AccountCommand {
     #[command(visible_alias = "ls")]
     List(AccountListCommand),
     Check(AccountCheckCommand),
+    Discover(AccountDiscoverCommand),
 }
 
 impl AccountCommand {
@@ -33,6 +36,7 @@ impl AccountCommand {
         match self {
             Self::List(cmd) => cmd.execute(printer, config_paths),
             Self::Check(cmd) => cmd.execute(printer, config_paths, account_name, backend),
+            Self::Discover(cmd) => cmd.execute(printer, config_paths, account_name, backend),
         }
     }
 }
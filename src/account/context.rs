@@ -11,8 +11,12 @@ use anyhow::{Result, bail};
 use comfy_table::{Color as TableColor, ContentArrangement, presets};
 use crossterm::style::Color;
 
-use crate::config::{
-    AccountConfig, AddressbookListTableConfig, CardListTableConfig, Config, TableArrangementConfig,
+use crate::{
+    config::{
+        AccountConfig, AddressbookListTableConfig, CardListTableConfig, Config,
+        TableArrangementConfig,
+    },
+    shared::card::{CardColumn, CardSort},
 };
 
 const DEFAULT_CARDS_LIST_PAGE_SIZE: u32 = 25;
@@ -23,6 +27,10 @@ pub struct Account {
     pub table_arrangement: Option<TableArrangementConfig>,
 
     pub cards_list_page_size: Option<u32>,
+    pub cards_list_columns: Option<Vec<CardColumn>>,
+    pub cards_list_sort: Option<CardSort>,
+    pub cards_cache: Option<bool>,
+    pub cards_groups: Option<bool>,
 
     /// Fallback addressbook id for `card` commands when their
     /// `-k/--addressbook` flag is omitted.
@@ -42,6 +50,10 @@ impl Account {
             table_arrangement: other.table_arrangement.or(self.table_arrangement),
 
             cards_list_page_size: other.cards_list_page_size.or(self.cards_list_page_size),
+            cards_list_columns: other.cards_list_columns.or(self.cards_list_columns),
+            cards_list_sort: other.cards_list_sort.or(self.cards_list_sort),
+            cards_cache: other.cards_cache.or(self.cards_cache),
+            cards_groups: other.cards_groups.or(self.cards_groups),
 
             addressbook_default: other.addressbook_default.or(self.addressbook_default),
 
@@ -78,6 +90,37 @@ impl Account {
             .unwrap_or(DEFAULT_CARDS_LIST_PAGE_SIZE)
     }
 
+    /// Effective columns of the cards table when the `--columns` flag
+    /// is not passed. Defaults to ID, FN, EMAIL and TEL.
+    pub fn cards_list_columns(&self) -> Vec<CardColumn> {
+        self.cards_list_columns.clone().unwrap_or_else(|| {
+            vec![
+                CardColumn::Id,
+                CardColumn::Fn,
+                CardColumn::Email,
+                CardColumn::Tel,
+            ]
+        })
+    }
+
+    /// Effective sort order of the cards table when the `--sort` flag
+    /// is not passed. Defaults to the backend order.
+    pub fn cards_list_sort(&self) -> Option<CardSort> {
+        self.cards_list_sort
+    }
+
+    /// Whether `card` commands go through the on-disk card cache of
+    /// remote backends. Defaults to `false`.
+    pub fn cards_cache(&self) -> bool {
+        self.cards_cache.unwrap_or(false)
+    }
+
+    /// Whether backend contact groups round-trip through the CATEGORIES
+    /// of the cards. Defaults to `false`.
+    pub fn cards_groups(&self) -> bool {
+        self.cards_groups.unwrap_or(false)
+    }
+
     /// Resolves the addressbook id a shared-API command operates on: the
     /// `-k/--addressbook` flag wins; otherwise the `addressbook.default`
     /// config is used; otherwise the command bails.
@@ -124,6 +167,10 @@ impl From<Config> for Account {
             table_preset: config.table.preset,
             table_arrangement: config.table.arrangement,
             cards_list_page_size: config.card.list.page_size,
+            cards_list_columns: config.card.list.columns,
+            cards_list_sort: config.card.list.sort,
+            cards_cache: config.card.cache,
+            cards_groups: config.card.groups,
             addressbook_default: config.addressbook.default,
             addressbooks_list_table: config.addressbook.list.table,
             cards_list_table: config.card.list.table,
@@ -137,6 +184,10 @@ impl From<AccountConfig> for Account {
             table_preset: config.table.preset,
             table_arrangement: config.table.arrangement,
             cards_list_page_size: config.card.list.page_size,
+            cards_list_columns: config.card.list.columns,
+            cards_list_sort: config.card.list.sort,
+            cards_cache: config.card.cache,
+            cards_groups: config.card.groups,
             addressbook_default: config.addressbook.default,
             addressbooks_list_table: config.addressbook.list.table,
             cards_list_table: config.card.list.table,
@@ -0,0 +1,163 @@
+use std::{fmt, path::PathBuf};
+
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
+use pimalaya_cli::printer::Printer;
+use pimalaya_config::toml::TomlConfig;
+use serde::Serialize;
+
+use crate::{backend::Backend, config::Config};
+
+/// Resolve the discovery of the account afresh.
+///
+/// Runs the CardDAV discovery (PACC, RFC 6764, then the principal and
+/// addressbook-home-set walk) and fetches the JMAP session of the
+/// active account, bypassing the discovery cache, then prints the
+/// results. The CardDAV home-set can be pinned into the account config
+/// as `carddav.home`, which skips discovery entirely. With `--write`,
+/// the results also replace the cached ones, restarting their
+/// `discovery-ttl`.
+///
+/// JSON output: `{"account", "carddav_home", "jmap_api_url",
+/// "written"}`.
+#[derive(Debug, Parser)]
+pub struct AccountDiscoverCommand {
+    /// Store the results in the discovery cache.
+    #[arg(long)]
+    pub write: bool,
+}
+
+impl AccountDiscoverCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: Option<&str>,
+        backend: Backend,
+    ) -> Result<()> {
+        let mut config = match Config::from_paths_or_default(config_paths)? {
+            Some(config) => config,
+            None => bail!(
+                "No configuration found. Run bare `cardamum` to launch the wizard \
+                 and generate one."
+            ),
+        };
+
+        #[allow(unused_mut)]
+        let (name, mut account_config) = config
+            .take_account(account_name)?
+            .ok_or_else(|| anyhow!("Cannot find account"))?;
+
+        #[allow(unused_mut)]
+        let mut report = DiscoverReport {
+            account: name,
+            carddav_home: None,
+            jmap_api_url: None,
+            written: false,
+        };
+
+        #[cfg(feature = "carddav")]
+        if backend.allows_carddav()
+            && let Some(carddav_config) = account_config.carddav.take()
+        {
+            use crate::{
+                account::discovery::DiscoveryCache,
+                carddav::client::{discovery_source, open_carddav_client},
+            };
+
+            let pinned = carddav_config.home.is_some();
+            let source = discovery_source(&carddav_config);
+            let client = open_carddav_client(carddav_config, None)?;
+            let home = client
+                .addressbook_home_set
+                .clone()
+                .ok_or_else(|| anyhow!("CardDAV discovery found no addressbook home-set"))?;
+
+            if self.write
+                && !pinned
+                && let Some(cache) = DiscoveryCache::new(&report.account, "carddav", source)
+            {
+                cache.save(&home)?;
+                report.written = true;
+            }
+
+            report.carddav_home = Some(home.to_string());
+        }
+
+        #[cfg(feature = "jmap")]
+        if backend.allows_jmap()
+            && let Some(jmap_config) = account_config.jmap.take()
+        {
+            use crate::{
+                account::discovery::DiscoveryCache,
+                jmap::client::{discovery_source, open_jmap_client},
+            };
+
+            let source = discovery_source(&jmap_config);
+            let client = open_jmap_client(jmap_config, None)?;
+            let session = client
+                .session()
+                .ok_or_else(|| anyhow!("JMAP session is not available"))?;
+
+            if self.write
+                && let Some(cache) = DiscoveryCache::new(&report.account, "jmap", source)
+            {
+                cache.save(session)?;
+                report.written = true;
+            }
+
+            report.jmap_api_url = Some(session.api_url.to_string());
+        }
+
+        if report.carddav_home.is_none() && report.jmap_api_url.is_none() {
+            bail!(
+                "No CardDAV nor JMAP backend matching `{backend}` is configured for this account"
+            );
+        }
+
+        printer.out(report)
+    }
+}
+
+/// Discovery results of an account.
+#[derive(Clone, Debug, Serialize)]
+pub struct DiscoverReport {
+    pub account: String,
+    /// CardDAV addressbook home-set URL.
+    pub carddav_home: Option<String>,
+    /// JMAP API URL of the session.
+    pub jmap_api_url: Option<String>,
+    /// Whether the results were stored in the discovery cache.
+    pub written: bool,
+}
+
+impl fmt::Display for DiscoverReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Account: {}", self.account)?;
+
+        if let Some(home) = &self.carddav_home {
+            writeln!(f, "  carddav home: {home}")?;
+        }
+
+        if let Some(api_url) = &self.jmap_api_url {
+            writeln!(f, "  jmap api-url: {api_url}")?;
+        }
+
+        if self.written {
+            writeln!(f)?;
+            writeln!(f, "Discovery cache updated.")?;
+        }
+
+        if let Some(home) = &self.carddav_home {
+            writeln!(f)?;
+            writeln!(
+                f,
+                "Pin the CardDAV home-set to skip discovery, in [accounts.{}]:",
+                self.account
+            )?;
+            writeln!(f, "  carddav.home = \"{home}\"")?;
+        }
+
+        Ok(())
+    }
+}
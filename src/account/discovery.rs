@@ -0,0 +1,120 @@
+//! Per-account cache of discovery results: the CardDAV addressbook
+//! home-set and the JMAP session, so that commands skip the DNS and
+//! HTTP round-trips of discovery until the entry expires.
+
+use std::{
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::shared::hash::stable_hash;
+
+/// Lifetime of a discovery entry when the backend config sets no
+/// `discovery-ttl`: one day.
+pub const DEFAULT_DISCOVERY_TTL: u64 = 24 * 60 * 60;
+
+/// Discovery entry of one backend of one account, stored under
+/// `$XDG_CACHE_HOME/cardamum/discovery/`.
+#[derive(Clone, Debug)]
+pub struct DiscoveryCache {
+    path: PathBuf,
+    source: String,
+}
+
+/// On-disk shape of a [`DiscoveryCache`] entry.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Discovered<T> {
+    /// Config the value was resolved from, so that editing it
+    /// invalidates the entry.
+    source: String,
+    /// Unix time the value was resolved at.
+    resolved_at: u64,
+    value: T,
+}
+
+impl DiscoveryCache {
+    /// Entry of the given account and backend. `source` is the config
+    /// the discovery starts from (domain, server URL). `None` when the
+    /// user cache dir cannot be located.
+    pub fn new(account: &str, backend: &str, source: String) -> Option<Self> {
+        let name = format!("{}.json", stable_hash(&[account, backend]));
+
+        let path = dirs::cache_dir()?
+            .join("cardamum")
+            .join("discovery")
+            .join(name);
+
+        Some(Self { path, source })
+    }
+
+    /// The cached value, unless missing, resolved from another source,
+    /// or older than `ttl` seconds. A `ttl` of 0 disables the cache.
+    pub fn load<T: DeserializeOwned>(&self, ttl: u64) -> Option<T> {
+        if ttl == 0 {
+            return None;
+        }
+
+        let bytes = fs::read(&self.path).ok()?;
+        let entry: Discovered<T> = match serde_json::from_slice(&bytes) {
+            Ok(entry) => entry,
+            Err(err) => {
+                debug!(
+                    "ignoring invalid discovery cache `{}`: {err}",
+                    self.path.display()
+                );
+                return None;
+            }
+        };
+
+        if entry.source != self.source || now().saturating_sub(entry.resolved_at) >= ttl {
+            return None;
+        }
+
+        debug!("discovery cache hit: {}", self.path.display());
+        Some(entry.value)
+    }
+
+    /// Stores a freshly resolved value.
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).with_context(|| {
+                format!("Cannot create discovery cache dir `{}`", dir.display())
+            })?;
+        }
+
+        let entry = Discovered {
+            source: self.source.clone(),
+            resolved_at: now(),
+            value,
+        };
+
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&entry)?)
+            .with_context(|| format!("Cannot write discovery cache `{}`", tmp.display()))?;
+        fs::rename(&tmp, &self.path)
+            .with_context(|| format!("Cannot write discovery cache `{}`", self.path.display()))?;
+
+        Ok(())
+    }
+
+    /// Stores a freshly resolved value as [`Self::save`] does, a
+    /// failure being logged rather than failing the command.
+    pub fn save_or_log<T: Serialize>(&self, value: &T) {
+        if let Err(err) = self.save(value) {
+            debug!("cannot cache discovery result: {err:#}");
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
+}
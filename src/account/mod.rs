@@ -1,4 +1,6 @@
 pub mod check;
 pub mod cli;
 pub mod context;
+pub mod discover;
+pub mod discovery;
 pub mod list;
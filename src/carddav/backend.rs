@@ -8,13 +8,15 @@ use io_webdav::{
     rfc4918::send::SendError,
     rfc6352::{addressbook::Addressbook as WireAddressbook, card::CardEntry},
 };
+use log::debug;
 
 use crate::{
     config::CarddavConfig,
+    project::{uuid_v4, vcard_matches},
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
-        client::paginate,
+        card::{Card, CardChanges, CardQuery},
+        client::PageWindow,
     },
 };
 
@@ -26,10 +28,10 @@ pub struct CarddavBackend {
 
 impl CarddavBackend {
     /// Opens the client from the account's `[carddav]` block, running
-    /// discovery when needed (see
+    /// discovery when needed and not cached for `account_name` (see
     /// [`open_carddav_client`](crate::carddav::client::open_carddav_client)).
-    pub fn new(config: CarddavConfig) -> Result<Self> {
-        let inner = crate::carddav::client::open_carddav_client(config)?;
+    pub fn new(config: CarddavConfig, account_name: &str) -> Result<Self> {
+        let inner = crate::carddav::client::open_carddav_client(config, Some(account_name))?;
         Ok(Self { inner })
     }
 
@@ -102,19 +104,67 @@ impl CarddavBackend {
     }
 
     /// Lists the cards inside `addressbook_id`, applying 1-indexed
-    /// pagination.
+    /// pagination. A page is served by listing the card ids alone
+    /// (initial sync-collection) and fetching its cards in one
+    /// addressbook-multiget batch; servers without sync-collection
+    /// fall back to the full addressbook-query. Both paths list cards
+    /// in id order.
     pub fn list_cards(
         &mut self,
         addressbook_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Card>> {
+        let window = PageWindow::new(page, page_size);
+
+        if window.take.is_some() {
+            match self.card_changes(addressbook_id, None) {
+                Ok(changes) => {
+                    // NOTE: sync-collection does not order its hrefs;
+                    // sorting keeps pages stable between calls.
+                    let mut ids = changes.created;
+                    ids.sort();
+
+                    let ids = window.apply(ids);
+                    if ids.is_empty() {
+                        return Ok(Vec::new());
+                    }
+
+                    let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+                    let entries = self.inner.multiget_cards(addressbook_id, &ids)?;
+
+                    let mut cards: Vec<Card> = entries
+                        .into_iter()
+                        .map(|entry| into_card(addressbook_id, entry))
+                        .collect();
+                    cards.sort_by(|a, b| a.id.cmp(&b.id));
+                    return Ok(cards);
+                }
+                Err(err) if is_unsupported_report(&err) => {
+                    debug!("sync-collection unsupported, listing every card: {err:#}")
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
         let entries = self.inner.list_cards(addressbook_id)?;
-        let cards = entries
+        let mut cards: Vec<Card> = entries
             .into_iter()
             .map(|entry| into_card(addressbook_id, entry))
             .collect();
-        Ok(paginate(cards, page, page_size))
+        // NOTE: same order as the sync-collection path, so pages stay
+        // stable whichever path serves them.
+        cards.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(window.apply(cards))
+    }
+
+    /// Lists the cards inside `addressbook_id` matching `query`.
+    /// io-webdav only issues match-all addressbook-query REPORTs, so
+    /// the cards are matched client-side.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let mut cards = self.list_cards(addressbook_id, None, None)?;
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
     }
 
     /// Fetches `card_id` from `addressbook_id`.
@@ -160,6 +210,58 @@ impl CarddavBackend {
         self.inner.delete_card(addressbook_id, card_id, None)?;
         Ok(())
     }
+
+    /// Lists the cards changed since the sync-token `since` (RFC 6578
+    /// sync-collection), draining truncated responses. The feed does
+    /// not tell creates from updates, so changes land in `updated`
+    /// unless `since` is `None` (initial sync).
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        let mut changes = CardChanges::default();
+        let mut token = since.map(str::to_string);
+
+        loop {
+            let delta = self.inner.sync_cards(addressbook_id, token.as_deref())?;
+
+            for change in delta.changed {
+                let Some(id) = href_card_id(&change.href) else {
+                    continue;
+                };
+
+                match since {
+                    None => changes.created.push(id.to_string()),
+                    Some(_) => changes.updated.push(id.to_string()),
+                }
+            }
+
+            changes.deleted.extend(
+                delta
+                    .vanished
+                    .iter()
+                    .filter_map(|href| href_card_id(href))
+                    .map(str::to_string),
+            );
+
+            let next = delta
+                .sync_token
+                .ok_or_else(|| anyhow!("CardDAV sync response is missing the sync token"))?;
+
+            // NOTE: a truncated response that does not move the token
+            // forward would loop forever.
+            let done = !delta.truncated || token.as_deref() == Some(next.as_str());
+            token = Some(next);
+
+            if done {
+                break;
+            }
+        }
+
+        changes.token = token.unwrap_or_default();
+        Ok(changes)
+    }
 }
 
 /// Maps a WebDAV wire addressbook to the shared shape: the display
@@ -175,6 +277,16 @@ fn into_addressbook(wire: WireAddressbook) -> Addressbook {
     }
 }
 
+/// Card id of a sync-collection href: its last path segment. The
+/// collection itself (trailing slash) yields `None`.
+fn href_card_id(href: &str) -> Option<&str> {
+    if href.ends_with('/') {
+        return None;
+    }
+
+    href.rsplit('/').next().filter(|id| !id.is_empty())
+}
+
 /// Maps a WebDAV [`CardEntry`] to a shared [`Card`].
 fn into_card(addressbook_id: &str, entry: CardEntry) -> Card {
     Card {
@@ -185,6 +297,24 @@ fn into_card(addressbook_id: &str, entry: CardEntry) -> Card {
     }
 }
 
+/// Whether `err` is the server turning the sync-collection REPORT
+/// down (RFC 6578 3.2: 403 with the `supported-report` precondition,
+/// or the 400, 405 and 501 of servers not knowing it), rather than an
+/// authentication or a network failure.
+fn is_unsupported_report(err: &anyhow::Error) -> bool {
+    let Some(WebdavClientStdError::Send(SendError::HttpStatus(status, body))) =
+        err.downcast_ref::<WebdavClientStdError>()
+    else {
+        return false;
+    };
+
+    match *status {
+        400 | 405 | 501 => true,
+        403 => body.contains("supported-report"),
+        _ => false,
+    }
+}
+
 /// Adds an actionable hint when a card write is rejected because the
 /// server considers the vCard invalid (the CardDAV `valid-address-data`
 /// precondition, RFC 6352 §6.3.2.1). cardamum forwards the vCard
@@ -237,23 +367,5 @@ fn fresh_card_id() -> Result<String> {
     let mut bytes = [0u8; 16];
     getrandom::fill(&mut bytes).map_err(|err| anyhow::anyhow!("Gather randomness error: {err}"))?;
 
-    // NOTE: RFC 4122 4.4 stamps version 4 and variant 10xx.
-    bytes[6] = (bytes[6] & 0x0f) | 0x40;
-    bytes[8] = (bytes[8] & 0x3f) | 0x80;
-
-    const HEX: &[u8; 16] = b"0123456789abcdef";
-    let mut out = [0u8; 36];
-    let mut cursor = 0;
-    for (i, byte) in bytes.iter().enumerate() {
-        if matches!(i, 4 | 6 | 8 | 10) {
-            out[cursor] = b'-';
-            cursor += 1;
-        }
-        out[cursor] = HEX[(byte >> 4) as usize];
-        out[cursor + 1] = HEX[(byte & 0x0f) as usize];
-        cursor += 2;
-    }
-
-    let uuid = String::from_utf8(out.to_vec()).expect("ASCII hex is always valid UTF-8");
-    Ok(format!("{uuid}.vcf"))
+    Ok(format!("{}.vcf", uuid_v4(bytes)))
 }
@@ -26,12 +26,16 @@ use io_pim_discovery::{
     rfc6764::{client::DiscoveryWebdavClientStd, service::DiscoveryDavService},
 };
 use io_webdav::{client::WebdavClientStd as Inner, rfc4918::WebdavAuth};
+use log::debug;
 use pimalaya_stream::{std::stream::StreamStd, tls::Tls};
 use secrecy::ExposeSecret;
 use url::Url;
 
 use crate::{
-    account::context::Account,
+    account::{
+        context::Account,
+        discovery::{DEFAULT_DISCOVERY_TTL, DiscoveryCache},
+    },
     config::{AccountConfig, CarddavAuthConfig, CarddavConfig, Config, TlsConfig},
 };
 
@@ -80,7 +84,7 @@ pub fn build_carddav_client(
         .take()
         .ok_or_else(|| anyhow!("CardDAV config is missing for account `{name}`"))?;
     let account = Account::from(config).merge(Account::from(account_config));
-    let inner = open_carddav_client(carddav_config)?;
+    let inner = open_carddav_client(carddav_config, Some(&name))?;
     Ok(CarddavClient::new(inner, account))
 }
 
@@ -90,14 +94,71 @@ pub fn build_carddav_client(
 /// `home` skips every discovery step; `server` resolves principal +
 /// addressbook-home-set from the given context root; `discover`
 /// resolves a bare domain to that context root through io-pim-discovery
-/// first.
-pub fn open_carddav_client(config: CarddavConfig) -> Result<Inner> {
+/// first. With an `account_name`, the home-set resolved by the last two
+/// routes is cached for `discovery-ttl` and reused as `home` until it
+/// expires. The cached home-set is probed with a PROPFIND first, and
+/// discovery runs again once when it fails (a moved or removed
+/// home-set); `None` always resolves afresh.
+pub fn open_carddav_client(config: CarddavConfig, account_name: Option<&str>) -> Result<Inner> {
+    let cache = match account_name {
+        Some(name) if config.home.is_none() => {
+            DiscoveryCache::new(name, "carddav", discovery_source(&config))
+        }
+        _ => None,
+    };
+    let ttl = config.discovery_ttl.unwrap_or(DEFAULT_DISCOVERY_TTL);
+
+    if let Some(home) = cache.as_ref().and_then(|cache| cache.load(ttl)) {
+        let mut cached = config.clone();
+        cached.home = Some(home);
+        let probed = connect_carddav_client(cached).and_then(|mut client| {
+            client.list_addressbooks()?;
+            Ok(client)
+        });
+
+        match probed {
+            Ok(client) => return Ok(client),
+            Err(err) => debug!("cached CardDAV home-set unreachable, discovering again: {err:#}"),
+        }
+    }
+
+    let client = connect_carddav_client(config)?;
+
+    if let Some(cache) = cache
+        && ttl > 0
+        && let Some(home) = &client.addressbook_home_set
+    {
+        cache.save_or_log(home);
+    }
+
+    Ok(client)
+}
+
+/// Config a CardDAV discovery starts from, keying its cache entry.
+/// The Basic username is part of it, as principals differ per user.
+pub fn discovery_source(config: &CarddavConfig) -> String {
+    let source = match (&config.server, &config.discover) {
+        (Some(server), _) => format!("server:{server}"),
+        (None, Some(domain)) => format!("discover:{domain}"),
+        (None, None) => String::new(),
+    };
+
+    match &config.auth {
+        CarddavAuthConfig::Basic { username, .. } => format!("{username}@{source}"),
+        CarddavAuthConfig::Bearer { .. } => source,
+    }
+}
+
+/// Runs the route of [`open_carddav_client`] selected by the config,
+/// without the discovery cache.
+fn connect_carddav_client(config: CarddavConfig) -> Result<Inner> {
     let CarddavConfig {
         discover,
         server,
         home,
         tls,
         auth,
+        ..
     } = config;
 
     let tls = tls_with_http_alpn(tls);
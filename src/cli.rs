@@ -1,10 +1,11 @@
 use std::{path::PathBuf, process::exit};
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use clap::{CommandFactory, Parser, Subcommand};
+use log::warn;
 use pimalaya_cli::{
     clap::{
-        args::{AccountFlag, JsonFlag, LogFlags},
+        args::{JsonFlag, LogFlags},
         commands::{CompletionCommand, ManualCommand},
         parsers::path_parser,
     },
@@ -29,7 +30,10 @@ use crate::{
     backend::Backend,
     config::{AccountConfig, Config},
     shared::{
-        addressbook::cli::AddressbookCommand, card::cli::CardCommand, client::AddressbookClient,
+        addressbook::cli::AddressbookCommand,
+        card::cli::{CACHED_ONLY, CardCommand},
+        client::AddressbookClient,
+        sync::command::SyncCommand,
     },
     wizard,
 };
@@ -58,13 +62,26 @@ pub struct Cli {
     #[arg(short, long = "config", global = true, env = "CARDAMUM_CONFIG")]
     #[arg(value_name = "PATH", value_parser = path_parser, value_delimiter = ':')]
     pub config_paths: Vec<PathBuf>,
-    #[command(flatten)]
-    pub account: AccountFlag,
+    /// Override the default account.
+    ///
+    /// Repeatable for the shared read commands (`addressbook list`,
+    /// `card list`, `card search` and `card export`), which then run
+    /// over every given account and tag each row with the account and
+    /// backend it comes from. Every other command takes a single
+    /// account.
+    #[arg(short, long = "account", global = true, value_name = "NAME")]
+    pub accounts: Vec<String>,
+    /// Run the shared read commands over every configured account.
+    ///
+    /// Same as repeating `-a` for each account of the config, in
+    /// account name order.
+    #[arg(long, global = true, conflicts_with = "accounts")]
+    pub all_accounts: bool,
     /// Force a specific backend for cross-protocol commands.
     ///
-    /// Only consumed by the shared commands (addressbook, card); the
-    /// protocol-specific subcommands (vdir, carddav) ignore it and
-    /// always use their own backend.
+    /// Only consumed by the shared commands (addressbook, card,
+    /// sync); the protocol-specific subcommands (vdir, carddav) ignore
+    /// it and always use their own backend.
     ///
     /// Possible values: auto (default), carddav, jmap, msgraph,
     /// google, vdir. With auto, the shared command picks the first
@@ -74,6 +91,15 @@ pub struct Cli {
     /// it).
     #[arg(short, long, global = true, default_value_t)]
     pub backend: Backend,
+    /// Serve card reads from the on-disk card cache only.
+    ///
+    /// Only consumed by the card commands: `card list`, `card read`
+    /// and `card search` read the cards last cached from the remote
+    /// backend without reaching it, every other card command bails.
+    /// The cache is filled by the card commands of an account with
+    /// `card.cache = true`.
+    #[arg(long, global = true)]
+    pub cached: bool,
     #[command(flatten)]
     pub json: JsonFlag,
     #[command(flatten)]
@@ -88,6 +114,7 @@ pub enum Command {
     Addressbook(AddressbookCommand),
     #[command(subcommand, alias = "cards")]
     Card(CardCommand),
+    Sync(SyncCommand),
 
     // --- Protocol-specific APIs
     //
@@ -149,29 +176,131 @@ pub fn resolve_account(
     Ok((config, name, account_config))
 }
 
+/// Accounts selected by the global `-a/--account` flags and
+/// `--all-accounts`.
+#[derive(Clone, Debug, Default)]
+pub struct AccountSelection {
+    pub names: Vec<String>,
+    pub all: bool,
+}
+
+impl AccountSelection {
+    /// Whether the selection spans several accounts.
+    pub fn is_many(&self) -> bool {
+        self.all || self.names.len() > 1
+    }
+
+    /// The account of the single-account commands: the one given with
+    /// `-a`, `None` for the default one.
+    pub fn name(&self) -> Option<&str> {
+        self.names.first().map(String::as_str)
+    }
+}
+
+/// Opens one shared client per selected account, in `-a` order, or in
+/// account name order with `--all-accounts`. An account that cannot be
+/// opened is skipped with a warning, so that it does not hide the
+/// others; none opening is an error. With `Some(offline)` as
+/// `cached`, the clients are the ones of the card commands (see
+/// [`AddressbookClient::new_cached`]).
+pub fn open_clients(
+    printer: &mut impl Printer,
+    config_paths: &[PathBuf],
+    accounts: &AccountSelection,
+    backend: Backend,
+    cached: Option<bool>,
+) -> Result<Vec<AddressbookClient>> {
+    let names = if accounts.all {
+        let Some(config) = Config::from_paths_or_default(config_paths)? else {
+            bail!(
+                "No configuration found. Run bare `cardamum` to launch the wizard and generate one."
+            );
+        };
+        let mut names: Vec<String> = config.accounts.into_keys().collect();
+        names.sort();
+        names
+    } else {
+        accounts.names.clone()
+    };
+
+    if names.is_empty() {
+        bail!("No account found in the configuration");
+    }
+
+    let mut clients = Vec::with_capacity(names.len());
+
+    for name in names {
+        let client = resolve_account(printer, config_paths, Some(&name)).and_then(
+            |(config, name, account_config)| match cached {
+                Some(offline) => {
+                    AddressbookClient::new_cached(config, &name, account_config, backend, offline)
+                }
+                None => AddressbookClient::new(config, &name, account_config, backend),
+            },
+        );
+
+        match client {
+            Ok(client) => clients.push(client),
+            Err(err) => warn!("cannot open account `{name}`, skipping it: {err:#}"),
+        }
+    }
+
+    if clients.is_empty() {
+        bail!("Cannot open any of the selected accounts");
+    }
+
+    Ok(clients)
+}
+
 impl Command {
     pub fn execute(
         self,
         printer: &mut impl Printer,
         config_paths: &[PathBuf],
-        account_name: Option<&str>,
+        accounts: &AccountSelection,
         backend: Backend,
+        cached: bool,
+        json: bool,
     ) -> Result<()> {
+        if accounts.is_many() {
+            return match self {
+                Self::Addressbook(cmd) => {
+                    cmd.execute_many(printer, config_paths, accounts, backend)
+                }
+                Self::Card(cmd) => {
+                    cmd.execute_many(printer, config_paths, accounts, backend, cached, json)
+                }
+                _ => bail!(
+                    "Only `addressbook list`, `card list`, `card search` and `card export` run over several accounts"
+                ),
+            };
+        }
+
+        let account_name = accounts.name();
+
         match self {
             // --- Shared API
             //
             Self::Addressbook(cmd) => {
-                let (config, _name, account_config) =
+                let (config, name, account_config) =
                     resolve_account(printer, config_paths, account_name)?;
-                let client = AddressbookClient::new(config, account_config, backend)?;
-                cmd.execute(printer, client)
+                let client = AddressbookClient::new(config, &name, account_config, backend)?;
+                cmd.execute(printer, config_paths, name, client)
             }
             Self::Card(cmd) => {
-                let (config, _name, account_config) =
+                if cached && !cmd.reads_cache() {
+                    bail!(CACHED_ONLY);
+                }
+                let (config, name, account_config) =
                     resolve_account(printer, config_paths, account_name)?;
-                let client = AddressbookClient::new(config, account_config, backend)?;
-                cmd.execute(printer, client)
+                let client = if cmd.reads_cache() {
+                    AddressbookClient::new_cached(config, &name, account_config, backend, cached)?
+                } else {
+                    AddressbookClient::new(config, &name, account_config, backend)?
+                };
+                cmd.execute(printer, client, json)
             }
+            Self::Sync(cmd) => cmd.execute(printer, config_paths, account_name, backend),
 
             // --- Protocol-specific APIs
             //
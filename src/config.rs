@@ -33,6 +33,10 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "carddav")]
 use url::Url;
 
+#[cfg(any(feature = "vdir", feature = "carddav"))]
+use crate::shared::card::VcardVersion;
+use crate::shared::card::{CardColumn, CardSort};
+
 /// Global configuration.
 ///
 /// Represents the whole TOML user's configuration file.
@@ -111,6 +115,9 @@ pub struct VdirConfig {
     /// immediate subdirectory is one addressbook.
     #[serde(deserialize_with = "shell_expanded_string")]
     pub home_dir: String,
+    /// vCard version cards are converted to before being written
+    /// (`"3.0"` or `"4.0"`). Unset, cards are written as given.
+    pub vcard_version: Option<VcardVersion>,
 }
 
 /// CardDAV configuration.
@@ -121,8 +128,9 @@ pub struct CarddavConfig {
     /// Bare domain resolved to a server URL at runtime: PACC first,
     /// then RFC 6764 (SRV record, its TXT `path`, then `.well-known`);
     /// Google domains use an authenticated `.well-known` probe.
-    /// Convenient but adds DNS + HTTP round-trips on every run; `server`
-    /// or `home` win when set and skip this.
+    /// Convenient but adds DNS + HTTP round-trips, only saved while
+    /// the discovery cache holds; `server` or `home` win when set and
+    /// skip this.
     pub discover: Option<String>,
     /// DAV context root. Principal + addressbook-home-set discovery
     /// start from this URL; the `.well-known` step is skipped. Accepts
@@ -130,8 +138,17 @@ pub struct CarddavConfig {
     /// default to `https://`.
     pub server: Option<String>,
     /// Pre-resolved addressbook home-set URL. Skips every discovery
-    /// step; the client lists addressbooks at this URL.
+    /// step; the client lists addressbooks at this URL. `account
+    /// discover` prints the one `discover` or `server` resolve to.
     pub home: Option<Url>,
+    /// Seconds the home-set resolved from `discover` or `server` is
+    /// cached for, per account. Defaults to one day; 0 disables the
+    /// cache.
+    pub discovery_ttl: Option<u64>,
+    /// vCard version cards are converted to before being written
+    /// (`"3.0"` or `"4.0"`), for servers that reject the other one.
+    /// Unset, cards are written as given.
+    pub vcard_version: Option<VcardVersion>,
 
     /// TLS configuration.
     #[serde(default)]
@@ -181,6 +198,10 @@ pub struct JmapConfig {
     #[serde(default = "io_jmap::client::JmapClientStd::default_alpn")]
     pub alpn: Vec<String>,
 
+    /// Seconds the JMAP session is cached for, per account. Defaults
+    /// to one day; 0 disables the cache.
+    pub discovery_ttl: Option<u64>,
+
     /// Authentication configuration.
     pub auth: JmapAuthConfig,
 }
@@ -305,10 +326,17 @@ pub struct AddressbookListTableConfig {
     pub color_color: Option<Color>,
 }
 
-/// Card-level rendering options.
+/// Card-level options.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
 pub struct CardConfig {
+    /// Whether `card` commands go through the on-disk cache of remote
+    /// addressbooks. Defaults to `false`.
+    pub cache: Option<bool>,
+    /// Whether backend contact groups (Google contact groups, CardDAV
+    /// and vdir group cards) round-trip through the CATEGORIES of the
+    /// cards. Defaults to `false`.
+    pub groups: Option<bool>,
     #[serde(default)]
     pub list: CardListConfig,
 }
@@ -320,6 +348,10 @@ pub struct CardListConfig {
     /// wins when passed; otherwise the merged account/global value
     /// wins; otherwise the hard fallback (25) is used.
     pub page_size: Option<u32>,
+    /// Default `--columns` value for `card list` and `card search`.
+    pub columns: Option<Vec<CardColumn>>,
+    /// Default `--sort` value for `card list` and `card search`.
+    pub sort: Option<CardSort>,
     #[serde(default)]
     pub table: CardListTableConfig,
 }
@@ -7,9 +7,13 @@
 //! (the group every contact belongs to) is surfaced first as Contacts,
 //! then the user's own groups. Memberships are m:n labels, so one card
 //! can appear under several books; the shared API narrows each listing
-//! to the requested group.
+//! to the requested group. With `card.groups`, the user groups of a
+//! contact also read as its CATEGORIES, and written CATEGORIES become
+//! memberships.
 
-use anyhow::{Error, Result, bail};
+use std::collections::HashMap;
+
+use anyhow::{Error, Result, anyhow, bail};
 use io_people::v1::{
     client::{PeopleClientStd, PeopleClientStdConnectOptions},
     rest::{
@@ -24,10 +28,11 @@ use secrecy::ExposeSecret;
 use crate::{
     config::GoogleConfig,
     google::project,
+    project::{VcardPhoto, set_categories, vcard_categories, vcard_matches, vcard_photo},
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
-        client::paginate,
+        card::{Card, CardChanges, CardQuery},
+        client::PageWindow,
     },
 };
 
@@ -35,9 +40,25 @@ use crate::{
 /// every Google contact belongs to.
 pub const MY_CONTACTS_GROUP: &str = "myContacts";
 
+/// Largest page `connections.list` serves.
+const CONNECTIONS_PAGE_SIZE: u32 = 1000;
+
+/// Member resource names requested along with a contact group, above
+/// any group size the People API allows.
+const GROUP_MAX_MEMBERS: u32 = 100_000;
+
+/// Largest number of persons `people:batchGet` serves at once.
+const BATCH_GET_SIZE: usize = 200;
+
 /// Google People backend of the shared-API client.
 pub struct GoogleBackend {
     pub inner: PeopleClientStd,
+    /// Whether user contact groups round-trip through the CATEGORIES
+    /// of the cards, from the `card.groups` config.
+    pub groups: bool,
+    /// Names of the user contact groups by id, listed once per client
+    /// when [`Self::groups`] is set.
+    group_names: Option<HashMap<String, String>>,
 }
 
 impl GoogleBackend {
@@ -48,7 +69,11 @@ impl GoogleBackend {
             tls: config.tls.into_tls(config.alpn),
         };
         let inner = PeopleClientStd::connect(token.expose_secret(), options)?;
-        Ok(Self { inner })
+        Ok(Self {
+            inner,
+            groups: false,
+            group_names: None,
+        })
     }
 
     /// Lists the account's contact groups as addressbooks: the
@@ -177,19 +202,30 @@ impl GoogleBackend {
     }
 
     /// Lists the contacts of the group, each projected onto a vCard
-    /// document, applying 1-indexed pagination.
+    /// document, applying 1-indexed pagination. myContacts (every
+    /// connection) follows the connection pages only until the
+    /// requested page is covered; a user group reads its member
+    /// resource names (`contactGroups.get`) and fetches the page of
+    /// them (`people:batchGet`), without scanning the connections.
     pub fn list_cards(
         &mut self,
         addressbook_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Card>> {
+        let window = PageWindow::new(page, page_size);
+
+        if addressbook_id != MY_CONTACTS_GROUP {
+            return self.list_group_cards(addressbook_id, window);
+        }
+
+        let groups = self.categories_groups()?;
         let mut cards = Vec::new();
         let mut page_token: Option<String> = None;
 
         loop {
             let params = PeopleConnectionsListParams {
-                page_size: Some(100),
+                page_size: Some(window.batch(cards.len(), CONNECTIONS_PAGE_SIZE)),
                 page_token: page_token.as_deref(),
                 ..Default::default()
             };
@@ -198,21 +234,70 @@ impl GoogleBackend {
                 .connections_list(project::READ_FIELDS, &params)?
                 .response;
 
+            // NOTE: connections also hold the contacts taken out of
+            // myContacts (left only in a user group), which this
+            // addressbook must not list.
             cards.extend(
                 current
                     .connections
                     .into_iter()
                     .filter(|person| in_group(person, addressbook_id))
-                    .map(|person| into_card(addressbook_id, person)),
+                    .map(|person| into_card(addressbook_id, person, groups.as_ref())),
             );
 
+            if window.is_covered(cards.len()) {
+                break;
+            }
+
             match current.next_page_token {
                 Some(next) => page_token = Some(next),
                 None => break,
             }
         }
 
-        Ok(paginate(cards, page, page_size))
+        Ok(window.apply(cards))
+    }
+
+    /// Lists the window of the members of the user group `id`.
+    fn list_group_cards(&mut self, id: &str, window: PageWindow) -> Result<Vec<Card>> {
+        let groups = self.categories_groups()?;
+        let group = self
+            .inner
+            .contact_group_get(&format!("contactGroups/{id}"), Some(GROUP_MAX_MEMBERS), &[])?
+            .response;
+
+        let members = window.apply(group.member_resource_names);
+        let mut cards = Vec::with_capacity(members.len());
+
+        for chunk in members.chunks(BATCH_GET_SIZE) {
+            let batch = self
+                .inner
+                .people_get_batch(chunk, project::READ_FIELDS, &[])?
+                .response;
+
+            // NOTE: members deleted in the meantime come back without
+            // a person.
+            cards.extend(
+                batch
+                    .responses
+                    .into_iter()
+                    .filter_map(|response| response.person)
+                    .map(|person| into_card(id, person, groups.as_ref())),
+            );
+        }
+
+        Ok(cards)
+    }
+
+    /// Lists the contacts of the group matching `query`. Google's
+    /// `searchContacts` only prefix-matches and serves at most 30
+    /// results, so it cannot narrow the substring matching of the
+    /// shared API: the group is listed, then the projected vCards are
+    /// matched against every criterion.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let mut cards = self.list_cards(addressbook_id, None, None)?;
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
     }
 
     /// Reads the contact `card_id`, projected onto a vCard document.
@@ -221,16 +306,20 @@ impl GoogleBackend {
             .inner
             .person_get(&format!("people/{card_id}"), project::READ_FIELDS, &[])?
             .response;
+        let groups = self.categories_groups()?;
 
-        Ok(into_card(addressbook_id, person))
+        Ok(into_card(addressbook_id, person, groups.as_ref()))
     }
 
     /// Creates the vCard as a People contact; creates always land in
     /// myContacts, so a user group target adds the membership right
-    /// after. Returns the server-assigned id.
+    /// after. An inline PHOTO is uploaded as the contact photo. With
+    /// [`Self::groups`], the CATEGORIES become memberships. Returns the
+    /// server-assigned id.
     pub fn create_card(&mut self, addressbook_id: &str, contents: Vec<u8>) -> Result<String> {
         let vcard = into_vcard_text(contents)?;
-        let person = project::to_person(&vcard).map_err(Error::msg)?;
+        let (categories, stripped) = self.take_categories(&vcard);
+        let person = project::to_person(&stripped).map_err(Error::msg)?;
 
         let created = self
             .inner
@@ -238,22 +327,20 @@ impl GoogleBackend {
             .response;
         let id = project::person_id(&created.resource_name).to_string();
 
+        if let Some(VcardPhoto::Inline { bytes, .. }) = vcard_photo(&vcard) {
+            self.inner
+                .contact_photo_update(&created.resource_name, &bytes)?;
+        }
+
+        let mut current = Vec::new();
+
         if addressbook_id != MY_CONTACTS_GROUP {
-            let modified = self
-                .inner
-                .contact_group_members_modify(
-                    &format!("contactGroups/{addressbook_id}"),
-                    &[created.resource_name],
-                    &[],
-                )?
-                .response;
+            self.modify_membership(addressbook_id, &created.resource_name, true)?;
+            current.push(addressbook_id.to_string());
+        }
 
-            if !modified.not_found_resource_names.is_empty() {
-                bail!(
-                    "Google group member add rejected: {:?} not found",
-                    modified.not_found_resource_names
-                );
-            }
+        if let Some(categories) = categories {
+            self.sync_memberships(&created.resource_name, &current, &categories, false)?;
         }
 
         Ok(id)
@@ -264,7 +351,11 @@ impl GoogleBackend {
     /// changed fields. People requires the person's current etag on
     /// updates; `if_match` supplies it, otherwise the fetched one is
     /// used. A stash write (clientData in the mask) merges the server's
-    /// foreign clientData entries under the same guard.
+    /// foreign clientData entries under the same guard. Photos go
+    /// through their own endpoints: an inline PHOTO is uploaded, and
+    /// the contact photo is deleted when the vCard no longer carries
+    /// one. With [`Self::groups`], the user group memberships follow
+    /// the CATEGORIES.
     pub fn update_card(
         &mut self,
         _addressbook_id: &str,
@@ -275,23 +366,158 @@ impl GoogleBackend {
         let vcard = into_vcard_text(contents)?;
         let resource_name = format!("people/{card_id}");
 
-        let mut person = project::to_person(&vcard).map_err(Error::msg)?;
+        let (categories, stripped) = self.take_categories(&vcard);
+        let mut person = project::to_person(&stripped).map_err(Error::msg)?;
         person.resource_name = resource_name.clone();
 
         let current = self
             .inner
             .person_get(&resource_name, project::READ_FIELDS, &[])?
             .response;
+        let had_photo = project::user_photo(&current).is_some();
+        let memberships: Vec<String> = group_ids(&current).map(str::to_string).collect();
 
         let base = project::to_vcard(&current);
         let base_person = project::to_person(&base).map_err(Error::msg)?;
         let fields = project::changed_fields(&person, &base_person);
-        if fields.is_empty() {
-            // NOTE: nothing differs from the server state, no request
-            // to send.
-            return Ok(());
+        if !fields.is_empty() {
+            self.update_person(person, current, &fields, if_match)?;
+        }
+
+        // NOTE: an unchanged photo reads back as its Google URL, which
+        // calls for no request either way.
+        match vcard_photo(&vcard) {
+            Some(VcardPhoto::Inline { bytes, .. }) => {
+                self.inner.contact_photo_update(&resource_name, &bytes)?;
+            }
+            None if had_photo => {
+                self.inner.contact_photo_delete(&resource_name)?;
+            }
+            _ => {}
+        }
+
+        if let Some(categories) = categories {
+            self.sync_memberships(&resource_name, &memberships, &categories, true)?;
+        }
+
+        Ok(())
+    }
+
+    /// Splits the CATEGORIES off the vCard when [`Self::groups`] is
+    /// set, for them to become memberships rather than stashed
+    /// properties.
+    fn take_categories(&self, vcard: &str) -> (Option<Vec<String>>, String) {
+        if !self.groups {
+            return (None, vcard.to_string());
         }
 
+        let categories = vcard_categories(vcard);
+        (Some(categories), set_categories(vcard, &[]))
+    }
+
+    /// Names of the user contact groups by id when [`Self::groups`] is
+    /// set, for [`into_card`] to read memberships as CATEGORIES.
+    fn categories_groups(&mut self) -> Result<Option<HashMap<String, String>>> {
+        if !self.groups {
+            return Ok(None);
+        }
+
+        if self.group_names.is_none() {
+            let names = self
+                .list_addressbooks()?
+                .into_iter()
+                .filter(|book| book.id != MY_CONTACTS_GROUP)
+                .map(|book| (book.id, book.name))
+                .collect();
+            self.group_names = Some(names);
+        }
+
+        Ok(self.group_names.clone())
+    }
+
+    /// Brings the user group memberships of the contact `resource_name`
+    /// in step with its CATEGORIES: the groups named by a category
+    /// (case insensitively) gain the contact, missing ones are created
+    /// first, and with `remove` the other user groups of `current`
+    /// lose it. myContacts and the system groups are left alone.
+    fn sync_memberships(
+        &mut self,
+        resource_name: &str,
+        current: &[String],
+        categories: &[String],
+        remove: bool,
+    ) -> Result<()> {
+        let groups = self.categories_groups()?.unwrap_or_default();
+        let mut wanted = Vec::new();
+
+        for category in categories {
+            let known = groups
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(category));
+
+            let id = match known {
+                Some((id, _)) => id.clone(),
+                None => {
+                    // NOTE: the cached names miss the new group, so
+                    // they are listed again on next use.
+                    self.group_names = None;
+                    self.create_addressbook(category, None, None)?
+                }
+            };
+
+            if !wanted.contains(&id) {
+                wanted.push(id);
+            }
+        }
+
+        for id in wanted.iter().filter(|id| !current.contains(*id)) {
+            self.modify_membership(id, resource_name, true)?;
+        }
+
+        if remove {
+            let dropped = current
+                .iter()
+                .filter(|id| groups.contains_key(*id) && !wanted.contains(*id));
+
+            for id in dropped {
+                self.modify_membership(id, resource_name, false)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the contact `resource_name` to the contact group `id`, or
+    /// removes it.
+    fn modify_membership(&mut self, id: &str, resource_name: &str, add: bool) -> Result<()> {
+        let member = [resource_name.to_string()];
+        let (added, removed): (&[String], &[String]) =
+            if add { (&member, &[]) } else { (&[], &member) };
+
+        let modified = self
+            .inner
+            .contact_group_members_modify(&format!("contactGroups/{id}"), added, removed)?
+            .response;
+
+        if !modified.not_found_resource_names.is_empty() {
+            bail!(
+                "Google group member update rejected: {:?} not found",
+                modified.not_found_resource_names
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Sends the masked update of the `fields` of `person`, `current`
+    /// being the server person it derives from.
+    fn update_person(
+        &mut self,
+        mut person: PeoplePerson,
+        current: PeoplePerson,
+        fields: &[PeoplePersonField],
+        if_match: Option<&str>,
+    ) -> Result<()> {
         // NOTE: a masked update replaces the whole clientData list and
         // other clients may own entries there, so a stash write merges
         // the server's foreign entries first (the etag guard turns a
@@ -312,7 +538,7 @@ impl GoogleBackend {
         };
 
         self.inner
-            .contact_update(&person, &fields, project::READ_FIELDS, &[])?;
+            .contact_update(&person, fields, project::READ_FIELDS, &[])?;
 
         Ok(())
     }
@@ -322,10 +548,80 @@ impl GoogleBackend {
         self.inner.contact_delete(&format!("people/{card_id}"))?;
         Ok(())
     }
+
+    /// Takes the contact `card_id` out of the group `addressbook_id`:
+    /// a user group only loses the membership, the contact staying in
+    /// myContacts, while myContacts deletes the contact.
+    pub fn detach_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<()> {
+        if addressbook_id == MY_CONTACTS_GROUP {
+            return self.delete_card(addressbook_id, card_id);
+        }
+
+        self.modify_membership(addressbook_id, &format!("people/{card_id}"), false)
+    }
+
+    /// Lists the contacts changed since the sync token `since`
+    /// (`connections.list` with `requestSyncToken`), draining every
+    /// page. Sync tokens are account-wide: deleted persons and persons
+    /// no longer in the group are reported as deleted. The feed does not
+    /// tell creates from updates, so changes land in `updated` unless
+    /// `since` is `None` (initial sync).
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        let mut changes = CardChanges::default();
+        let mut page_token: Option<String> = None;
+
+        let token = loop {
+            let params = PeopleConnectionsListParams {
+                page_size: Some(100),
+                page_token: page_token.as_deref(),
+                sync_token: since,
+                request_sync_token: true,
+                ..Default::default()
+            };
+            let current = self
+                .inner
+                .connections_list(project::READ_FIELDS, &params)?
+                .response;
+
+            for person in current.connections {
+                let id = project::person_id(&person.resource_name).to_string();
+                let deleted = person.metadata.as_ref().and_then(|m| m.deleted) == Some(true);
+
+                if deleted || !in_group(&person, addressbook_id) {
+                    // NOTE: the initial feed lists live contacts only,
+                    // the ones outside the group are just skipped.
+                    if since.is_some() {
+                        changes.deleted.push(id);
+                    }
+                } else if since.is_none() {
+                    changes.created.push(id);
+                } else {
+                    changes.updated.push(id);
+                }
+            }
+
+            match current.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => break current.next_sync_token,
+            }
+        };
+
+        changes.token = token.ok_or_else(|| anyhow!("People API returned no sync token"))?;
+        Ok(changes)
+    }
 }
 
 /// Whether the person is a member of the contact group `id`.
 fn in_group(person: &PeoplePerson, id: &str) -> bool {
+    group_ids(person).any(|group| group == id)
+}
+
+/// Ids of the contact groups the person is a member of.
+fn group_ids(person: &PeoplePerson) -> impl Iterator<Item = &str> {
     person
         .memberships
         .iter()
@@ -337,14 +633,37 @@ fn in_group(person: &PeoplePerson, id: &str) -> bool {
                 .map(group_id)
                 .or(group.contact_group_id.as_deref())
         })
-        .any(|group| group == id)
 }
 
 /// io-people person to the shared card shape: the projected
 /// vCard document as contents, the person id as id and the person etag
-/// as ETag.
-fn into_card(addressbook_id: &str, person: PeoplePerson) -> Card {
-    let vcard = project::to_vcard(&person);
+/// as ETag. With user group names, the groups of the person join the
+/// CATEGORIES of the vCard.
+fn into_card(
+    addressbook_id: &str,
+    person: PeoplePerson,
+    groups: Option<&HashMap<String, String>>,
+) -> Card {
+    let mut vcard = project::to_vcard(&person);
+
+    if let Some(groups) = groups {
+        let mut categories = vcard_categories(&vcard);
+        let known = categories.len();
+
+        for name in group_ids(&person).filter_map(|id| groups.get(id)) {
+            if !categories
+                .iter()
+                .any(|category| category.eq_ignore_ascii_case(name))
+            {
+                categories.push(name.clone());
+            }
+        }
+
+        if categories.len() > known {
+            vcard = set_categories(&vcard, &categories);
+        }
+    }
+
     let etag = (!person.etag.is_empty()).then(|| person.etag.clone());
 
     Card {
@@ -7,19 +7,23 @@
 //! and [`to_person`] projects a vCard back onto a person. Per the
 //! custom property policy of docs/contacts-mapping.md only fields with
 //! a well-defined vCard slot are projected; People-only fields
-//! (fileAses, memberships, events, photos, ...) stay out of the managed
-//! set ([`READ_FIELDS`]), out of every update mask, and survive updates
+//! (fileAses, memberships, ...) stay out of the managed set
+//! ([`READ_FIELDS`]), out of every update mask, and survive updates
 //! untouched. Unlike Graph's fixed slots, People fields are true
-//! lists, so every vCard property projects without truncation.
+//! lists, so every vCard property projects without truncation. The
+//! contact photo reads as a PHOTO URL and writes through the dedicated
+//! photo endpoints (see [`user_photo`]), never through the mask.
+//! Birthdays and events may be year-less: they read as the vCard 4.0
+//! `--MMDD`, and events as ANNIVERSARY or Apple `X-ABDATE` lines.
 
 use core::str::FromStr;
 use std::borrow::Cow;
 
 use io_people::v1::rest::people::{
     PeopleAddress, PeopleBiography, PeopleBirthday, PeopleClientData, PeopleContentType,
-    PeopleDate, PeopleEmailAddress, PeopleImClient, PeopleName, PeopleNickname, PeopleOccupation,
-    PeopleOrganization, PeoplePerson, PeoplePersonField, PeoplePhoneNumber, PeopleRelation,
-    PeopleUrl,
+    PeopleDate, PeopleEmailAddress, PeopleEvent, PeopleImClient, PeopleName, PeopleNickname,
+    PeopleOccupation, PeopleOrganization, PeoplePerson, PeoplePersonField, PeoplePhoneNumber,
+    PeopleRelation, PeopleUrl,
 };
 use vcard::{
     param::VcardParam,
@@ -43,7 +47,12 @@ use vcard::{
     },
 };
 
-use crate::project::{MAX_STASH_LINE, escape_text, full_date, splice_props, text_prop};
+use url::Url;
+
+use crate::project::{
+    CardDateKind, MAX_STASH_LINE, VcardDate, VcardPhoto, escape_text, parse_photo, splice_props,
+    take_dates, text_prop,
+};
 
 /// Person fields the projection reads: the managed set plus the
 /// Google-scoped fields minted as X-GOOGLE-* properties, read-only
@@ -54,6 +63,7 @@ pub const READ_FIELDS: &[PeoplePersonField] = &[
     PeoplePersonField::Birthdays,
     PeoplePersonField::ClientData,
     PeoplePersonField::EmailAddresses,
+    PeoplePersonField::Events,
     PeoplePersonField::ExternalIds,
     PeoplePersonField::ImClients,
     PeoplePersonField::Locations,
@@ -64,6 +74,7 @@ pub const READ_FIELDS: &[PeoplePersonField] = &[
     PeoplePersonField::Occupations,
     PeoplePersonField::Organizations,
     PeoplePersonField::PhoneNumbers,
+    PeoplePersonField::Photos,
     PeoplePersonField::Relations,
     PeoplePersonField::Urls,
 ];
@@ -211,19 +222,25 @@ pub fn to_vcard(person: &PeoplePerson) -> String {
         }
     }
 
-    // NOTE: People dates can be partial (year-less birthdays); only a
-    // full date has a portable vCard slot, see project::full_date.
-    if let Some(date) = person.birthdays.first().and_then(|birthday| {
-        let date = birthday.date.as_ref()?;
-        Some(format!(
-            "{:04}-{:02}-{:02}",
-            date.year?, date.month?, date.day?
-        ))
-    }) {
+    if let Some(url) = user_photo(person) {
+        card.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Photo),
+            params: vec![],
+            value: VcardValue::Uri(VcardUri(Cow::Owned(url.to_string()))),
+        });
+    }
+
+    // NOTE: People dates can be partial; a year-less birthday reads as
+    // the vCard 4.0 `--MMDD`.
+    if let Some(date) = person
+        .birthdays
+        .first()
+        .and_then(|birthday| vcard_date(birthday.date.as_ref()?))
+    {
         card.push(VcardProp {
             name: VcardPropName::Kind(VcardPropKind::Bday),
             params: vec![],
-            value: VcardValue::DateAndOrTime(VcardDateAndOrTime(Cow::Owned(date))),
+            value: VcardValue::DateAndOrTime(VcardDateAndOrTime(Cow::Owned(date.to_string()))),
         });
     }
 
@@ -254,7 +271,8 @@ pub fn to_vcard(person: &PeoplePerson) -> String {
 
     let vcard = String::from_utf8_lossy(&card.to_bytes()).into_owned();
 
-    let mut extra = minted_props(person);
+    let mut extra = event_lines(person);
+    extra.extend(minted_props(person));
     extra.extend(stash_lines(person));
     splice_props(vcard, &extra)
 }
@@ -265,14 +283,17 @@ pub fn to_vcard(person: &PeoplePerson) -> String {
 /// update), while unmanaged People fields stay out of the body. Every
 /// line that does not project (unknown and X-* properties, standard
 /// properties without a People slot, values past a single-instance
-/// slot, partial birthdays) is stashed verbatim into the cardamum
+/// slot, unparsable dates) is stashed verbatim into the cardamum
 /// clientData entry, so it survives on Google and restores on read.
 /// The UID is not read back (the resource name addresses the person
 /// through the request path, filled by the caller) and the minted
 /// X-GOOGLE-* properties are consumed, the server value being
 /// authoritative.
 pub fn to_person(vcard: &str) -> Result<PeoplePerson, String> {
-    let card = VcardCst::parse(vcard).map_err(|err| format!("Invalid vCard: {err}"))?;
+    // NOTE: dates are taken out first, Apple X-ABDATE lines pairing
+    // with their X-ABLabel; the CST only sees unparsable ones.
+    let (dates, vcard) = take_dates(vcard);
+    let card = VcardCst::parse(&vcard).map_err(|err| format!("Invalid vCard: {err}"))?;
     let version = card.version();
 
     let mut person = PeoplePerson::default();
@@ -458,26 +479,9 @@ pub fn to_person(vcard: &str) -> Result<PeoplePerson, String> {
                     false
                 }
             }
-            Ok(VcardPropKind::Bday) => {
-                // NOTE: a partial (year-less) birthday has no People
-                // date it round-trips through; it lands in the stash.
-                if person.birthdays.is_empty()
-                    && let Some(date) = full_date(&line.raw_value_str())
-                {
-                    let mut parts = date.split('-').map(|part| part.parse().ok());
-                    person.birthdays.push(PeopleBirthday {
-                        date: Some(PeopleDate {
-                            year: parts.next().flatten(),
-                            month: parts.next().flatten(),
-                            day: parts.next().flatten(),
-                        }),
-                        ..Default::default()
-                    });
-                    true
-                } else {
-                    false
-                }
-            }
+            // NOTE: a parsable BDAY was taken out with the other
+            // dates; this one lands in the stash.
+            Ok(VcardPropKind::Bday) => false,
             Ok(VcardPropKind::Note) => {
                 let note = NOTE::decode(line, version);
                 let note = note.0.trim();
@@ -488,6 +492,16 @@ pub fn to_person(vcard: &str) -> Result<PeoplePerson, String> {
                     false
                 }
             }
+            Ok(VcardPropKind::Photo) => {
+                // NOTE: an inline picture is uploaded by the backend
+                // and a Google-hosted URL is the one served on read;
+                // any other URL has no People slot and is stashed.
+                match parse_photo(&line.to_string()) {
+                    Some(VcardPhoto::Inline { .. }) => true,
+                    Some(VcardPhoto::Uri(uri)) => is_google_photo(&uri),
+                    None => false,
+                }
+            }
             Ok(VcardPropKind::Related) => {
                 // NOTE: only free-form spouse and child names
                 // (VALUE=text) project; other types and URI RELATED
@@ -536,6 +550,40 @@ pub fn to_person(vcard: &str) -> Result<PeoplePerson, String> {
         }
     }
 
+    for (date, lines) in dates {
+        let people_date = Some(PeopleDate {
+            year: date.date.year.map(Into::into),
+            month: Some(date.date.month.into()),
+            day: Some(date.date.day.into()),
+        });
+
+        // NOTE: a contact holds a single birthday; more land in the
+        // stash.
+        match date.kind {
+            CardDateKind::Birthday if person.birthdays.is_empty() => {
+                person.birthdays.push(PeopleBirthday {
+                    date: people_date,
+                    ..Default::default()
+                });
+            }
+            CardDateKind::Birthday => stash.extend(lines),
+            CardDateKind::Anniversary => person.events.push(PeopleEvent {
+                date: people_date,
+                event_type: Some("anniversary".into()),
+                ..Default::default()
+            }),
+            CardDateKind::Custom(label) => person.events.push(PeopleEvent {
+                date: people_date,
+                event_type: Some(if label.eq_ignore_ascii_case("other") {
+                    "other".into()
+                } else {
+                    label
+                }),
+                ..Default::default()
+            }),
+        }
+    }
+
     if !stash.is_empty() {
         person.client_data = vec![PeopleClientData {
             key: Some(CLIENT_DATA_KEY.to_string()),
@@ -590,6 +638,7 @@ pub fn changed_fields(person: &PeoplePerson, base: &PeoplePerson) -> Vec<PeopleP
         birthdays => Birthdays,
         client_data => ClientData,
         email_addresses => EmailAddresses,
+        events => Events,
         im_clients => ImClients,
         names => Names,
         nicknames => Nicknames,
@@ -603,6 +652,78 @@ pub fn changed_fields(person: &PeoplePerson, base: &PeoplePerson) -> Vec<PeopleP
     fields
 }
 
+/// The vCard date of a People date, None without month or day. People
+/// leaves the year unset (or 0) on year-less dates.
+fn vcard_date(date: &PeopleDate) -> Option<VcardDate> {
+    let date = VcardDate {
+        year: date
+            .year
+            .and_then(|year| u16::try_from(year).ok())
+            .filter(|year| *year > 0),
+        month: u8::try_from(date.month?).ok()?,
+        day: u8::try_from(date.day?).ok()?,
+    };
+
+    (date.month > 0 && date.day > 0).then_some(date)
+}
+
+/// ANNIVERSARY and Apple `X-ABDATE` lines from the person's events,
+/// the way Apple and Google's own CardDAV server carry them: the first
+/// anniversary takes the ANNIVERSARY slot, every other event becomes
+/// an `eventN.X-ABDATE` labelled with its type, the People `anniversary`
+/// and `other` types as the Apple well-known labels.
+fn event_lines(person: &PeoplePerson) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut anniversary = false;
+    let mut group = 0;
+
+    for event in &person.events {
+        let Some(date) = event.date.as_ref().and_then(vcard_date) else {
+            continue;
+        };
+
+        let event_type = opt(&event.event_type).unwrap_or("other");
+        let label = match event_type.to_ascii_lowercase().as_str() {
+            "anniversary" if !anniversary => {
+                anniversary = true;
+                lines.push(format!("ANNIVERSARY:{date}"));
+                continue;
+            }
+            "anniversary" => "_$!<Anniversary>!$_".to_string(),
+            "other" => "_$!<Other>!$_".to_string(),
+            _ => escape_text(event_type),
+        };
+
+        group += 1;
+        lines.push(format!("event{group}.X-ABDATE:{date}"));
+        lines.push(format!("event{group}.X-ABLabel:{label}"));
+    }
+
+    lines
+}
+
+/// URL of the person's own photo. Google serves a generated
+/// placeholder (flagged `default`) for persons without one, which is
+/// not a picture of the card.
+pub fn user_photo(person: &PeoplePerson) -> Option<&str> {
+    person
+        .photos
+        .iter()
+        .filter(|photo| photo.default != Some(true))
+        .find_map(|photo| opt(&photo.url))
+}
+
+/// Whether a PHOTO URL is hosted by Google, hence the person's own
+/// photo as read back by [`to_vcard`].
+fn is_google_photo(uri: &str) -> bool {
+    Url::parse(uri)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .is_some_and(|host| {
+            host == "googleusercontent.com" || host.ends_with(".googleusercontent.com")
+        })
+}
+
 /// The person's display name: the server-formatted one, the
 /// unstructured name, or composed from the parts.
 fn display_name(person: &PeoplePerson) -> String {
@@ -859,6 +980,8 @@ fn type_values(line: &VcardLine) -> Vec<String> {
 
 #[cfg(test)]
 mod tests {
+    use io_people::v1::rest::people::PeoplePhoto;
+
     use super::*;
 
     /// A person filling every field the projection manages.
@@ -1083,7 +1206,7 @@ mod tests {
     #[test]
     fn stash_preserves_unprojected_props() {
         let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nX-FOO;TYPE=bar:baz\r\n\
-            GENDER:F\r\nBDAY:--0412\r\nEND:VCARD\r\n";
+            GENDER:F\r\nBDAY;VALUE=text:circa 1800\r\nEND:VCARD\r\n";
 
         let person = to_person(vcard).unwrap();
         assert!(person.birthdays.is_empty());
@@ -1092,19 +1215,69 @@ mod tests {
         assert_eq!(stash.key.as_deref(), Some(CLIENT_DATA_KEY));
         assert_eq!(
             stash.value.as_deref(),
-            Some("X-FOO;TYPE=bar:baz\nGENDER:F\nBDAY:--0412")
+            Some("X-FOO;TYPE=bar:baz\nGENDER:F\nBDAY;VALUE=text:circa 1800")
         );
 
         let restored = to_vcard(&person);
         assert!(restored.contains("X-FOO;TYPE=bar:baz\r\n"));
         assert!(restored.contains("GENDER:F\r\n"));
-        assert!(restored.contains("BDAY:--0412\r\n"));
+        assert!(restored.contains("BDAY;VALUE=text:circa 1800\r\n"));
         assert!(restored.ends_with("END:VCARD\r\n"));
 
         // The restored document projects back to the same person.
         assert_eq!(to_person(&restored).unwrap(), person);
     }
 
+    #[test]
+    fn dates_project_year_less_and_as_events() {
+        let date = |year, month, day| PeopleDate {
+            year,
+            month: Some(month),
+            day: Some(day),
+        };
+        let event = |event_type: &str, date| PeopleEvent {
+            date: Some(date),
+            event_type: Some(event_type.into()),
+            ..Default::default()
+        };
+
+        let person = PeoplePerson {
+            birthdays: vec![PeopleBirthday {
+                date: Some(date(None, 4, 12)),
+                ..Default::default()
+            }],
+            events: vec![
+                event("anniversary", date(Some(2010), 6, 1)),
+                event("other", date(None, 9, 1)),
+                event("Adoption", date(Some(2015), 3, 2)),
+            ],
+            ..Default::default()
+        };
+
+        let vcard = to_vcard(&person);
+        assert!(vcard.contains("BDAY:--0412\r\n"));
+        assert!(vcard.contains("ANNIVERSARY:2010-06-01\r\n"));
+        assert!(vcard.contains("event1.X-ABDATE:--0901\r\nevent1.X-ABLabel:_$!<Other>!$_\r\n"));
+        assert!(vcard.contains("event2.X-ABDATE:2015-03-02\r\nevent2.X-ABLabel:Adoption\r\n"));
+
+        let restored = to_person(&vcard).unwrap();
+        assert_eq!(restored.birthdays, person.birthdays);
+        assert_eq!(restored.events, person.events);
+        assert!(restored.client_data.is_empty());
+
+        // NOTE: 3.0 spellings read too: Apple year-less birthdays and
+        // X-ANNIVERSARY.
+        let v3 = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\n\
+            BDAY;X-APPLE-OMIT-YEAR=1604:1604-04-12\r\n\
+            X-ANNIVERSARY:2010-06-01\r\nEND:VCARD\r\n";
+        let person = to_person(v3).unwrap();
+        assert_eq!(person.birthdays[0].date, Some(date(None, 4, 12)));
+        assert_eq!(
+            person.events,
+            vec![event("anniversary", date(Some(2010), 6, 1))]
+        );
+    }
+
     #[test]
     fn minted_props_project_and_consume() {
         use io_people::v1::rest::people::{
@@ -1153,6 +1326,42 @@ mod tests {
         assert_eq!(fields, vec![PeoplePersonField::ClientData]);
     }
 
+    #[test]
+    fn photos_project_as_urls() {
+        let person = PeoplePerson {
+            names: vec![PeopleName {
+                unstructured_name: Some("X".into()),
+                ..Default::default()
+            }],
+            photos: vec![
+                PeoplePhoto {
+                    url: Some("https://lh3.googleusercontent.com/a/default".into()),
+                    default: Some(true),
+                    ..Default::default()
+                },
+                PeoplePhoto {
+                    url: Some("https://lh3.googleusercontent.com/a/jane".into()),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let vcard = to_vcard(&person);
+        assert!(vcard.contains("PHOTO:https://lh3.googleusercontent.com/a/jane\r\n"));
+        assert!(!vcard.contains("/a/default"));
+        assert!(to_person(&vcard).unwrap().client_data.is_empty());
+
+        let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\n\
+            PHOTO:data:image/png;base64,iVBORw0KGgo=\r\n\
+            PHOTO:https://example.org/x.png\r\nEND:VCARD\r\n";
+        let person = to_person(vcard).unwrap();
+        assert_eq!(
+            person.client_data[0].value.as_deref(),
+            Some("PHOTO:https://example.org/x.png")
+        );
+    }
+
     #[test]
     fn stash_skips_oversized_lines() {
         let photo = format!("PHOTO:data:image/jpeg;base64,{}", "A".repeat(10_000));
@@ -9,7 +9,7 @@
 //! requested AddressBook. Card updates carry no If-Match equivalent
 //! server-side (last-write-wins), so passing one bails.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
 
 use anyhow::{Error, Result, anyhow, bail};
 use base64::{Engine, prelude::BASE64_STANDARD};
@@ -23,6 +23,7 @@ use io_jmap::{
         },
         contact_card::{
             JmapContactCard,
+            changes::JmapContactCardChangesOptions,
             get::JmapContactCardGetOptions,
             query::{JmapContactCardFilter, JmapContactCardQueryOptions},
             set::{JmapContactCardPatch, JmapContactCardSetArgs},
@@ -32,12 +33,13 @@ use io_jmap::{
 use secrecy::{ExposeSecret, SecretString};
 
 use crate::{
-    config::{JmapAuthConfig, JmapConfig, parse_server},
-    jmap::project,
+    config::{JmapAuthConfig, JmapConfig},
+    jmap::{client::open_jmap_client, project},
+    project::vcard_matches,
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
-        client::paginate,
+        card::{Card, CardChanges, CardQuery},
+        client::PageWindow,
     },
 };
 
@@ -49,15 +51,10 @@ pub struct JmapBackend {
 
 impl JmapBackend {
     /// Establishes the JMAP session (TLS, `/.well-known/jmap`
-    /// discovery) from the account's `[jmap]` block.
-    pub fn new(config: JmapConfig) -> Result<Self> {
-        let tls = config.tls.into_tls(config.alpn);
-        let http_auth = jmap_http_auth(config.auth)?;
-        let url = parse_server(&config.server, "https", &["http", "https", "jmap", "jmaps"])?;
-
-        let mut inner = JmapClientStd::connect(&url, &tls, http_auth)?;
-        inner.session_get(&url)?;
-
+    /// discovery) from the account's `[jmap]` block, unless cached for
+    /// `account_name` (see [`open_jmap_client`]).
+    pub fn new(config: JmapConfig, account_name: &str) -> Result<Self> {
+        let inner = open_jmap_client(config, Some(account_name))?;
         Ok(Self { inner })
     }
 
@@ -157,30 +154,58 @@ impl JmapBackend {
     }
 
     /// Lists the ContactCards of the AddressBook, each converted to a
-    /// vCard document, applying 1-indexed pagination.
+    /// vCard document, applying 1-indexed pagination. The page maps
+    /// onto the query `position` and `limit`, so only its cards are
+    /// fetched.
     pub fn list_cards(
         &mut self,
         addressbook_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Card>> {
+        let window = PageWindow::new(page, page_size);
         let opts = JmapContactCardQueryOptions {
             filter: Some(JmapContactCardFilter {
                 in_address_book: Some(addressbook_id.to_string()),
                 ..Default::default()
             }),
+            position: Some(window.skip.try_into()?),
+            limit: window.take.map(|take| take as u64),
             ..Default::default()
         };
         let out = self.inner.contact_card_query(opts)?;
 
-        let cards = out
+        out.cards
+            .into_iter()
+            .map(|card| project::to_card(addressbook_id, card))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(Error::msg)
+    }
+
+    /// Lists the ContactCards of the AddressBook matching `query`. The
+    /// server narrows the candidates with its `text` filter on the
+    /// query's main term, then the converted vCards are matched
+    /// against every criterion.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let opts = JmapContactCardQueryOptions {
+            filter: Some(JmapContactCardFilter {
+                in_address_book: Some(addressbook_id.to_string()),
+                text: query.term().map(str::to_string),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let out = self.inner.contact_card_query(opts)?;
+
+        let mut cards = out
             .cards
             .into_iter()
             .map(|card| project::to_card(addressbook_id, card))
             .collect::<Result<Vec<_>, _>>()
             .map_err(Error::msg)?;
 
-        Ok(paginate(cards, page, page_size))
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
     }
 
     /// Reads the ContactCard `card_id`, converted to a vCard document.
@@ -281,6 +306,88 @@ impl JmapBackend {
 
         Ok(())
     }
+
+    /// Lists the ContactCards changed since the state `since`
+    /// (`ContactCard/changes`, drained while `hasMoreChanges`). Changes
+    /// are account-wide: created and updated cards are narrowed to the
+    /// AddressBook (an updated card no longer in it counts as deleted),
+    /// destroyed ones are reported as is. Without `since`, every card
+    /// of the AddressBook is created and the current state returned.
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        let Some(since) = since else {
+            // NOTE: the state is read before the listing, so a card
+            // changed in between shows up again in the next round
+            // rather than being missed.
+            let opts = JmapContactCardGetOptions {
+                ids: Some(Vec::new()),
+                ..Default::default()
+            };
+            let token = self.inner.contact_card_get(opts)?.new_state;
+            let cards = self.list_cards(addressbook_id, None, None)?;
+
+            return Ok(CardChanges {
+                created: cards.into_iter().map(|card| card.id).collect(),
+                token,
+                ..Default::default()
+            });
+        };
+
+        let mut changes = CardChanges::default();
+        let mut state = since.to_string();
+
+        loop {
+            let out = self
+                .inner
+                .contact_card_changes(state, JmapContactCardChangesOptions::default())?;
+
+            changes.created.extend(out.created);
+            changes.updated.extend(out.updated);
+            changes.deleted.extend(out.destroyed);
+            state = out.new_state;
+
+            if !out.has_more_changes {
+                break;
+            }
+        }
+
+        let ids: Vec<String> = changes
+            .created
+            .iter()
+            .chain(&changes.updated)
+            .cloned()
+            .collect();
+
+        if !ids.is_empty() {
+            let opts = JmapContactCardGetOptions {
+                ids: Some(ids),
+                ..Default::default()
+            };
+            let inside: HashSet<String> = self
+                .inner
+                .contact_card_get(opts)?
+                .cards
+                .into_iter()
+                .filter(|card| card.address_book_ids.get(addressbook_id) == Some(&true))
+                .filter_map(|card| card.id)
+                .collect();
+
+            changes.created.retain(|id| inside.contains(id));
+
+            let (updated, moved_out): (Vec<_>, Vec<_>) = changes
+                .updated
+                .into_iter()
+                .partition(|id| inside.contains(id));
+            changes.updated = updated;
+            changes.deleted.extend(moved_out);
+        }
+
+        changes.token = state;
+        Ok(changes)
+    }
 }
 
 /// Converts a [`JmapAuthConfig`] into the pre-formatted HTTP
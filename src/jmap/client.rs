@@ -1,15 +1,18 @@
 //! Cardamum wrapper around [`io_jmap::client::JmapClientStd`] that
 //! bundles the merged [`Account`] alongside the connected client (its
-//! JMAP session already discovered).
+//! JMAP session already discovered, or read from the discovery cache).
 
 use std::ops::{Deref, DerefMut};
 
 use anyhow::{Result, anyhow};
-use io_jmap::client::JmapClientStd;
+use io_jmap::{client::JmapClientStd, rfc8620::session::JmapSession};
 
 use crate::{
-    account::context::Account,
-    config::{AccountConfig, Config, parse_server},
+    account::{
+        context::Account,
+        discovery::{DEFAULT_DISCOVERY_TTL, DiscoveryCache},
+    },
+    config::{AccountConfig, Config, JmapAuthConfig, JmapConfig, parse_server},
     jmap::backend::jmap_http_auth,
 };
 
@@ -45,17 +48,51 @@ pub fn build_jmap_client(
         .take()
         .ok_or_else(|| anyhow!("JMAP config is missing for account `{name}`"))?;
 
-    let tls = jmap_config.tls.into_tls(jmap_config.alpn);
-    let http_auth = jmap_http_auth(jmap_config.auth)?;
-    let url = parse_server(
-        &jmap_config.server,
-        "https",
-        &["http", "https", "jmap", "jmaps"],
-    )?;
+    let inner = open_jmap_client(jmap_config, Some(&name))?;
 
+    let account = Account::from(config).merge(Account::from(account_config));
+    Ok(JmapClient { inner, account })
+}
+
+/// Opens a [`JmapClientStd`] from a [`JmapConfig`] and fetches its
+/// session. With an `account_name`, the session is cached for
+/// `discovery-ttl` and reused until it expires; `None` always fetches
+/// it afresh.
+pub fn open_jmap_client(config: JmapConfig, account_name: Option<&str>) -> Result<JmapClientStd> {
+    let url = parse_server(&config.server, "https", &["http", "https", "jmap", "jmaps"])?;
+    let source = discovery_source(&config);
+    let cache = account_name.and_then(|name| DiscoveryCache::new(name, "jmap", source));
+    let ttl = config.discovery_ttl.unwrap_or(DEFAULT_DISCOVERY_TTL);
+
+    let tls = config.tls.into_tls(config.alpn);
+    let http_auth = jmap_http_auth(config.auth)?;
     let mut inner = JmapClientStd::connect(&url, &tls, http_auth)?;
+
+    if let Some(session) = cache
+        .as_ref()
+        .and_then(|cache| cache.load::<JmapSession>(ttl))
+    {
+        inner.set_session(session);
+        return Ok(inner);
+    }
+
     inner.session_get(&url)?;
 
-    let account = Account::from(config).merge(Account::from(account_config));
-    Ok(JmapClient { inner, account })
+    if let Some(cache) = cache
+        && ttl > 0
+        && let Some(session) = inner.session()
+    {
+        cache.save_or_log(session);
+    }
+
+    Ok(inner)
+}
+
+/// Config a JMAP session is fetched from, keying its cache entry. The
+/// Basic username is part of it, as sessions differ per user.
+pub fn discovery_source(config: &JmapConfig) -> String {
+    match &config.auth {
+        JmapAuthConfig::Basic { username, .. } => format!("{username}@{}", config.server),
+        _ => config.server.clone(),
+    }
 }
@@ -15,12 +15,14 @@
 //! 8620 + RFC 9610), io-msgraph (Microsoft Graph) and io-people (Google
 //! People); the local storage backend is io-vdir. vcard-rs parses and
 //! builds vCard (and converts to/from JSContact) for the backends with
-//! no native vCard. Account discovery comes from io-pim-discovery (fixed provider
-//! rules, PACC, RFC 6764 CardDAV resolve, RFC 8620 JMAP resolve, a
+//! no native vCard, and backs the shared card commands that look inside
+//! a vCard (such as the three-way merge of `card update`). Account
+//! discovery comes from io-pim-discovery (fixed provider rules, PACC,
+//! RFC 6764 CardDAV resolve, RFC 8620 JMAP resolve, a
 //! `WWW-Authenticate` probe). The CLI plumbing (clap args, printer,
 //! logger), TOML config loading and the blocking stream runtime come
-//! from pimalaya-cli, pimalaya-config and pimalaya-stream. Every backend
-//! sits behind its own cargo feature, so a build ships only the
+//! from pimalaya-cli, pimalaya-config and pimalaya-stream. Every
+//! backend sits behind its own cargo feature, so a build ships only the
 //! protocols it needs.
 //!
 //! ## No aggregator crate
@@ -37,13 +39,15 @@
 //! ## Command families
 //!
 //! The command tree ([`cli`], `Command`) splits into three groups. The
-//! shared API (`addressbook`, `card`) is the cross-protocol
+//! shared API (`addressbook`, `card`, `sync`) is the cross-protocol
 //! least-common-denominator surface, behaving the same whatever backend
-//! serves the active account. The protocol-specific APIs (`carddav`,
-//! `vdir`) each expose the full surface of one backend, including
-//! operations the shared API cannot model (`carddav propfind`/`report`,
-//! `vdir rename`). The meta commands (`account`, `completions`,
-//! `manuals`) cover account inspection, shell completions and man pages.
+//! serves the active account; `sync` reconciles two such surfaces, of
+//! two accounts or of two backends of one account. The
+//! protocol-specific APIs (`carddav`, `vdir`) each expose the full
+//! surface of one backend, including operations the shared API cannot
+//! model (`carddav propfind`/`report`, `vdir rename`). The meta
+//! commands (`account`, `completions`, `manuals`) cover account
+//! inspection, shell completions and man pages.
 //!
 //! ## Shared commands and backend selection
 //!
@@ -109,7 +113,6 @@ mod google;
 mod jmap;
 #[cfg(feature = "msgraph")]
 mod msgraph;
-#[cfg(any(feature = "msgraph", feature = "google"))]
 mod project;
 mod shared;
 #[cfg(feature = "vdir")]
@@ -120,7 +123,10 @@ use anyhow::Result;
 use clap::Parser;
 use pimalaya_cli::{error::ErrorReport, log::Logger, printer::StdoutPrinter};
 
-use crate::{cli::Cli, wizard::discover};
+use crate::{
+    cli::{AccountSelection, Cli},
+    wizard::discover,
+};
 
 fn main() {
     let cli = Cli::parse();
@@ -132,11 +138,16 @@ fn main() {
 fn execute(cli: Cli, printer: &mut StdoutPrinter) -> Result<()> {
     Logger::try_init(&cli.log)?;
     let config = cli.config_paths.as_ref();
-    let account = cli.account.name.as_deref();
+    let accounts = AccountSelection {
+        names: cli.accounts,
+        all: cli.all_accounts,
+    };
     let backend = cli.backend;
+    let cached = cli.cached;
+    let json = cli.json.json;
 
     match cli.cmd {
-        Some(cmd) => cmd.execute(printer, config, account, backend),
+        Some(cmd) => cmd.execute(printer, config, &accounts, backend, cached, json),
         None => discover::run(printer),
     }
 }
@@ -7,6 +7,9 @@
 //! folder is not listed by the folders endpoint, so it is surfaced
 //! under the [`CONTACTS_FOLDER`] sentinel id. Card updates carry no
 //! If-Match guard server-side (last-write-wins), so passing one bails.
+//! Contact photos live behind their own endpoint, one request per
+//! contact: single card reads project them as an inline PHOTO, while
+//! listings leave them out.
 
 use anyhow::{Error, Result, bail};
 use io_msgraph::v1::{
@@ -23,10 +26,11 @@ use url::Url;
 use crate::{
     config::MsgraphConfig,
     msgraph::project,
+    project::{VcardPhoto, vcard_matches, vcard_photo},
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
-        client::paginate,
+        card::{Card, CardChanges, CardQuery},
+        client::PageWindow,
     },
 };
 
@@ -34,6 +38,12 @@ use crate::{
 /// addresses by omitting the folder segment.
 pub const CONTACTS_FOLDER: &str = "contacts";
 
+/// Host of the Graph endpoint, the only one paging links may point to.
+const GRAPH_HOST: &str = "graph.microsoft.com";
+
+/// Largest contacts page (`$top`) Graph serves.
+const GRAPH_PAGE_SIZE: u32 = 1000;
+
 /// Microsoft Graph backend of the shared-API client.
 pub struct MsgraphBackend {
     pub inner: MsgraphClientStd,
@@ -149,16 +159,37 @@ impl MsgraphBackend {
     }
 
     /// Lists the contacts of the folder, each projected onto a vCard
-    /// document, applying 1-indexed pagination.
+    /// document, applying 1-indexed pagination. Graph pages are
+    /// followed only until the requested page is covered.
     pub fn list_cards(
         &mut self,
         addressbook_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
     ) -> Result<Vec<Card>> {
+        let window = PageWindow::new(page, page_size);
+        let cards = self.fetch_cards(addressbook_id, window)?;
+        Ok(window.apply(cards))
+    }
+
+    /// Lists the contacts of the folder matching `query`. Graph
+    /// `$filter` only offers prefix (`startswith`) and exact (`eq`)
+    /// matches, which would miss cards the substring matching of the
+    /// shared API finds, so the folder is listed, then the projected
+    /// vCards are matched against every criterion.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let mut cards = self.fetch_cards(addressbook_id, PageWindow::default())?;
+
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
+    }
+
+    /// Fetches the contacts of the folder, following next links until
+    /// `window` is covered.
+    fn fetch_cards(&mut self, addressbook_id: &str, window: PageWindow) -> Result<Vec<Card>> {
         let expand = graph_expand();
         let params = MsgraphContactsListParams {
-            top: Some(100),
+            top: Some(window.batch(0, GRAPH_PAGE_SIZE)),
             expand: Some(&expand),
             ..Default::default()
         };
@@ -177,6 +208,10 @@ impl MsgraphBackend {
                     .map(|contact| into_card(addressbook_id, contact)),
             );
 
+            if window.is_covered(cards.len()) {
+                break;
+            }
+
             match current.next_link {
                 Some(next) => {
                     let url = parse_graph_url(&next)?;
@@ -187,18 +222,34 @@ impl MsgraphBackend {
             }
         }
 
-        Ok(paginate(cards, page, page_size))
+        Ok(cards)
     }
 
     /// Reads the contact `card_id`, projected onto a vCard document.
+    /// Like listings, it carries no PHOTO: the contact photo costs a
+    /// request of its own (see [`Self::get_card_with_photo`]).
     pub fn get_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
         let expand = graph_expand();
         let contact = self.inner.contact_get(card_id, Some(&expand))?.response;
         Ok(into_card(addressbook_id, contact))
     }
 
-    /// Creates the vCard as a Graph contact in the folder. Graph names
-    /// the resource, so the returned id is server-assigned.
+    /// Reads the contact `card_id` like [`Self::get_card`], embedding
+    /// its contact photo as an inline PHOTO.
+    pub fn get_card_with_photo(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
+        let mut card = self.get_card(addressbook_id, card_id)?;
+
+        if let Some(photo) = self.inner.contact_photo_get(card_id)?.response {
+            let vcard = into_vcard_text(card.contents)?;
+            card.contents = project::with_photo(vcard, &photo).into_bytes();
+        }
+
+        Ok(card)
+    }
+
+    /// Creates the vCard as a Graph contact in the folder, uploading an
+    /// inline PHOTO as the contact photo. Graph names the resource, so
+    /// the returned id is server-assigned.
     pub fn create_card(&mut self, addressbook_id: &str, contents: Vec<u8>) -> Result<String> {
         let vcard = into_vcard_text(contents)?;
         let contact = project::to_new_contact(&vcard).map_err(Error::msg)?;
@@ -208,6 +259,11 @@ impl MsgraphBackend {
             .contact_create(folder(addressbook_id), &contact)?
             .response;
 
+        if let Some(VcardPhoto::Inline { media_type, bytes }) = vcard_photo(&vcard) {
+            self.inner
+                .contact_photo_update(&created.id, &media_type, &bytes)?;
+        }
+
         Ok(created.id)
     }
 
@@ -215,7 +271,9 @@ impl MsgraphBackend {
     /// contact serves as delta base, so the PATCH body shrinks to the
     /// changed fields, plus nulls for the removed ones. Graph has no
     /// If-Match guard (updates are last-write-wins), so passing one
-    /// bails instead of pretending to honor it.
+    /// bails instead of pretending to honor it. An inline PHOTO
+    /// differing from the contact photo is uploaded; a vCard without
+    /// PHOTO leaves the contact photo alone, reads carrying none.
     pub fn update_card(
         &mut self,
         _addressbook_id: &str,
@@ -236,6 +294,14 @@ impl MsgraphBackend {
         let contact = project::to_contact_delta(&vcard, &base_vcard).map_err(Error::msg)?;
         self.inner.contact_update(card_id, &contact)?;
 
+        if let Some(VcardPhoto::Inline { media_type, bytes }) = vcard_photo(&vcard) {
+            let current = self.inner.contact_photo_get(card_id)?.response;
+            if current.as_deref() != Some(bytes.as_slice()) {
+                self.inner
+                    .contact_photo_update(card_id, &media_type, &bytes)?;
+            }
+        }
+
         Ok(())
     }
 
@@ -244,6 +310,66 @@ impl MsgraphBackend {
         self.inner.contact_delete(card_id)?;
         Ok(())
     }
+
+    /// Deletes the photo of the contact `card_id`, if any.
+    pub fn delete_card_photo(&mut self, _addressbook_id: &str, card_id: &str) -> Result<()> {
+        if self.inner.contact_photo_get(card_id)?.response.is_some() {
+            self.inner.contact_photo_delete(card_id)?;
+        }
+        Ok(())
+    }
+
+    /// Lists the contacts changed since the delta link `since` (Graph
+    /// delta query), following next links until the round's delta
+    /// link, which becomes the new token. The feed does not tell
+    /// creates from updates, so changes land in `updated` unless
+    /// `since` is `None` (initial round).
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        let auth = self.inner.auth.clone();
+        let mut current = match since {
+            None => {
+                self.inner
+                    .contacts_delta(folder(addressbook_id), Some("id"))?
+                    .response
+            }
+            Some(link) => {
+                let url = parse_graph_url(link)?;
+                self.inner.run(MsgraphSend::get(&auth, url))?.response
+            }
+        };
+
+        let mut changes = CardChanges::default();
+        loop {
+            for delta in current.value {
+                let id = delta.contact.id;
+                if delta.removed.is_some() {
+                    changes.deleted.push(id);
+                } else if since.is_none() {
+                    changes.created.push(id);
+                } else {
+                    changes.updated.push(id);
+                }
+            }
+
+            if let Some(link) = current.delta_link {
+                changes.token = link;
+                break;
+            }
+
+            let Some(next) = current.next_link else {
+                bail!("Graph delta page carries neither a next nor a delta link");
+            };
+
+            let url = parse_graph_url(&next)?;
+            current = self.inner.run(MsgraphSend::get(&auth, url))?.response;
+        }
+
+        Ok(changes)
+    }
 }
 
 /// Maps the sentinel Contacts folder id to the omitted folder segment.
@@ -268,9 +394,19 @@ fn into_vcard_text(contents: Vec<u8>) -> Result<String> {
     String::from_utf8(contents).map_err(|_| anyhow::anyhow!("Card contents are not valid UTF-8"))
 }
 
-/// Parses an OData paging link served by Graph.
+/// Parses an OData paging link served by Graph. The link is followed
+/// with the bearer token attached, and delta links come back from the
+/// user as `--since` tokens, so only HTTPS links to the Graph endpoint
+/// are accepted.
 fn parse_graph_url(raw: &str) -> Result<Url> {
-    Url::parse(raw).map_err(|err| anyhow::anyhow!("Invalid Graph page URL `{raw}`: {err}"))
+    let url =
+        Url::parse(raw).map_err(|err| anyhow::anyhow!("Invalid Graph page URL `{raw}`: {err}"))?;
+
+    if url.scheme() != "https" || url.host_str() != Some(GRAPH_HOST) {
+        bail!("Refusing to follow Graph page URL `{raw}`: not on https://{GRAPH_HOST}");
+    }
+
+    Ok(url)
 }
 
 /// The `$expand` clause fetching the stash extended property along with
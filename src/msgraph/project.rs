@@ -13,7 +13,9 @@
 //! stay Unset, out of the body, and survive updates untouched. Wire
 //! bodies refine it: [`to_new_contact`] shapes the create body and
 //! [`to_contact_delta`] the update body, both stripping the gratuitous
-//! nulls the Outlook backend rejects with HTTP 500.
+//! nulls the Outlook backend rejects with HTTP 500. The contact photo
+//! is no contact field: the backend reads and uploads it through the
+//! photo endpoint, so an inline PHOTO is consumed here.
 
 use core::str::FromStr;
 use std::borrow::Cow;
@@ -49,7 +51,10 @@ use vcard::{
     },
 };
 
-use crate::project::{MAX_STASH_LINE, escape_text, full_date, splice_props, text_prop};
+use crate::project::{
+    MAX_STASH_LINE, VcardPhoto, escape_text, full_date, image_media_type, parse_photo, photo_line,
+    splice_props, text_prop, vcard_photo,
+};
 
 /// Extended-property id under which the vCard remainder is stashed on
 /// the Graph contact (a fixed app GUID plus a name, the String MAPI
@@ -266,6 +271,19 @@ pub fn to_vcard(contact: &MsgraphContact) -> String {
     splice_props(vcard, &extra)
 }
 
+/// Splices the contact photo fetched from the photo endpoint into a
+/// projected vCard as an inline PHOTO, unless the stash restored one
+/// already.
+pub fn with_photo(vcard: String, photo: &[u8]) -> String {
+    if vcard_photo(&vcard).is_some() {
+        return vcard;
+    }
+
+    // NOTE: Graph stores contact photos as JPEG.
+    let media_type = image_media_type(photo).unwrap_or("image/jpeg");
+    splice_props(vcard, &[photo_line(media_type, photo)])
+}
+
 /// Projects a vCard onto an io-msgraph contact, the full-state
 /// projection: every managed field is Set from the vCard or Null
 /// (collections: Set empty) when absent, while unmanaged Graph fields
@@ -548,6 +566,12 @@ pub fn to_contact(vcard: &str) -> Result<MsgraphContact, String> {
                     false
                 }
             }
+            // NOTE: an inline picture is uploaded by the backend; a
+            // URL has no Graph slot and is stashed.
+            Ok(VcardPropKind::Photo) => matches!(
+                parse_photo(&line.to_string()),
+                Some(VcardPhoto::Inline { .. })
+            ),
             Ok(_) => false,
         };
 
@@ -1120,6 +1144,27 @@ mod tests {
         assert_eq!(to_contact(&to_vcard(&contact)).unwrap(), expected);
     }
 
+    #[test]
+    fn photos_go_through_the_photo_endpoint() {
+        let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\n\
+            PHOTO:data:image/png;base64,iVBORw0KGgo=\r\n\
+            PHOTO:https://example.org/x.png\r\nEND:VCARD\r\n";
+        let contact = to_contact(vcard).unwrap();
+        let props = contact
+            .single_value_extended_properties
+            .as_option()
+            .unwrap();
+        assert_eq!(props[0].value, "PHOTO:https://example.org/x.png");
+
+        let bare = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nEND:VCARD\r\n".to_string();
+        let png = b"\x89PNG\r\n\x1a\n";
+        assert!(
+            with_photo(bare, png)
+                .ends_with("PHOTO:data:image/png;base64,iVBORw0KGgo=\r\nEND:VCARD\r\n")
+        );
+        assert_eq!(with_photo(vcard.to_string(), png), vcard);
+    }
+
     #[test]
     fn stash_preserves_unprojected_props() {
         let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nX-FOO;TYPE=bar:baz\r\n\
@@ -0,0 +1,290 @@
+//! Conversion of vCard documents between versions 3.0 and 4.0.
+
+use crate::{
+    project::{ContentLine, fold_line, unfold},
+    shared::card::VcardVersion,
+};
+
+/// Converts a vCard to the given version, returning it untouched when
+/// it already is. Works on the unfolded content lines, so that 3.0
+/// documents the parser would reject convert too. Covers the
+/// differences devices trip on: `PREF=` versus `TYPE=pref`, `tel:`
+/// URIs, inline `data:` media versus `ENCODING=b`, the Apple
+/// `X-ADDRESSBOOKSERVER-*` group properties and `X-APPLE-OMIT-YEAR`
+/// year-less birthdays, `X-` fallbacks for the 4.0-only ANNIVERSARY and
+/// GENDER, and the N property 3.0 requires. vCard 2.1 QUOTED-PRINTABLE
+/// values are decoded, and the output lines are folded again.
+pub fn convert_vcard(vcard: &str, version: VcardVersion) -> String {
+    let unfolded = unfold(vcard);
+
+    let mut lines: Vec<ContentLine> = join_soft_breaks(&unfolded)
+        .iter()
+        .filter_map(|line| ContentLine::parse(line))
+        .collect();
+
+    let current = lines
+        .iter()
+        .find(|line| line.name == "VERSION")
+        .map(|line| line.value.trim());
+    let target = match version {
+        VcardVersion::V3 => "3.0",
+        VcardVersion::V4 => "4.0",
+    };
+
+    if current == Some(target) {
+        return vcard.to_string();
+    }
+
+    for line in &mut lines {
+        line.decode_quoted_printable();
+        match version {
+            VcardVersion::V3 => line.downgrade(),
+            VcardVersion::V4 => line.upgrade(),
+        }
+    }
+
+    if version == VcardVersion::V3 && !lines.iter().any(|line| line.name == "N") {
+        let end = lines.iter().rposition(|line| line.name == "END");
+        let n = ContentLine {
+            name: "N".into(),
+            value: ";;;;".into(),
+            ..Default::default()
+        };
+        lines.insert(end.unwrap_or(lines.len()), n);
+    }
+
+    lines
+        .iter()
+        .map(|line| fold_line(&line.to_string()) + "\r\n")
+        .collect()
+}
+
+/// Splits unfolded content lines, joining the quoted-printable soft
+/// line breaks (a trailing `=`) that folding does not cover.
+fn join_soft_breaks(unfolded: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut pending = false;
+
+    for line in unfolded.lines() {
+        let line = line.trim_end_matches('\r');
+        match lines.last_mut() {
+            Some(last) if pending => {
+                last.pop();
+                last.push_str(line);
+            }
+            _ => lines.push(line.to_string()),
+        }
+
+        let last = lines.last().map(String::as_str).unwrap_or_default();
+        pending = last.ends_with('=')
+            && last
+                .split(':')
+                .next()
+                .is_some_and(|head| head.to_ascii_uppercase().contains("QUOTED-PRINTABLE"));
+    }
+
+    lines
+}
+
+/// Decodes a quoted-printable value (RFC 2045 6.7), its soft line
+/// breaks already joined. Malformed escapes are kept as they are.
+fn decode_quoted_printable(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+
+        match (bytes[index], escaped) {
+            (b'=', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    decoded
+}
+
+impl ContentLine {
+    /// Decodes a QUOTED-PRINTABLE value into plain UTF-8 text, line
+    /// breaks escaped. The bytes are read as Latin-1 when the CHARSET
+    /// says so or when they are not UTF-8.
+    fn decode_quoted_printable(&mut self) {
+        let encoding = self.param("ENCODING").unwrap_or_default();
+        if !encoding
+            .iter()
+            .any(|value| value.eq_ignore_ascii_case("QUOTED-PRINTABLE"))
+        {
+            return;
+        }
+
+        self.remove_param("ENCODING");
+        let charset = self.remove_param("CHARSET");
+        let latin1 = charset.iter().any(|charset| {
+            charset.eq_ignore_ascii_case("ISO-8859-1") || charset.eq_ignore_ascii_case("LATIN1")
+        });
+
+        let bytes = decode_quoted_printable(&self.value);
+        let text = match String::from_utf8(bytes) {
+            Ok(text) if !latin1 => text,
+            Ok(text) => text.bytes().map(char::from).collect(),
+            Err(err) => err.into_bytes().into_iter().map(char::from).collect(),
+        };
+
+        self.value = text.replace("\r\n", "\\n").replace('\n', "\\n");
+    }
+
+    fn downgrade(&mut self) {
+        self.remove_param("ALTID");
+        self.remove_param("PID");
+
+        if !self.remove_param("PREF").is_empty() {
+            self.add_type("pref");
+        }
+
+        match self.name.as_str() {
+            "VERSION" => self.value = "3.0".into(),
+            "KIND" => self.name = "X-ADDRESSBOOKSERVER-KIND".into(),
+            "MEMBER" => self.name = "X-ADDRESSBOOKSERVER-MEMBER".into(),
+            "ANNIVERSARY" => self.name = "X-ANNIVERSARY".into(),
+            "GENDER" => self.name = "X-GENDER".into(),
+            "TEL" => {
+                if let Some(number) = self.value.strip_prefix("tel:") {
+                    self.value = number.to_string();
+                    self.remove_param("VALUE");
+                }
+            }
+            "BDAY" => {
+                let date = self.value.strip_prefix("--").filter(|date| date.len() == 4);
+                if let Some(date) = date {
+                    self.value = format!("1604-{}-{}", &date[..2], &date[2..]);
+                    self.params
+                        .push(("X-APPLE-OMIT-YEAR".into(), vec!["1604".into()]));
+                }
+            }
+            "PHOTO" | "LOGO" | "SOUND" => {
+                let inline = self
+                    .value
+                    .strip_prefix("data:")
+                    .and_then(|data| data.split_once(";base64,"));
+                if let Some((mime, data)) = inline {
+                    let kind = mime.rsplit('/').next().unwrap_or(mime).to_ascii_uppercase();
+                    self.value = data.to_string();
+                    self.remove_param("VALUE");
+                    self.remove_param("MEDIATYPE");
+                    self.params.push(("ENCODING".into(), vec!["b".into()]));
+                    self.params.push(("TYPE".into(), vec![kind]));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(super) fn upgrade(&mut self) {
+        self.remove_param("CHARSET");
+
+        if let Some((_, types)) = self.params.iter_mut().find(|(param, _)| param == "TYPE") {
+            let count = types.len();
+            types.retain(|value| !value.eq_ignore_ascii_case("pref"));
+            if types.len() != count {
+                self.params.push(("PREF".into(), vec!["1".into()]));
+            }
+            self.params.retain(|(_, values)| !values.is_empty());
+        }
+
+        match self.name.as_str() {
+            "VERSION" => self.value = "4.0".into(),
+            "X-ADDRESSBOOKSERVER-KIND" => self.name = "KIND".into(),
+            "X-ADDRESSBOOKSERVER-MEMBER" => self.name = "MEMBER".into(),
+            "X-ANNIVERSARY" => self.name = "ANNIVERSARY".into(),
+            "X-GENDER" => self.name = "GENDER".into(),
+            "BDAY" => {
+                let omitted = self.remove_param("X-APPLE-OMIT-YEAR");
+                let date = omitted
+                    .first()
+                    .and_then(|year| self.value.strip_prefix(year.as_str()))
+                    .map(|date| date.replace('-', ""));
+                if let Some(date) = date.filter(|date| date.len() == 4) {
+                    self.value = format!("--{date}");
+                }
+            }
+            "PHOTO" | "LOGO" | "SOUND" => {
+                let encoding = self.param("ENCODING").unwrap_or_default();
+                let inline = encoding.iter().any(|value| {
+                    value.eq_ignore_ascii_case("b") || value.eq_ignore_ascii_case("base64")
+                });
+                if inline {
+                    self.remove_param("ENCODING");
+                    let kind = self.remove_param("TYPE").into_iter().next();
+                    let top = match self.name.as_str() {
+                        "SOUND" => "audio",
+                        _ => "image",
+                    };
+                    let mime = match kind {
+                        Some(kind) => format!("{top}/{}", kind.to_ascii_lowercase()),
+                        None => "application/octet-stream".into(),
+                    };
+                    self.value = format!("data:{mime};base64,{}", self.value);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_between_versions() {
+        let v4 = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane Doe\r\n\
+            TEL;VALUE=uri;TYPE=cell;PREF=1:tel:+33111\r\nBDAY:--0415\r\n\
+            PHOTO:data:image/jpeg;base64,AAAA\r\nKIND:individual\r\n\
+            item1.EMAIL;TYPE=\"work,internet\":jane@acme.org\r\nEND:VCARD\r\n";
+
+        let v3 = convert_vcard(v4, VcardVersion::V3);
+        assert_eq!(
+            v3,
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Jane Doe\r\n\
+                TEL;TYPE=cell,pref:+33111\r\nBDAY;X-APPLE-OMIT-YEAR=1604:1604-04-15\r\n\
+                PHOTO;ENCODING=b;TYPE=JPEG:AAAA\r\nX-ADDRESSBOOKSERVER-KIND:individual\r\n\
+                item1.EMAIL;TYPE=\"work,internet\":jane@acme.org\r\nN:;;;;\r\nEND:VCARD\r\n"
+        );
+
+        let back = convert_vcard(&v3, VcardVersion::V4);
+        assert!(back.contains("VERSION:4.0\r\n"));
+        assert!(back.contains("TEL;TYPE=cell;PREF=1:+33111\r\n"));
+        assert!(back.contains("BDAY:--0415\r\n"));
+        assert!(back.contains("PHOTO:data:image/jpeg;base64,AAAA\r\n"));
+        assert!(back.contains("KIND:individual\r\n"));
+
+        assert_eq!(convert_vcard(v4, VcardVersion::V4), v4);
+    }
+
+    #[test]
+    fn converts_quoted_printable_and_folds() {
+        let v21 = "BEGIN:VCARD\r\nVERSION:2.1\r\n\
+            FN;CHARSET=UTF-8;ENCODING=QUOTED-PRINTABLE:Ren=C3=A9e\r\n\
+            NOTE;QUOTED-PRINTABLE;CHARSET=ISO-8859-1:caf=E9=0D=0A=\r\nbar\r\n\
+            X-LONG:0123456789012345678901234567890123456789012345678901234567890123456789\r\n\
+            END:VCARD\r\n";
+
+        let v4 = convert_vcard(v21, VcardVersion::V4);
+        assert!(v4.contains("FN:Renée\r\n"));
+        assert!(v4.contains("NOTE:café\\nbar\r\n"));
+        assert!(v4.contains(
+            "X-LONG:01234567890123456789012345678901234567890123456789012345678901234567\r\n 89\r\n"
+        ));
+        assert!(v4.lines().all(|line| line.len() <= 75));
+    }
+}
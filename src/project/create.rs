@@ -0,0 +1,301 @@
+//! Minting of new vCard documents, and the small values they need.
+
+use std::borrow::Cow;
+
+use vcard::{
+    param::VcardParam,
+    prop::{VcardProp, VcardPropKind, VcardPropName},
+    tree::cst::VcardCst,
+    value::{
+        VcardValue, adr::VcardAdr, datetime::VcardDateAndOrTime, n::VcardN, org::VcardOrg,
+        text::VcardTextList,
+    },
+};
+
+use crate::project::{PatchValue, text_prop};
+
+/// Structured contents of a card minted by [`build_vcard`]. Values are
+/// plain text, escaped when serialized.
+#[derive(Clone, Debug, Default)]
+pub struct NewCard {
+    pub uid: String,
+    /// REV timestamp, as formatted by [`rev_timestamp`].
+    pub rev: String,
+    pub fn_value: String,
+    /// N components: family, given, additional names, prefixes and
+    /// suffixes.
+    pub n: [String; 5],
+    pub nickname: Vec<String>,
+    pub email: Vec<PatchValue>,
+    pub tel: Vec<PatchValue>,
+    pub adr: Option<NewAddress>,
+    pub org: Option<String>,
+    pub title: Option<String>,
+    pub bday: Option<String>,
+    pub note: Option<String>,
+    pub categories: Vec<String>,
+}
+
+/// ADR components of a [`NewCard`], with its TYPE parameter values.
+#[derive(Clone, Debug, Default)]
+pub struct NewAddress {
+    pub types: Vec<String>,
+    pub street: String,
+    pub locality: String,
+    pub region: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+/// Serializes a [`NewCard`] as a vCard 4.0 document, through the CST
+/// like the projections. The N property is always written, empty
+/// components included, for the 3.0 consumers requiring it.
+pub fn build_vcard(card: &NewCard) -> String {
+    let mut vcard = VcardCst::v4();
+
+    vcard.push(text_prop(VcardPropKind::Uid, vec![], &card.uid));
+    vcard.push(text_prop(VcardPropKind::Rev, vec![], &card.rev));
+    vcard.push(text_prop(VcardPropKind::Fn, vec![], &card.fn_value));
+
+    let [family, given, additional, prefixes, suffixes] = &card.n;
+    vcard.push(VcardProp {
+        name: VcardPropName::Kind(VcardPropKind::N),
+        params: vec![],
+        value: VcardValue::N(VcardN {
+            family: component(family),
+            given: component(given),
+            additional: component(additional),
+            prefixes: component(prefixes),
+            suffixes: component(suffixes),
+        }),
+    });
+
+    for nickname in &card.nickname {
+        vcard.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Nickname),
+            params: vec![],
+            value: VcardValue::TextList(VcardTextList(vec![Cow::Owned(nickname.clone())])),
+        });
+    }
+    for email in &card.email {
+        let params = type_params(&email.types);
+        vcard.push(text_prop(VcardPropKind::Email, params, &email.value));
+    }
+    for tel in &card.tel {
+        let params = type_params(&tel.types);
+        vcard.push(text_prop(VcardPropKind::Tel, params, &tel.value));
+    }
+    if let Some(adr) = &card.adr {
+        vcard.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Adr),
+            params: type_params(&adr.types),
+            value: VcardValue::Adr(VcardAdr {
+                street: component(&adr.street),
+                locality: component(&adr.locality),
+                region: component(&adr.region),
+                postal_code: component(&adr.postal_code),
+                country: component(&adr.country),
+                ..Default::default()
+            }),
+        });
+    }
+    if let Some(org) = &card.org {
+        vcard.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Org),
+            params: vec![],
+            value: VcardValue::Org(VcardOrg(vec![Cow::Owned(org.clone())])),
+        });
+    }
+    if let Some(title) = &card.title {
+        vcard.push(text_prop(VcardPropKind::Title, vec![], title));
+    }
+    if let Some(bday) = &card.bday {
+        vcard.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Bday),
+            params: vec![],
+            value: VcardValue::DateAndOrTime(VcardDateAndOrTime(Cow::Owned(bday.clone()))),
+        });
+    }
+    if let Some(note) = &card.note {
+        vcard.push(text_prop(VcardPropKind::Note, vec![], note));
+    }
+    if !card.categories.is_empty() {
+        let categories = card.categories.iter().cloned().map(Cow::Owned).collect();
+        vcard.push(VcardProp {
+            name: VcardPropName::Kind(VcardPropKind::Categories),
+            params: vec![],
+            value: VcardValue::TextList(VcardTextList(categories)),
+        });
+    }
+
+    String::from_utf8_lossy(&vcard.to_bytes()).into_owned()
+}
+
+/// A structured value component: the value alone, or none when empty.
+fn component(value: &str) -> Vec<Cow<'static, str>> {
+    if value.is_empty() {
+        Vec::new()
+    } else {
+        vec![Cow::Owned(value.to_string())]
+    }
+}
+
+/// The TYPE parameter of `types`, none when empty.
+fn type_params(types: &[String]) -> Vec<VcardParam<'static>> {
+    if types.is_empty() {
+        return Vec::new();
+    }
+
+    let types = types.iter().cloned().map(Cow::Owned).collect();
+    vec![VcardParam::Type(types)]
+}
+
+/// Formats 16 random bytes as an RFC 4122 version 4 UUID.
+pub fn uuid_v4(mut bytes: [u8; 16]) -> String {
+    // NOTE: RFC 4122 4.4 stamps version 4 and variant 10xx.
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut out = String::with_capacity(36);
+    for (i, byte) in bytes.iter().enumerate() {
+        if matches!(i, 4 | 6 | 8 | 10) {
+            out.push('-');
+        }
+        out.push_str(&format!("{byte:02x}"));
+    }
+    out
+}
+
+/// Formats a Unix timestamp as a UTC vCard REV value
+/// (`yyyymmddThhmmssZ`).
+pub fn rev_timestamp(unix_secs: u64) -> String {
+    let days = (unix_secs / 86_400) as i64;
+    let secs = unix_secs % 86_400;
+
+    // NOTE: civil-from-days, from Howard Hinnant's date algorithms.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60
+    )
+}
+
+/// Splits an RFC 5322 mailbox into its display name and address:
+/// `Jane Doe <jane@doe.org>`, `"Doe, Jane" <jane@doe.org>`, or a bare
+/// `jane@doe.org`. Quoted names are unquoted; an empty name is none.
+/// Returns `None` when no plausible address is found.
+pub fn parse_mailbox(mailbox: &str) -> Option<(Option<String>, String)> {
+    let mailbox = mailbox.trim();
+
+    let (name, address) = match mailbox
+        .strip_suffix('>')
+        .and_then(|rest| rest.rsplit_once('<'))
+    {
+        Some((name, address)) => (name.trim(), address.trim()),
+        None => ("", mailbox),
+    };
+
+    let (local, domain) = address.split_once('@')?;
+    if local.is_empty() || domain.is_empty() || address.contains(char::is_whitespace) {
+        return None;
+    }
+
+    let name = match name
+        .strip_prefix('"')
+        .and_then(|name| name.strip_suffix('"'))
+    {
+        Some(quoted) => {
+            let mut unquoted = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => unquoted.extend(chars.next()),
+                    c => unquoted.push(c),
+                }
+            }
+            unquoted
+        }
+        None => name.to_string(),
+    };
+
+    let name = Some(name.trim().to_string()).filter(|name| !name.is_empty());
+    Some((name, address.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_new_vcards() {
+        let card = NewCard {
+            uid: "urn:uuid:abc".into(),
+            rev: rev_timestamp(1_709_210_096),
+            fn_value: "Jane Doe".into(),
+            n: ["Doe".into(), "Jane".into(), "".into(), "".into(), "".into()],
+            email: vec![PatchValue {
+                types: vec!["work".into()],
+                value: "jane@acme.org".into(),
+            }],
+            adr: Some(NewAddress {
+                street: "1 rue de la Paix".into(),
+                locality: "Paris".into(),
+                country: "France".into(),
+                ..Default::default()
+            }),
+            org: Some("Acme; Inc.".into()),
+            categories: vec!["friends".into(), "work".into()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            build_vcard(&card),
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:urn:uuid:abc\r\nREV:20240229T123456Z\r\n\
+             FN:Jane Doe\r\nN:Doe;Jane;;;\r\nEMAIL;TYPE=work:jane@acme.org\r\n\
+             ADR:;;1 rue de la Paix;Paris;;;France\r\nORG:Acme\\; Inc.\r\n\
+             CATEGORIES:friends,work\r\nEND:VCARD\r\n"
+        );
+
+        let card = NewCard {
+            note: Some("a long note ".repeat(10)),
+            ..card
+        };
+        assert!(build_vcard(&card).lines().all(|line| line.len() <= 75));
+
+        assert_eq!(rev_timestamp(0), "19700101T000000Z");
+        assert_eq!(uuid_v4([0xff; 16]), "ffffffff-ffff-4fff-bfff-ffffffffffff");
+    }
+
+    #[test]
+    fn parses_mailboxes() {
+        assert_eq!(
+            parse_mailbox(" Jane Doe <jane@doe.org> "),
+            Some((Some("Jane Doe".into()), "jane@doe.org".into()))
+        );
+        assert_eq!(
+            parse_mailbox(r#""Doe, \"JD\" Jane" <jane@doe.org>"#),
+            Some((Some(r#"Doe, "JD" Jane"#.into()), "jane@doe.org".into()))
+        );
+        assert_eq!(
+            parse_mailbox("<jane@doe.org>"),
+            Some((None, "jane@doe.org".into()))
+        );
+        assert_eq!(
+            parse_mailbox("jane@doe.org"),
+            Some((None, "jane@doe.org".into()))
+        );
+        assert_eq!(parse_mailbox("Jane Doe"), None);
+        assert_eq!(parse_mailbox("Jane <jane doe@doe.org>"), None);
+    }
+}
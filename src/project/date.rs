@@ -0,0 +1,242 @@
+//! Dates of a vCard: BDAY, ANNIVERSARY and the `X-ABDATE` labelled dates.
+
+use crate::project::{ContentLine, logical_lines, unescape_text, unfold};
+
+/// Calendar date of a date-valued property, the year being optional.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VcardDate {
+    pub year: Option<u16>,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// Parses a vCard date (`yyyy-mm-dd`, `yyyymmdd`, or the year-less
+/// `--mmdd` and `--mm-dd` of RFC 6350 4.3.1), ignoring any time part.
+/// None for other partial dates (`yyyy-mm`, `---dd`), text values and
+/// out-of-range months or days, February 29 only passing on leap or
+/// unknown years.
+pub fn parse_date(raw: &str) -> Option<VcardDate> {
+    let raw = raw.trim();
+    let date = raw.split_once('T').map_or(raw, |(date, _)| date);
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+
+    let (year, month, day) = match date.strip_prefix("--") {
+        Some(date) => match date.split('-').collect::<Vec<_>>()[..] {
+            [m, d] if m.len() == 2 && d.len() == 2 => (None, m, d),
+            [md] if md.len() == 4 => (None, &md[..2], &md[2..]),
+            _ => return None,
+        },
+        None => match date.split('-').collect::<Vec<_>>()[..] {
+            [y, m, d] if y.len() == 4 && m.len() == 2 && d.len() == 2 => (Some(y), m, d),
+            [ymd] if ymd.len() == 8 => (Some(&ymd[..4]), &ymd[4..6], &ymd[6..]),
+            _ => return None,
+        },
+    };
+
+    if ![month, day].into_iter().chain(year).all(digits) {
+        return None;
+    }
+
+    let date = VcardDate {
+        year: year.and_then(|year| year.parse().ok()),
+        month: month.parse().ok()?,
+        day: day.parse().ok()?,
+    };
+
+    let days = match date.month {
+        2 if date.year.is_some_and(|year| !is_leap_year(year)) => 28,
+        2 => 29,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+
+    (1..=days).contains(&date.day).then_some(date)
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+impl std::fmt::Display for VcardDate {
+    /// `yyyy-mm-dd`, or the vCard 4.0 year-less `--mmdd`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{year:04}-{:02}-{:02}", self.month, self.day),
+            None => write!(f, "--{:02}{:02}", self.month, self.day),
+        }
+    }
+}
+
+/// Kind of a [`CardDate`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CardDateKind {
+    Birthday,
+    Anniversary,
+    /// An Apple `X-ABDATE` with its `X-ABLabel`, e.g. a custom date of
+    /// a Google contact.
+    Custom(String),
+}
+
+/// One date of a card: its BDAY, ANNIVERSARY, or an `X-ABDATE`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CardDate {
+    pub kind: CardDateKind,
+    pub date: VcardDate,
+}
+
+/// The dates of a vCard, in card order. 3.0 spellings are read too
+/// (`X-ANNIVERSARY`, and `X-APPLE-OMIT-YEAR` year-less birthdays);
+/// unparsable values are skipped.
+pub fn vcard_dates(vcard: &str) -> Vec<CardDate> {
+    take_dates(vcard)
+        .0
+        .into_iter()
+        .map(|(date, _)| date)
+        .collect()
+}
+
+/// Splits the dates out of a vCard, for the projections with date
+/// slots: each [`CardDate`] of [`vcard_dates`] with its raw unfolded
+/// lines (an `X-ABDATE` comes with its `X-ABLabel`), and the vCard
+/// without them. Lines whose date does not parse stay in the vCard.
+pub fn take_dates(vcard: &str) -> (Vec<(CardDate, Vec<String>)>, String) {
+    let lines = logical_lines(vcard);
+    let parsed: Vec<Option<ContentLine>> = lines
+        .iter()
+        .map(|line| {
+            let mut line = ContentLine::parse(&unfold(line))?;
+            line.upgrade();
+            Some(line)
+        })
+        .collect();
+
+    // NOTE: Apple labels X-ABDATE through a grouped X-ABLabel, its
+    // well-known labels being spelled `_$!<Anniversary>!$_`.
+    let label_of = |group: &str| {
+        parsed.iter().position(|line| {
+            line.as_ref().is_some_and(|line| {
+                line.name == "X-ABLABEL"
+                    && line
+                        .group
+                        .as_deref()
+                        .is_some_and(|g| g.eq_ignore_ascii_case(group))
+            })
+        })
+    };
+
+    let mut taken = vec![false; lines.len()];
+    let mut dates = Vec::new();
+
+    for (index, line) in parsed.iter().enumerate() {
+        let Some(line) = line else {
+            continue;
+        };
+
+        let mut label = None;
+        let kind = match line.name.as_str() {
+            "BDAY" => CardDateKind::Birthday,
+            "ANNIVERSARY" => CardDateKind::Anniversary,
+            "X-ABDATE" => {
+                label = line.group.as_deref().and_then(&label_of);
+                let text = label
+                    .and_then(|label| parsed[label].as_ref())
+                    .map(|label| {
+                        let text = unescape_text(label.value.trim());
+                        let text = text.strip_prefix("_$!<").unwrap_or(&text);
+                        text.strip_suffix(">!$_").unwrap_or(text).to_string()
+                    })
+                    .unwrap_or_default();
+
+                if text.eq_ignore_ascii_case("anniversary") {
+                    CardDateKind::Anniversary
+                } else if text.is_empty() {
+                    CardDateKind::Custom("Other".into())
+                } else {
+                    CardDateKind::Custom(text)
+                }
+            }
+            _ => continue,
+        };
+
+        let Some(date) = parse_date(&line.value) else {
+            continue;
+        };
+
+        let mut raw = vec![unfold(&lines[index])];
+        taken[index] = true;
+        if let Some(label) = label {
+            raw.push(unfold(&lines[label]));
+            taken[label] = true;
+        }
+
+        dates.push((CardDate { kind, date }, raw));
+    }
+
+    let rest = lines
+        .iter()
+        .zip(taken)
+        .filter(|(_, taken)| !taken)
+        .map(|(line, _)| line.clone() + "\r\n")
+        .collect();
+
+    (dates, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::full_date;
+
+    #[test]
+    fn reads_full_and_year_less_dates() {
+        let date = |year, month, day| VcardDate { year, month, day };
+
+        assert_eq!(parse_date("1983-04-15"), Some(date(Some(1983), 4, 15)));
+        assert_eq!(
+            parse_date("19830415T000000Z"),
+            Some(date(Some(1983), 4, 15))
+        );
+        assert_eq!(parse_date("--0415"), Some(date(None, 4, 15)));
+        assert_eq!(parse_date("--04-15"), Some(date(None, 4, 15)));
+        assert_eq!(parse_date("1983-04"), None);
+        assert_eq!(parse_date("---15"), None);
+        assert_eq!(parse_date("1983-13-01"), None);
+        assert_eq!(parse_date("--0231"), None);
+        assert_eq!(parse_date("1983-04-31"), None);
+        assert_eq!(parse_date("--0229"), Some(date(None, 2, 29)));
+        assert_eq!(parse_date("2000-02-29"), Some(date(Some(2000), 2, 29)));
+        assert_eq!(parse_date("1900-02-29"), None);
+        assert_eq!(parse_date("circa 1800"), None);
+        assert_eq!(full_date("--0415"), None);
+        assert_eq!(full_date("19830415"), Some("1983-04-15".into()));
+
+        let vcard = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\n\
+            BDAY;X-APPLE-OMIT-YEAR=1604:1604-04-15\r\n\
+            X-ANNIVERSARY:2010-06-01\r\n\
+            item1.X-ABDATE:2015-09-01\r\nitem1.X-ABLabel:Adoption\\, first cat\r\n\
+            item2.X-ABDATE:2012-06-01\r\nitem2.X-ABLabel:_$!<Anniversary>!$_\r\n\
+            END:VCARD\r\n";
+        assert_eq!(
+            vcard_dates(vcard),
+            vec![
+                CardDate {
+                    kind: CardDateKind::Birthday,
+                    date: date(None, 4, 15),
+                },
+                CardDate {
+                    kind: CardDateKind::Anniversary,
+                    date: date(Some(2010), 6, 1),
+                },
+                CardDate {
+                    kind: CardDateKind::Custom("Adoption, first cat".into()),
+                    date: date(Some(2015), 9, 1),
+                },
+                CardDate {
+                    kind: CardDateKind::Anniversary,
+                    date: date(Some(2012), 6, 1),
+                },
+            ]
+        );
+    }
+}
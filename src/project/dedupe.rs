@@ -0,0 +1,378 @@
+//! Detection and merge of likely-duplicate vCards.
+
+use crate::project::{merge::prop_lines, search::prop_values};
+
+/// Property duplicate detection matches cards on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DedupeKey {
+    /// Email addresses, compared case-insensitively.
+    Email,
+    /// Phone numbers, compared E.164-normalized.
+    Tel,
+    /// Formatted and structured names, compared fuzzily.
+    Name,
+}
+
+/// Groups of likely-duplicate vCards, as indexes into `vcards`. Two
+/// cards belong to the same group when they share a normalized email
+/// address or phone number, or when their names match fuzzily (same
+/// words in any order, up to a typo); the relation is transitive.
+/// National phone numbers (leading `0`) only normalize when
+/// `country_code` is given. Groups hold at least two cards, in input
+/// order; unparsable vCards belong to none.
+pub fn duplicate_groups(
+    vcards: &[&[u8]],
+    keys: &[DedupeKey],
+    country_code: Option<&str>,
+) -> Vec<Vec<usize>> {
+    let fingerprints: Vec<Option<Fingerprint>> = vcards
+        .iter()
+        .map(|vcard| {
+            let vcard = std::str::from_utf8(vcard).ok()?;
+            let props = prop_values(vcard).ok()?;
+            Some(Fingerprint::new(&props, country_code))
+        })
+        .collect();
+
+    let mut parents: Vec<usize> = (0..vcards.len()).collect();
+
+    fn root(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+        index
+    }
+
+    for (i, left) in fingerprints.iter().enumerate() {
+        let Some(left) = left else { continue };
+        for (j, right) in fingerprints.iter().enumerate().skip(i + 1) {
+            let Some(right) = right else { continue };
+            if left.matches(right, keys) {
+                let (i, j) = (root(&mut parents, i), root(&mut parents, j));
+                parents[j] = i;
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut roots: Vec<usize> = Vec::new();
+
+    for index in 0..vcards.len() {
+        let root = root(&mut parents, index);
+        match roots.iter().position(|r| *r == root) {
+            Some(position) => groups[position].push(index),
+            None => {
+                roots.push(root);
+                groups.push(vec![index]);
+            }
+        }
+    }
+
+    groups.retain(|group| group.len() > 1);
+    groups
+}
+
+/// Normalized keys of one vCard, as compared by [`duplicate_groups`].
+struct Fingerprint {
+    emails: Vec<String>,
+    tels: Vec<String>,
+    names: Vec<String>,
+}
+
+impl Fingerprint {
+    fn new(props: &[(String, String)], country_code: Option<&str>) -> Self {
+        let mut fingerprint = Self {
+            emails: Vec::new(),
+            tels: Vec::new(),
+            names: Vec::new(),
+        };
+
+        for (name, value) in props {
+            let key = match name.as_str() {
+                "EMAIL" => normalize_email(value).map(|key| (&mut fingerprint.emails, key)),
+                "TEL" => normalize_tel(value, country_code).map(|key| (&mut fingerprint.tels, key)),
+                "FN" | "N" => name_key(value).map(|key| (&mut fingerprint.names, key)),
+                _ => None,
+            };
+
+            if let Some((keys, key)) = key
+                && !keys.contains(&key)
+            {
+                keys.push(key);
+            }
+        }
+
+        fingerprint
+    }
+
+    fn matches(&self, other: &Self, keys: &[DedupeKey]) -> bool {
+        let shares = |left: &[String], right: &[String]| left.iter().any(|key| right.contains(key));
+
+        keys.iter().any(|key| match key {
+            DedupeKey::Email => shares(&self.emails, &other.emails),
+            DedupeKey::Tel => shares(&self.tels, &other.tels),
+            DedupeKey::Name => self
+                .names
+                .iter()
+                .any(|left| other.names.iter().any(|right| similar_names(left, right))),
+        })
+    }
+}
+
+/// Lower-cased email address, without its `mailto:` prefix.
+fn normalize_email(value: &str) -> Option<String> {
+    let email = value.trim();
+    let email = email.strip_prefix("mailto:").unwrap_or(email);
+    let email = email.to_lowercase();
+    email.contains('@').then_some(email)
+}
+
+/// E.164 form of a phone number: a `+`, the country code and the
+/// subscriber digits. An international `00` prefix becomes `+`; a
+/// national number (leading `0`) takes `country_code` in place of its
+/// trunk prefix, and is kept as bare digits without one. Numbers too
+/// short to identify a line (service codes) normalize to nothing.
+pub fn normalize_tel(value: &str, country_code: Option<&str>) -> Option<String> {
+    let value = value.trim();
+    let value = value.strip_prefix("tel:").unwrap_or(value);
+    let value = value.split([';', 'x', 'X']).next()?;
+
+    let international = value.trim_start().starts_with('+');
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+
+    if digits.len() < 6 {
+        return None;
+    }
+
+    if international {
+        return Some(format!("+{digits}"));
+    }
+
+    if let Some(digits) = digits.strip_prefix("00") {
+        return Some(format!("+{digits}"));
+    }
+
+    match (digits.strip_prefix('0'), country_code) {
+        (Some(national), Some(code)) => {
+            let code = code.trim_start_matches('+');
+            Some(format!("+{code}{national}"))
+        }
+        _ => Some(digits),
+    }
+}
+
+/// Name key: the lower-cased words of a name, sorted so that
+/// `Jane Doe` (FN) and `Doe;Jane` (N) compare equal.
+fn name_key(value: &str) -> Option<String> {
+    let mut words: Vec<String> = value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+
+    if words.is_empty() {
+        return None;
+    }
+
+    words.sort();
+    words.dedup();
+    Some(words.join(" "))
+}
+
+/// Whether two name keys denote the same person: equal, or one typo
+/// apart for names long enough (two for long ones).
+fn similar_names(left: &str, right: &str) -> bool {
+    if left == right {
+        return true;
+    }
+
+    let len = left.chars().count().min(right.chars().count());
+    let tolerance = match len {
+        0..6 => return false,
+        6..12 => 1,
+        _ => 2,
+    };
+
+    edit_distance(left, right) <= tolerance
+}
+
+/// Levenshtein distance between two strings, over chars.
+fn edit_distance(left: &str, right: &str) -> usize {
+    let right: Vec<char> = right.chars().collect();
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+
+    for (i, l) in left.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, r) in right.iter().enumerate() {
+            let substitution = previous[j] + usize::from(l != *r);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[right.len()]
+}
+
+/// Result of [`merge_duplicates`].
+#[derive(Clone, Debug)]
+pub struct DuplicatesMerge {
+    /// Index of the card the others merge into: the one with the
+    /// newest REV, the first one without any.
+    pub kept: usize,
+    /// The merged vCard document.
+    pub vcard: String,
+}
+
+/// Merges a group of duplicate vCards into the one with the newest REV.
+/// The kept card's properties stay as they are; the EMAIL, TEL and ADR
+/// properties only the other cards carry are added next to the kept
+/// ones (or at the end when it has none), deduplicated on their
+/// normalized value.
+pub fn merge_duplicates(
+    vcards: &[&str],
+    country_code: Option<&str>,
+) -> Result<DuplicatesMerge, String> {
+    const UNION: [&str; 3] = ["EMAIL", "TEL", "ADR"];
+
+    let cards = vcards
+        .iter()
+        .map(|vcard| {
+            let lines = prop_lines(vcard)?;
+            let values = prop_values(vcard)?
+                .into_iter()
+                .filter(|(name, _)| name != "BEGIN" && name != "END");
+            Ok(lines.into_iter().zip(values).collect::<Vec<_>>())
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let rev = |index: usize| {
+        cards[index]
+            .iter()
+            .find(|((name, _), _)| name == "REV")
+            .map(|(_, (_, value))| {
+                value
+                    .chars()
+                    .filter(char::is_ascii_digit)
+                    .collect::<String>()
+            })
+    };
+
+    let mut kept = 0;
+    for index in 1..cards.len() {
+        if rev(index) > rev(kept) {
+            kept = index;
+        }
+    }
+
+    let key = |name: &str, value: &str| match name {
+        "EMAIL" => normalize_email(value),
+        "TEL" => normalize_tel(value, country_code),
+        _ => Some(
+            value
+                .to_lowercase()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        ),
+    };
+
+    let mut seen: Vec<(String, String)> = cards[kept]
+        .iter()
+        .filter(|((name, _), _)| UNION.contains(&name.as_str()))
+        .filter_map(|((name, _), (_, value))| Some((name.clone(), key(name, value)?)))
+        .collect();
+
+    let mut extra: Vec<(&str, &str)> = Vec::new();
+    for (index, card) in cards.iter().enumerate() {
+        if index == kept {
+            continue;
+        }
+
+        for ((name, line), (_, value)) in card {
+            if !UNION.contains(&name.as_str()) {
+                continue;
+            }
+            let Some(key) = key(name, value) else {
+                continue;
+            };
+            let entry = (name.clone(), key);
+            if !seen.contains(&entry) {
+                seen.push(entry);
+                extra.push((name, line));
+            }
+        }
+    }
+
+    let lines = &cards[kept];
+    let mut vcard = String::from("BEGIN:VCARD\r\n");
+
+    for (position, ((name, line), _)) in lines.iter().enumerate() {
+        vcard.push_str(line);
+        vcard.push_str("\r\n");
+
+        let last_of_name = lines[position + 1..]
+            .iter()
+            .all(|((other, _), _)| other != name);
+        if last_of_name {
+            for (_, line) in extra.iter().filter(|(other, _)| other == name) {
+                vcard.push_str(line);
+                vcard.push_str("\r\n");
+            }
+        }
+    }
+
+    for (_, line) in extra
+        .iter()
+        .filter(|(name, _)| !lines.iter().any(|((other, _), _)| other == name))
+    {
+        vcard.push_str(line);
+        vcard.push_str("\r\n");
+    }
+
+    vcard.push_str("END:VCARD\r\n");
+
+    Ok(DuplicatesMerge { kept, vcard })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_duplicates_by_email_tel_and_name() {
+        let cards = [
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane Doe\r\nEMAIL:Jane@Doe.org\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:J. D.\r\nEMAIL:jane@doe.org\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:John Smith\r\nTEL:06 12 34 56 78\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Johnny\r\nTEL:+33 6 12 34 56 78\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nN:Smyth;John;;;\r\nEND:VCARD\r\n",
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Bob\r\nEND:VCARD\r\n",
+        ];
+        let cards: Vec<&[u8]> = cards.iter().map(|card| card.as_bytes()).collect();
+        let all = [DedupeKey::Email, DedupeKey::Tel, DedupeKey::Name];
+
+        let groups = duplicate_groups(&cards, &all, Some("33"));
+        assert_eq!(groups, vec![vec![0, 1], vec![2, 3, 4]]);
+
+        let groups = duplicate_groups(&cards, &[DedupeKey::Tel], None);
+        assert!(groups.is_empty());
+    }
+
+    #[test]
+    fn merges_duplicates_into_newest() {
+        let old = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane\r\nEMAIL:jane@doe.org\r\n\
+            TEL:+33 1 11 11 11 11\r\nADR:;;1 rue;Paris;;;FR\r\nREV:20200101T000000Z\r\nEND:VCARD\r\n";
+        let new = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane Doe\r\nEMAIL:JANE@doe.org\r\n\
+            EMAIL:jane@acme.org\r\nREV:20240101T000000Z\r\nEND:VCARD\r\n";
+
+        let merge = merge_duplicates(&[old, new], None).unwrap();
+        assert_eq!(merge.kept, 1);
+        assert_eq!(
+            merge.vcard,
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane Doe\r\nEMAIL:JANE@doe.org\r\n\
+                EMAIL:jane@acme.org\r\nREV:20240101T000000Z\r\nTEL:+33 1 11 11 11 11\r\n\
+                ADR:;;1 rue;Paris;;;FR\r\nEND:VCARD\r\n"
+        );
+    }
+}
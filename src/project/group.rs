@@ -0,0 +1,214 @@
+//! CATEGORIES, and the group cards (`KIND:group`) listing members.
+
+use vcard::tree::line::VcardLine;
+
+use crate::project::{
+    ContentLine, escape_text, fold_line, read_cst, rewrite_lines, split_escaped, unescape_text,
+};
+
+/// The CATEGORIES of a vCard, unescaped, across every CATEGORIES line
+/// and in order.
+pub fn vcard_categories(vcard: &str) -> Vec<String> {
+    read_cst(vcard, |card| {
+        let mut categories: Vec<String> = Vec::new();
+
+        for line in &card.props {
+            if !line.name.get().eq_ignore_ascii_case("CATEGORIES") {
+                continue;
+            }
+
+            for category in split_escaped(&line.raw_value_str(), ',') {
+                let category = unescape_text(&category);
+                if !category.is_empty() && !categories.contains(&category) {
+                    categories.push(category);
+                }
+            }
+        }
+
+        categories
+    })
+    .unwrap_or_default()
+}
+
+/// Replaces the CATEGORIES of a vCard with one line holding
+/// `categories`, or drops them on an empty slice.
+pub fn set_categories(vcard: &str, categories: &[String]) -> String {
+    let added = (!categories.is_empty()).then(|| ContentLine {
+        name: "CATEGORIES".into(),
+        value: categories
+            .iter()
+            .map(|category| escape_text(category))
+            .collect::<Vec<_>>()
+            .join(","),
+        ..Default::default()
+    });
+
+    rewrite_lines(vcard, |line| line.name != "CATEGORIES", added)
+}
+
+/// A group card (RFC 6350 `KIND:group`, or Apple's 3.0
+/// `X-ADDRESSBOOKSERVER-KIND:group`): its formatted name and the UIDs
+/// of its members.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VcardGroup {
+    pub name: String,
+    /// Member UIDs, `urn:uuid:` prefix stripped.
+    pub members: Vec<String>,
+}
+
+impl VcardGroup {
+    /// Whether the card of UID `uid` is a member of the group.
+    pub fn contains(&self, uid: &str) -> bool {
+        let uid = member_uid(uid);
+        self.members.iter().any(|member| *member == uid)
+    }
+}
+
+/// The group a vCard stands for, `None` for any card that is not a
+/// group card.
+pub fn vcard_group(vcard: &str) -> Option<VcardGroup> {
+    read_cst(vcard, |card| {
+        let named = |line: &&VcardLine, names: &[&str]| {
+            let name = line.name.get();
+            names.iter().any(|other| name.eq_ignore_ascii_case(other))
+        };
+
+        let group = card
+            .props
+            .iter()
+            .filter(|line| named(line, &["KIND", "X-ADDRESSBOOKSERVER-KIND"]))
+            .any(|line| line.raw_value_str().trim().eq_ignore_ascii_case("group"));
+        if !group {
+            return None;
+        }
+
+        let name = card
+            .props
+            .iter()
+            .find(|line| named(line, &["FN"]))
+            .map(|line| unescape_text(&line.raw_value_str()))
+            .unwrap_or_default();
+
+        let members = card
+            .props
+            .iter()
+            .filter(|line| named(line, &["MEMBER", "X-ADDRESSBOOKSERVER-MEMBER"]))
+            .map(|line| member_uid(&line.raw_value_str()))
+            .filter(|member| !member.is_empty())
+            .collect();
+
+        Some(VcardGroup { name, members })
+    })
+    .flatten()
+}
+
+/// Adds the card of UID `uid` to a group card, or removes it, as a
+/// `MEMBER:urn:uuid:` line (`X-ADDRESSBOOKSERVER-MEMBER` on 3.0).
+pub fn set_group_member(vcard: &str, uid: &str, member: bool) -> String {
+    let uid = member_uid(uid);
+    let added = member.then(|| ContentLine {
+        name: "MEMBER".into(),
+        value: format!("urn:uuid:{uid}"),
+        ..Default::default()
+    });
+
+    rewrite_lines(
+        vcard,
+        |line| line.name != "MEMBER" || member_uid(&line.value) != uid,
+        added,
+    )
+}
+
+/// A new vCard 4.0 group card named `name`, holding the cards of the
+/// given UIDs.
+pub fn group_vcard(uid: &str, rev: &str, name: &str, members: &[String]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:4.0".into(),
+        "KIND:group".into(),
+        format!("UID:{uid}"),
+        format!("REV:{rev}"),
+        format!("FN:{}", escape_text(name)),
+    ];
+
+    for member in members {
+        lines.push(format!("MEMBER:urn:uuid:{}", member_uid(member)));
+    }
+
+    lines.push("END:VCARD".into());
+
+    lines.iter().map(|line| fold_line(line) + "\r\n").collect()
+}
+
+/// A MEMBER value or a UID, `urn:uuid:` prefix stripped, so that both
+/// compare.
+fn member_uid(value: &str) -> String {
+    let value = value.trim();
+    let uid = value
+        .get(..9)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("urn:uuid:"))
+        .map_or(value, |_| &value[9..]);
+    uid.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+        EMAIL:jane@doe.org\r\nTEL:+331111\r\nNOTE:a note\r\nEND:VCARD\r\n";
+
+    #[test]
+    fn reads_and_sets_categories() {
+        let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nCATEGORIES:Work,R\\,D\r\n\
+            CATEGORIES:Friends,Work\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_categories(vcard), vec!["Work", "R,D", "Friends"]);
+
+        let set = set_categories(vcard, &["Family".into(), "R,D".into()]);
+        assert_eq!(
+            set,
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nCATEGORIES:Family,R\\,D\r\nEND:VCARD\r\n"
+        );
+        assert_eq!(
+            set_categories(&set, &[]),
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn reads_and_edits_group_cards() {
+        let group = group_vcard(
+            "urn:uuid:g",
+            "20240101T000000Z",
+            "Friends",
+            &["urn:uuid:a".into()],
+        );
+        assert_eq!(
+            vcard_group(&group),
+            Some(VcardGroup {
+                name: "Friends".into(),
+                members: vec!["a".into()],
+            })
+        );
+
+        let group = set_group_member(&group, "b", true);
+        let group = set_group_member(&group, "urn:uuid:a", false);
+        let parsed = vcard_group(&group).unwrap();
+        assert_eq!(parsed.members, vec!["b".to_string()]);
+        assert!(parsed.contains("urn:uuid:b"));
+
+        let apple = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Team\r\n\
+            X-ADDRESSBOOKSERVER-KIND:group\r\n\
+            X-ADDRESSBOOKSERVER-MEMBER:urn:uuid:a\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_group(apple).unwrap().members, vec!["a".to_string()]);
+        assert_eq!(
+            set_group_member(apple, "c", true),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Team\r\n\
+             X-ADDRESSBOOKSERVER-KIND:group\r\n\
+             X-ADDRESSBOOKSERVER-MEMBER:urn:uuid:a\r\n\
+             X-ADDRESSBOOKSERVER-MEMBER:urn:uuid:c\r\nEND:VCARD\r\n"
+        );
+
+        assert_eq!(vcard_group(BASE), None);
+    }
+}
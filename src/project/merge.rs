@@ -0,0 +1,232 @@
+//! Property-level three-way merge of vCard documents.
+
+use vcard::tree::cst::VcardCst;
+
+use crate::project::unfold;
+
+/// Side winning the properties a [`merge_vcards`] cannot settle.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MergeSide {
+    Ours,
+    Theirs,
+}
+
+/// Outcome of a [`merge_vcards`] three-way merge.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct VcardMerge {
+    /// The merged vCard document.
+    pub vcard: String,
+    /// Names of the properties both sides changed differently, settled
+    /// by the preferred side.
+    pub conflicts: Vec<String>,
+}
+
+/// Property-level three-way merge of two vCard documents derived from
+/// `base`. Each property name is merged as a unit (all its lines, in
+/// order): a side that left it as in `base` takes the other side's
+/// version, while two sides changing it differently conflict and
+/// `prefer` wins. REV and PRODID change on nearly every edit, so they
+/// never conflict: the newer REV wins, and PRODID follows `prefer`.
+/// Properties come out in `theirs` order, followed by the ones only
+/// `ours` carries; VERSION stays first.
+pub fn merge_vcards(
+    base: &str,
+    ours: &str,
+    theirs: &str,
+    prefer: MergeSide,
+) -> Result<VcardMerge, String> {
+    let base = prop_lines(base)?;
+    let ours = prop_lines(ours)?;
+    let theirs = prop_lines(theirs)?;
+
+    let mut names: Vec<&str> = Vec::new();
+    for (name, _) in theirs.iter().chain(&ours) {
+        if !names.contains(&name.as_str()) {
+            names.push(name);
+        }
+    }
+    names.sort_by_key(|name| *name != "VERSION");
+
+    let mut merge = VcardMerge::default();
+    let mut vcard = String::from("BEGIN:VCARD\r\n");
+
+    for name in names {
+        let base_lines = lines_of(&base, name);
+        let our_lines = lines_of(&ours, name);
+        let their_lines = lines_of(&theirs, name);
+
+        let lines = if our_lines == their_lines || their_lines == base_lines {
+            our_lines
+        } else if our_lines == base_lines {
+            their_lines
+        } else if name == "REV" {
+            if rev_digits(&our_lines) > rev_digits(&their_lines) {
+                our_lines
+            } else {
+                their_lines
+            }
+        } else {
+            if name != "PRODID" {
+                merge.conflicts.push(name.to_string());
+            }
+            match prefer {
+                MergeSide::Ours => our_lines,
+                MergeSide::Theirs => their_lines,
+            }
+        };
+
+        for line in lines {
+            vcard.push_str(line);
+            vcard.push_str("\r\n");
+        }
+    }
+
+    vcard.push_str("END:VCARD\r\n");
+    merge.vcard = vcard;
+
+    Ok(merge)
+}
+
+/// Whether two vCards hold the same properties, whatever their order
+/// and folding, REV and PRODID aside: the differences a backend
+/// introduces when it normalizes a stored card.
+pub fn same_vcard(a: &str, b: &str) -> bool {
+    let normalized = |vcard: &str| {
+        let mut lines: Vec<String> = prop_lines(vcard)
+            .ok()?
+            .into_iter()
+            .filter(|(name, _)| name != "REV" && name != "PRODID")
+            .map(|(_, line)| unfold(&line))
+            .collect();
+        lines.sort();
+        Some(lines)
+    };
+
+    match (normalized(a), normalized(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Raw logical property lines of a vCard, each keyed by its upper-cased
+/// property name.
+pub(super) fn prop_lines(vcard: &str) -> Result<Vec<(String, String)>, String> {
+    let card = VcardCst::parse(vcard).map_err(|err| format!("Invalid vCard: {err}"))?;
+
+    Ok(card
+        .props
+        .iter()
+        .map(|line| {
+            let name = line.name.get().to_ascii_uppercase();
+            let raw = line.to_string().trim_end().to_string();
+            (name, raw)
+        })
+        .filter(|(name, _)| name != "BEGIN" && name != "END")
+        .collect())
+}
+
+/// Digits of the first REV value, padded so that the basic
+/// (`20240101T120000Z`) and extended (`2024-01-01T12:00:00Z`) forms
+/// of RFC 6350 4.3.5 compare in time order.
+fn rev_digits(lines: &[&str]) -> String {
+    let value = lines
+        .first()
+        .and_then(|line| line.split_once(':'))
+        .map_or("", |(_, value)| value);
+
+    let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+    format!("{digits:0<14}")
+}
+
+/// Lines of the property `name`, in document order.
+fn lines_of<'a>(props: &'a [(String, String)], name: &str) -> Vec<&'a str> {
+    props
+        .iter()
+        .filter(|(prop, _)| prop == name)
+        .map(|(_, line)| line.as_str())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+        EMAIL:jane@doe.org\r\nTEL:+331111\r\nNOTE:a note\r\nEND:VCARD\r\n";
+
+    #[test]
+    fn merge_takes_both_sides_edits() {
+        let ours = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+            EMAIL:jane@doe.org\r\nEMAIL;TYPE=work:jane@acme.org\r\nTEL:+331111\r\n\
+            NOTE:a note\r\nEND:VCARD\r\n";
+        let theirs = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+            EMAIL:jane@doe.org\r\nTEL:+332222\r\nEND:VCARD\r\n";
+
+        let merge = merge_vcards(BASE, ours, theirs, MergeSide::Theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+        assert_eq!(
+            merge.vcard,
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+                EMAIL:jane@doe.org\r\nEMAIL;TYPE=work:jane@acme.org\r\n\
+                TEL:+332222\r\nEND:VCARD\r\n"
+        );
+    }
+
+    #[test]
+    fn merge_reports_conflicts() {
+        let ours = BASE.replace("FN:Jane Doe", "FN:Jane Q. Doe");
+        let theirs = BASE.replace("FN:Jane Doe", "FN:Janet Doe");
+
+        let merge = merge_vcards(BASE, &ours, &theirs, MergeSide::Ours).unwrap();
+        assert_eq!(merge.conflicts, vec!["FN".to_string()]);
+        assert!(merge.vcard.contains("FN:Jane Q. Doe\r\n"));
+
+        let merge = merge_vcards(BASE, &ours, &theirs, MergeSide::Theirs).unwrap();
+        assert!(merge.vcard.contains("FN:Janet Doe\r\n"));
+    }
+
+    #[test]
+    fn merge_settles_rev_and_prodid() {
+        let base = BASE.replace(
+            "NOTE:a note",
+            "NOTE:a note\r\nREV:20240101T000000Z\r\nPRODID:-//a//EN",
+        );
+        let ours = base
+            .replace("FN:Jane Doe", "FN:Jane Q. Doe")
+            .replace("REV:20240101T000000Z", "REV:2024-03-01T10:00:00Z")
+            .replace("PRODID:-//a//EN", "PRODID:-//ours//EN");
+        let theirs = base
+            .replace("TEL:+331111", "TEL:+332222")
+            .replace("REV:20240101T000000Z", "REV:20240201T000000Z")
+            .replace("PRODID:-//a//EN", "PRODID:-//theirs//EN");
+
+        let merge = merge_vcards(&base, &ours, &theirs, MergeSide::Theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+        assert!(merge.vcard.contains("FN:Jane Q. Doe\r\n"));
+        assert!(merge.vcard.contains("TEL:+332222\r\n"));
+        assert!(merge.vcard.contains("REV:2024-03-01T10:00:00Z\r\n"));
+        assert!(merge.vcard.contains("PRODID:-//theirs//EN\r\n"));
+    }
+
+    #[test]
+    fn same_vcard_ignores_normalization() {
+        let normalized = "BEGIN:VCARD\r\nVERSION:4.0\r\nPRODID:-//server//EN\r\n\
+            REV:20240101T000000Z\r\nUID:abc\r\nEMAIL:jane@doe.org\r\nFN:Jane\r\n  Doe\r\n\
+            TEL:+331111\r\nNOTE:a note\r\nEND:VCARD\r\n";
+
+        assert!(same_vcard(BASE, normalized));
+        assert!(!same_vcard(
+            BASE,
+            &BASE.replace("TEL:+331111", "TEL:+332222")
+        ));
+    }
+
+    #[test]
+    fn merge_of_identical_edits_is_clean() {
+        let edit = BASE.replace("NOTE:a note", "NOTE:another note");
+
+        let merge = merge_vcards(BASE, &edit, &edit, MergeSide::Theirs).unwrap();
+        assert!(merge.conflicts.is_empty());
+        assert!(merge.vcard.contains("NOTE:another note\r\n"));
+    }
+}
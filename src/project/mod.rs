@@ -0,0 +1,395 @@
+//! Helpers shared by the vCard projections of the API backends
+//! (Microsoft Graph, Google People), ported from cardamum-android, and
+//! by the shared card commands.
+//!
+//! The API backends expose no vCard representation of a contact, so
+//! their projections synthesize the vCard document of record
+//! themselves; these helpers cover the pieces every projection needs
+//! (canonical text properties, date normalization, stash splicing and
+//! RFC 6350 text escaping). The listing preview, the property-level
+//! three-way merge, the search matcher and the duplicate detection
+//! work on any vCard document, whatever backend it comes from.
+
+mod convert;
+mod create;
+mod date;
+mod dedupe;
+mod group;
+mod merge;
+mod patch;
+mod photo;
+mod preview;
+mod search;
+mod stream;
+
+use std::borrow::Cow;
+
+use vcard::{
+    param::VcardParam,
+    prop::{VcardProp, VcardPropKind, VcardPropName},
+    tree::cst::VcardCst,
+    value::{VcardValue, text::VcardText},
+};
+
+use crate::shared::card::VcardVersion;
+
+#[doc(inline)]
+pub use self::{
+    convert::*, create::*, date::*, dedupe::*, group::*, merge::*, patch::*, photo::*, preview::*,
+    search::*, stream::*,
+};
+
+/// Longest raw property line the provider backends stash server-side.
+/// Longer lines (base64 LOGO or SOUND blobs, essentially; inline
+/// PHOTOs go through the provider photo endpoints) stay only in the
+/// local document of record instead of risking the whole write against
+/// undocumented provider size limits.
+pub const MAX_STASH_LINE: usize = 8 * 1024;
+
+/// A canonical text property built from an owned value.
+pub fn text_prop(
+    kind: VcardPropKind,
+    params: Vec<VcardParam<'static>>,
+    value: &str,
+) -> VcardProp<'static> {
+    VcardProp {
+        name: VcardPropName::Kind(kind),
+        params,
+        value: VcardValue::Text(VcardText(Cow::Owned(value.to_string()))),
+    }
+}
+
+/// Normalizes a BDAY value to `yyyy-mm-dd`, or None for anything
+/// partial, for the date slots that need a year (Graph's `birthday`).
+/// Year-less dates go through [`parse_date`].
+pub fn full_date(raw: &str) -> Option<String> {
+    parse_date(raw)
+        .filter(|date| date.year.is_some())
+        .map(|date| date.to_string())
+}
+
+/// Splices raw property lines (logical lines without their ending)
+/// into a serialized vCard, right before its END:VCARD line.
+pub fn splice_props(vcard: String, lines: &[String]) -> String {
+    if lines.is_empty() {
+        return vcard;
+    }
+
+    let mut extra = lines.join("\r\n");
+    extra.push_str("\r\n");
+
+    match vcard.rfind("END:VCARD") {
+        Some(position) => {
+            let mut out = vcard;
+            out.insert_str(position, &extra);
+            out
+        }
+        None => vcard + &extra,
+    }
+}
+
+/// Escapes a text value for a minted property line (RFC 6350 3.4:
+/// backslash, comma, semicolon and newline).
+pub fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '\\' => out.push_str("\\\\"),
+            ',' => out.push_str("\\,"),
+            ';' => out.push_str("\\;"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            _ => out.push(character),
+        }
+    }
+    out
+}
+
+/// Reverses [`escape_text`], turning unescaped component separators
+/// (`;`) into spaces.
+fn unescape_text(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => out.push('\n'),
+                Some(escaped) => out.push(escaped),
+                None => out.push('\\'),
+            },
+            ';' => out.push(' '),
+            _ => out.push(character),
+        }
+    }
+
+    out.trim().to_string()
+}
+
+/// Keeps the content lines of a vCard `keep` accepts (read in their
+/// 4.0 spelling) and inserts `added` (4.0 spelling, downgraded on a 3.0
+/// card, folded) before `END:VCARD`. Kept lines stay as they are,
+/// folding included.
+fn rewrite_lines(
+    vcard: &str,
+    keep: impl Fn(&ContentLine) -> bool,
+    added: impl IntoIterator<Item = ContentLine>,
+) -> String {
+    // NOTE: logical lines keep their continuations, so a dropped line
+    // takes its whole folded value along.
+    let mut lines = logical_lines(vcard);
+
+    let parse = |line: &str| ContentLine::parse(&unfold(line));
+
+    let v3 = lines
+        .iter()
+        .filter_map(|line| parse(line))
+        .any(|line| line.name == "VERSION" && matches!(line.value.trim(), "2.1" | "3.0"));
+
+    lines.retain(|line| match parse(line) {
+        Some(mut line) => {
+            line.upgrade();
+            keep(&line)
+        }
+        None => true,
+    });
+
+    let added = added.into_iter().map(|mut line| {
+        if v3 {
+            line.downgrade();
+        }
+        fold_line(&line.to_string())
+    });
+
+    let end = lines
+        .iter()
+        .rposition(|line| parse(line).is_some_and(|line| line.name == "END"))
+        .unwrap_or(lines.len());
+    lines.splice(end..end, added);
+
+    lines.iter().map(|line| line.clone() + "\r\n").collect()
+}
+
+/// The logical content lines of a vCard, each with its folded
+/// continuation lines, line endings excluded.
+fn logical_lines(vcard: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in vcard.lines() {
+        let line = line.trim_end_matches('\r');
+        match lines.last_mut() {
+            Some(last) if line.starts_with([' ', '\t']) => {
+                last.push_str("\r\n");
+                last.push_str(line);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+/// Folds a logical content line at 75 octets, never inside a
+/// character. iCalendar lines fold the same way (RFC 5545 3.1).
+pub fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / 25);
+    let mut width = 0;
+
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+
+    folded
+}
+
+/// Joins folded content lines back into logical lines.
+fn unfold(vcard: &str) -> String {
+    vcard
+        .replace("\r\n ", "")
+        .replace("\r\n\t", "")
+        .replace("\n ", "")
+        .replace("\n\t", "")
+}
+
+/// Reads a vCard through its CST, or through the CST of its 4.0
+/// conversion when the parser rejects it as it is (vCard 2.1, loose
+/// 3.0). `None` when neither parses.
+fn read_cst<T>(vcard: &str, read: impl FnOnce(&VcardCst) -> T) -> Option<T> {
+    if let Ok(card) = VcardCst::parse(vcard) {
+        return Some(read(&card));
+    }
+
+    let converted = convert_vcard(vcard, VcardVersion::V4);
+    let card = VcardCst::parse(&converted).ok()?;
+    Some(read(&card))
+}
+
+/// Splits `text` on `separator` outside backslash escapes, escapes
+/// kept.
+fn split_escaped(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let part = parts.last_mut().unwrap();
+                part.push(c);
+                part.extend(chars.next());
+            }
+            c if c == separator => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts
+}
+
+/// Encodings vCard 2.1 writes as bare parameters.
+const BARE_ENCODINGS: [&str; 4] = ["QUOTED-PRINTABLE", "BASE64", "8BIT", "7BIT"];
+
+/// One unfolded vCard content line: `[group.]NAME[;PARAM=v,...]:value`.
+/// Only used where lines are rewritten in place or converted between
+/// versions; property lookups go through the CST ([`read_cst`]).
+#[derive(Clone, Debug, Default)]
+struct ContentLine {
+    group: Option<String>,
+    name: String,
+    params: Vec<(String, Vec<String>)>,
+    value: String,
+}
+
+impl ContentLine {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        // NOTE: the value starts at the first colon outside quoted
+        // parameter values.
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(index, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(index),
+            _ => None,
+        })?;
+
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = split_unquoted(head, ';').into_iter();
+        let name = parts.next()?;
+
+        let (group, name) = match name.split_once('.') {
+            Some((group, name)) => (Some(group.to_string()), name),
+            None => (None, name.as_str()),
+        };
+
+        let params = parts
+            .map(|param| match param.split_once('=') {
+                Some((name, values)) => (
+                    name.to_ascii_uppercase(),
+                    split_unquoted(values, ',')
+                        .into_iter()
+                        .map(|value| value.trim_matches('"').to_string())
+                        .collect(),
+                ),
+                // NOTE: vCard 2.1 bare parameters are encodings or
+                // types.
+                None if BARE_ENCODINGS
+                    .iter()
+                    .any(|encoding| param.eq_ignore_ascii_case(encoding)) =>
+                {
+                    ("ENCODING".to_string(), vec![param])
+                }
+                None => ("TYPE".to_string(), vec![param]),
+            })
+            .collect();
+
+        Some(Self {
+            group,
+            name: name.to_ascii_uppercase(),
+            params,
+            value: value.to_string(),
+        })
+    }
+
+    fn param(&self, name: &str) -> Option<&[String]> {
+        self.params
+            .iter()
+            .find(|(param, _)| param == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    fn remove_param(&mut self, name: &str) -> Vec<String> {
+        let mut removed = Vec::new();
+        self.params.retain(|(param, values)| {
+            if param == name {
+                removed.extend(values.iter().cloned());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    fn add_type(&mut self, value: &str) {
+        match self.params.iter_mut().find(|(param, _)| param == "TYPE") {
+            Some((_, values)) => values.push(value.to_string()),
+            None => self.params.push(("TYPE".into(), vec![value.to_string()])),
+        }
+    }
+}
+
+impl std::fmt::Display for ContentLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(group) = &self.group {
+            write!(f, "{group}.")?;
+        }
+
+        write!(f, "{}", self.name)?;
+
+        for (name, values) in &self.params {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| {
+                    if value.contains([':', ';', ',']) {
+                        format!("\"{value}\"")
+                    } else {
+                        value.clone()
+                    }
+                })
+                .collect();
+            write!(f, ";{name}={}", values.join(","))?;
+        }
+
+        write!(f, ":{}", self.value)
+    }
+}
+
+/// Splits `text` on `separator` outside double quotes.
+fn split_unquoted(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                parts.last_mut().unwrap().push(c);
+            }
+            c if c == separator && !quoted => parts.push(String::new()),
+            c => parts.last_mut().unwrap().push(c),
+        }
+    }
+
+    parts
+}
@@ -0,0 +1,118 @@
+//! Property-level edits of a vCard through the CST.
+
+use std::{borrow::Cow, str::FromStr};
+
+use vcard::{
+    param::VcardParam,
+    prop::{VcardProp, VcardPropKind, VcardPropName},
+    tree::cst::VcardCst,
+    value::{VcardValue, uri::VcardUri},
+};
+
+use crate::project::text_prop;
+
+/// One value of a property set by [`patch_vcard`], with its TYPE
+/// parameter values.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PatchValue {
+    pub types: Vec<String>,
+    pub value: String,
+}
+
+/// Property-level edit of a vCard, applied by [`patch_vcard`].
+#[derive(Clone, Debug, Default)]
+pub struct VcardPatch {
+    /// Properties to replace, by name: every existing line of the
+    /// property is dropped, then one line per value is added.
+    pub set: Vec<(String, Vec<PatchValue>)>,
+    /// Names of the properties to drop, whatever their kind.
+    pub unset: Vec<String>,
+}
+
+/// Applies `patch` to a vCard through the CST, leaving every other
+/// line untouched. Removals apply before replacements, so unsetting
+/// and setting a property replaces it.
+pub fn patch_vcard(vcard: &str, patch: &VcardPatch) -> Result<String, String> {
+    let mut card = VcardCst::parse(vcard).map_err(|err| format!("Invalid vCard: {err}"))?;
+
+    for name in &patch.unset {
+        if ["BEGIN", "END", "VERSION"].contains(&name.to_ascii_uppercase().as_str()) {
+            return Err(format!("Cannot unset the structural {name} property"));
+        }
+    }
+
+    card.props.retain(|line| {
+        let name = line.name.get();
+        let unset = patch
+            .unset
+            .iter()
+            .any(|prop| name.eq_ignore_ascii_case(prop));
+        let replaced = patch
+            .set
+            .iter()
+            .any(|(prop, _)| name.eq_ignore_ascii_case(prop));
+        !unset && !replaced
+    });
+
+    for (name, values) in &patch.set {
+        for PatchValue { types, value } in values {
+            let kind = VcardPropKind::from_str(name)
+                .map_err(|_| format!("Cannot set the unknown {name} property"))?;
+
+            let params = if types.is_empty() {
+                vec![]
+            } else {
+                let types = types.iter().map(|ty| Cow::Owned(ty.clone())).collect();
+                vec![VcardParam::Type(types)]
+            };
+
+            let prop = match kind {
+                VcardPropKind::Url => VcardProp {
+                    name: VcardPropName::Kind(VcardPropKind::Url),
+                    params,
+                    value: VcardValue::Uri(VcardUri(Cow::Owned(value.clone()))),
+                },
+                kind => text_prop(kind, params, value),
+            };
+
+            card.push(prop);
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&card.to_bytes()).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+        EMAIL:jane@doe.org\r\nTEL:+331111\r\nNOTE:a note\r\nEND:VCARD\r\n";
+
+    #[test]
+    fn patches_properties() {
+        let patch = VcardPatch {
+            set: vec![(
+                "EMAIL".into(),
+                vec![PatchValue {
+                    types: vec!["work".into()],
+                    value: "jane@acme.org".into(),
+                }],
+            )],
+            unset: vec!["note".into()],
+        };
+
+        let vcard = patch_vcard(BASE, &patch).unwrap();
+        assert!(vcard.contains("FN:Jane Doe\r\n"));
+        assert!(vcard.contains("TEL:+331111\r\n"));
+        assert!(vcard.contains("EMAIL;TYPE=work:jane@acme.org\r\n"));
+        assert!(!vcard.contains("jane@doe.org"));
+        assert!(!vcard.contains("NOTE"));
+
+        let patch = VcardPatch {
+            unset: vec!["VERSION".into()],
+            ..Default::default()
+        };
+        assert!(patch_vcard(BASE, &patch).is_err());
+    }
+}
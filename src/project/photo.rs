@@ -0,0 +1,164 @@
+//! Inline and URI photos of a vCard.
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+
+use crate::project::{ContentLine, rewrite_lines, unfold};
+
+/// Picture of a card, as held by its PHOTO property.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VcardPhoto {
+    /// Image embedded in the card: a 4.0 `data:` URI or a 3.0
+    /// `ENCODING=b` value.
+    Inline { media_type: String, bytes: Vec<u8> },
+    /// Image referenced by URI.
+    Uri(String),
+}
+
+/// The picture of the first PHOTO property of a vCard, if any.
+pub fn vcard_photo(vcard: &str) -> Option<VcardPhoto> {
+    unfold(vcard).lines().find_map(parse_photo)
+}
+
+/// The picture of a raw PHOTO property line; `None` for any other
+/// property, or an empty or undecodable value.
+pub fn parse_photo(line: &str) -> Option<VcardPhoto> {
+    let mut line = ContentLine::parse(&unfold(line))?;
+    if line.name != "PHOTO" {
+        return None;
+    }
+
+    // NOTE: the 4.0 upgrade turns an ENCODING=b value into a data URI.
+    line.upgrade();
+    let value = line.value.trim();
+
+    let Some(data) = value.strip_prefix("data:") else {
+        return (!value.is_empty()).then(|| VcardPhoto::Uri(value.to_string()));
+    };
+
+    let (media_type, data) = data.split_once(";base64,")?;
+    let data: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    let bytes = BASE64_STANDARD.decode(data).ok()?;
+
+    let media_type = match media_type {
+        "" | "application/octet-stream" => image_media_type(&bytes).unwrap_or(media_type),
+        _ => media_type,
+    };
+
+    Some(VcardPhoto::Inline {
+        media_type: media_type.to_string(),
+        bytes,
+    })
+}
+
+/// PHOTO property line embedding the image as a 4.0 `data:` URI.
+pub fn photo_line(media_type: &str, bytes: &[u8]) -> String {
+    format!(
+        "PHOTO:data:{media_type};base64,{}",
+        BASE64_STANDARD.encode(bytes)
+    )
+}
+
+/// Media type of a JPEG, PNG, GIF or WebP image, sniffed from its
+/// leading bytes.
+pub fn image_media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if bytes.starts_with(b"GIF8") {
+        Some("image/gif")
+    } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else {
+        None
+    }
+}
+
+/// Replaces every PHOTO property of a vCard with the given inline
+/// picture, or drops them all on `None`. The picture goes in as a
+/// `data:` URI, or as an `ENCODING=b` value on a 3.0 card, folded at
+/// 75 octets and placed before `END:VCARD`; other lines are kept as
+/// they are, folding included.
+pub fn replace_photo(vcard: &str, photo: Option<(&str, &[u8])>) -> String {
+    let added = photo.map(|(media_type, bytes)| ContentLine {
+        name: "PHOTO".into(),
+        value: format!("data:{media_type};base64,{}", BASE64_STANDARD.encode(bytes)),
+        ..Default::default()
+    });
+
+    rewrite_lines(vcard, |line| line.name != "PHOTO", added)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:abc\r\nFN:Jane Doe\r\n\
+        EMAIL:jane@doe.org\r\nTEL:+331111\r\nNOTE:a note\r\nEND:VCARD\r\n";
+
+    #[test]
+    fn reads_inline_and_uri_photos() {
+        let png = b"\x89PNG\r\n\x1a\n".to_vec();
+        let inline = Some(VcardPhoto::Inline {
+            media_type: "image/png".into(),
+            bytes: png.clone(),
+        });
+
+        let v4 = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\n\
+            PHOTO:data:image/png;base64,iVBO\r\n Rw0KGgo=\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_photo(v4), inline);
+
+        // NOTE: the media type of an untyped 3.0 photo is sniffed.
+        let v3 = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\n\
+            PHOTO;ENCODING=b:iVBORw0KGgo=\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_photo(v3), inline);
+
+        let uri = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\n\
+            PHOTO;VALUE=uri:https://example.org/x.png\r\nEND:VCARD\r\n";
+        assert_eq!(
+            vcard_photo(uri),
+            Some(VcardPhoto::Uri("https://example.org/x.png".into()))
+        );
+
+        assert_eq!(vcard_photo(BASE), None);
+        assert_eq!(
+            photo_line("image/png", &png),
+            "PHOTO:data:image/png;base64,iVBORw0KGgo="
+        );
+    }
+
+    #[test]
+    fn replaces_and_removes_photos() {
+        let png: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+        let v4 = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\n\
+            PHOTO:https://example.org/x.png\r\nNOTE:a\r\n b\r\nEND:VCARD\r\n";
+        assert_eq!(
+            replace_photo(v4, Some(("image/png", png))),
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:X\r\nNOTE:a\r\n b\r\n\
+             PHOTO:data:image/png;base64,iVBORw0KGgo=\r\nEND:VCARD\r\n"
+        );
+
+        let v3 = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\n\
+            PHOTO;ENCODING=b;TYPE=JPEG:/9j/4AAQ\r\n SkZJRg==\r\nEND:VCARD\r\n";
+        assert_eq!(
+            replace_photo(v3, Some(("image/png", png))),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\n\
+             PHOTO;ENCODING=b;TYPE=PNG:iVBORw0KGgo=\r\nEND:VCARD\r\n"
+        );
+        assert_eq!(
+            replace_photo(v3, None),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:X\r\nEND:VCARD\r\n"
+        );
+
+        let large = replace_photo(v4, Some(("image/png", &[0; 200][..])));
+        assert!(large.lines().all(|line| line.trim_end().len() <= 75));
+        assert_eq!(
+            vcard_photo(&large),
+            Some(VcardPhoto::Inline {
+                media_type: "image/png".into(),
+                bytes: vec![0; 200],
+            })
+        );
+    }
+}
@@ -0,0 +1,168 @@
+//! What a listing shows of a vCard: its preferred values and UID.
+
+use std::str::FromStr;
+
+use vcard::{
+    prop::VcardPropKind,
+    tree::{
+        cst::VcardCst,
+        line::VcardLine,
+        prop::{VcardPropLens, email::EMAIL, r#fn::FN, tel::TEL},
+    },
+};
+
+use crate::project::read_cst;
+
+/// Formatted name, preferred email address and preferred phone number
+/// of a vCard, for the cards listing. Lines are read through the CST,
+/// so folded lines, property groups (`item1.EMAIL`), quoted parameters
+/// and escaped characters are honored; among several emails or phones,
+/// the lowest `PREF` (or a 3.0 `TYPE=pref`) wins, then the first one. A
+/// card the parser rejects is read through its 4.0 conversion.
+pub fn vcard_preview(vcard: &[u8]) -> (Option<String>, Option<String>, Option<String>) {
+    read_cst(&String::from_utf8_lossy(vcard), preferred_values).unwrap_or_default()
+}
+
+fn preferred_values(card: &VcardCst) -> (Option<String>, Option<String>, Option<String>) {
+    let version = card.version();
+
+    let mut fn_value = None;
+    let mut email: Option<(u8, String)> = None;
+    let mut tel: Option<(u8, String)> = None;
+
+    for line in &card.props {
+        let (slot, value) = match VcardPropKind::from_str(line.name.get()) {
+            Ok(VcardPropKind::Fn) => {
+                let value = FN::decode(line, version).0.trim().to_string();
+                if fn_value.is_none() && !value.is_empty() {
+                    fn_value = Some(value);
+                }
+                continue;
+            }
+            Ok(VcardPropKind::Email) => (&mut email, EMAIL::decode(line, version).0),
+            Ok(VcardPropKind::Tel) => (&mut tel, TEL::decode(line, version).0),
+            _ => continue,
+        };
+
+        let value = value.trim();
+        let value = value.strip_prefix("tel:").unwrap_or(value);
+        if value.is_empty() {
+            continue;
+        }
+
+        let rank = pref_rank(line);
+        if slot.as_ref().is_none_or(|(best, _)| rank < *best) {
+            *slot = Some((rank, value.to_string()));
+        }
+    }
+
+    let value = |slot: Option<(u8, String)>| slot.map(|(_, value)| value);
+    (fn_value, value(email), value(tel))
+}
+
+/// Preference rank of a property line: its `PREF` parameter (1 is the
+/// most preferred), 1 for a 3.0 `TYPE=pref`, and 101 (below any PREF)
+/// otherwise.
+fn pref_rank(line: &VcardLine) -> u8 {
+    let mut rank = 101;
+
+    for param in &line.params {
+        let name = param.name.get();
+        for value in &param.values {
+            let value = value.get();
+            if name.eq_ignore_ascii_case("PREF") {
+                if let Ok(pref) = value.trim().parse::<u8>() {
+                    rank = rank.min(pref.clamp(1, 100));
+                }
+            } else if name.eq_ignore_ascii_case("TYPE") && value.eq_ignore_ascii_case("pref") {
+                rank = rank.min(1);
+            }
+        }
+    }
+
+    rank
+}
+
+/// Value of the first `UID` property of a vCard, if any, whatever
+/// its version.
+pub fn vcard_uid(contents: &[u8]) -> Option<String> {
+    read_cst(&String::from_utf8_lossy(contents), |card| {
+        card.props.iter().find_map(|line| {
+            let value = line.raw_value_str();
+            let value = value.trim();
+
+            (line.name.get().eq_ignore_ascii_case("UID") && !value.is_empty())
+                .then(|| value.to_string())
+        })
+    })
+    .flatten()
+}
+
+/// TYPE parameter values of every `name` property of a vCard, in
+/// document order, lower-cased. The `pref` marker is left out.
+pub fn vcard_types(vcard: &str, name: &str) -> Vec<Vec<String>> {
+    read_cst(vcard, |card| {
+        card.props
+            .iter()
+            .filter(|line| line.name.get().eq_ignore_ascii_case(name))
+            .map(line_types)
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Lower-cased TYPE parameter values of a property line, `pref` left
+/// out.
+fn line_types(line: &VcardLine) -> Vec<String> {
+    line.params
+        .iter()
+        .filter(|param| param.name.get().eq_ignore_ascii_case("TYPE"))
+        .flat_map(|param| &param.values)
+        .flat_map(|value| {
+            let value = value.get().trim_matches('"').to_lowercase();
+            value.split(',').map(str::to_string).collect::<Vec<_>>()
+        })
+        .filter(|value| !value.is_empty() && value != "pref")
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn previews_preferred_values() {
+        let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nEMAIL:jane@doe.org\r\n\
+            item1.EMAIL;PREF=1:jane@acme.org\r\nFN:Jane\r\n  Doe\r\n\
+            TEL;TYPE=\"cell,voice\":+33 1 11\r\nTEL;VALUE=uri;PREF=2:tel:+33 2 22\r\n\
+            END:VCARD\r\n";
+
+        let (fn_value, email, tel) = vcard_preview(vcard.as_bytes());
+        assert_eq!(fn_value.as_deref(), Some("Jane Doe"));
+        assert_eq!(email.as_deref(), Some("jane@acme.org"));
+        assert_eq!(tel.as_deref(), Some("+33 2 22"));
+    }
+
+    #[test]
+    fn reads_uid_of_any_version() {
+        let v4 = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane\r\nUID:urn:uuid:\r\n abc\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_uid(v4.as_bytes()).as_deref(), Some("urn:uuid:abc"));
+
+        let v21 = "BEGIN:VCARD\r\nVERSION:2.1\r\nN:Doe;Jane\r\nTEL;CELL:+331111\r\n\
+            item1.UID:abc\r\nEND:VCARD\r\n";
+        assert_eq!(vcard_uid(v21.as_bytes()).as_deref(), Some("abc"));
+    }
+
+    #[test]
+    fn reads_type_labels() {
+        let vcard = "BEGIN:VCARD\r\nVERSION:3.0\r\nFN:Jane\r\n\
+            EMAIL;TYPE=\"WORK,internet\":jane@acme.org\r\nEMAIL:jane@doe.org\r\n\
+            item1.TEL;type=cell;type=pref:+331111\r\nEND:VCARD\r\n";
+
+        assert_eq!(
+            vcard_types(vcard, "EMAIL"),
+            vec![vec!["work".to_string(), "internet".to_string()], vec![]]
+        );
+        assert_eq!(vcard_types(vcard, "TEL"), vec![vec!["cell".to_string()]]);
+    }
+}
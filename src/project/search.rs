@@ -0,0 +1,119 @@
+//! Client-side matching of vCards against a card query.
+
+use vcard::tree::cst::VcardCst;
+
+use crate::{project::unescape_text, shared::card::CardQuery};
+
+/// Whether the vCard matches every criterion of `query`. Values are
+/// compared unescaped and case-insensitively; phone numbers compare on
+/// their digits when the criterion holds any. An unparsable vCard
+/// matches nothing.
+pub fn vcard_matches(vcard: &[u8], query: &CardQuery) -> bool {
+    let Ok(vcard) = std::str::from_utf8(vcard) else {
+        return false;
+    };
+    let Ok(props) = prop_values(vcard) else {
+        return false;
+    };
+
+    let any = |names: &[&str], needle: &str| {
+        let needle = needle.to_lowercase();
+        props
+            .iter()
+            .filter(|(name, _)| names.is_empty() || names.contains(&name.as_str()))
+            .any(|(_, value)| value.to_lowercase().contains(&needle))
+    };
+
+    let tel = |needle: &str| {
+        let digits: String = needle.chars().filter(char::is_ascii_digit).collect();
+        if digits.is_empty() {
+            return any(&["TEL"], needle);
+        }
+        props
+            .iter()
+            .filter(|(name, _)| name == "TEL")
+            .map(|(_, value)| {
+                value
+                    .chars()
+                    .filter(char::is_ascii_digit)
+                    .collect::<String>()
+            })
+            .any(|value| value.contains(&digits))
+    };
+
+    query.text.as_deref().is_none_or(|text| any(&[], text))
+        && query
+            .name
+            .as_deref()
+            .is_none_or(|name| any(&["FN", "N", "NICKNAME"], name))
+        && query
+            .email
+            .as_deref()
+            .is_none_or(|email| any(&["EMAIL"], email))
+        && query.tel.as_deref().is_none_or(tel)
+        && query.org.as_deref().is_none_or(|org| any(&["ORG"], org))
+}
+
+/// Unescaped property values of a vCard (see [`prop_values`]), without
+/// the BEGIN/END delimiters. An unparsable vCard has none.
+pub fn vcard_values(vcard: &[u8]) -> Vec<(String, String)> {
+    let Ok(vcard) = std::str::from_utf8(vcard) else {
+        return Vec::new();
+    };
+
+    prop_values(vcard)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(name, _)| name != "BEGIN" && name != "END")
+        .collect()
+}
+
+/// Unescaped property values of a vCard, each keyed by its upper-cased
+/// property name. Structured components (N, ADR, ORG) are joined with
+/// spaces, so a name query spans them.
+pub(super) fn prop_values(vcard: &str) -> Result<Vec<(String, String)>, String> {
+    let card = VcardCst::parse(vcard).map_err(|err| format!("Invalid vCard: {err}"))?;
+
+    Ok(card
+        .props
+        .iter()
+        .map(|line| {
+            let name = line.name.get().to_ascii_uppercase();
+            let value = unescape_text(&line.raw_value_str());
+            (name, value)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_structured_criteria() {
+        let vcard = "BEGIN:VCARD\r\nVERSION:4.0\r\nFN:Jane Doe\r\nN:Doe;Jane;;;\r\n\
+            EMAIL:jane@doe.org\r\nTEL:+33 1 11 11\r\nORG:ACME\\, Inc.;R&D\r\nEND:VCARD\r\n"
+            .as_bytes();
+
+        let query = |query: CardQuery| vcard_matches(vcard, &query);
+
+        assert!(query(CardQuery {
+            name: Some("doe jane".into()),
+            ..Default::default()
+        }));
+        assert!(query(CardQuery {
+            email: Some("DOE.ORG".into()),
+            tel: Some("3311".into()),
+            ..Default::default()
+        }));
+        assert!(query(CardQuery {
+            org: Some("acme, inc".into()),
+            ..Default::default()
+        }));
+        assert!(!query(CardQuery {
+            text: Some("jane".into()),
+            tel: Some("42".into()),
+            ..Default::default()
+        }));
+    }
+}
@@ -0,0 +1,155 @@
+//! Decoding and splitting of `.vcf` streams holding several vCards.
+
+/// Decodes a `.vcf` stream to text. A byte order mark selects UTF-8 or
+/// UTF-16 (as Outlook exports), as does the NUL byte pattern of a
+/// BOM-less UTF-16 `BEGIN`; otherwise the stream must be UTF-8, unless
+/// it declares a Latin-1 `CHARSET` (as older phones export), read as
+/// ISO-8859-1 with that parameter dropped. Anything else is rejected
+/// rather than read with replacement characters.
+pub fn decode_vcf(bytes: &[u8]) -> Result<String, String> {
+    let utf16 = |bytes: &[u8], decode: fn([u8; 2]) -> u16| {
+        let units = bytes.chunks_exact(2).map(|pair| decode([pair[0], pair[1]]));
+        char::decode_utf16(units)
+            .collect::<Result<String, _>>()
+            .map_err(|err| format!("Invalid UTF-16 vCard stream: {err}"))
+    };
+
+    if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        return String::from_utf8(rest.to_vec())
+            .map_err(|err| format!("Invalid UTF-8 vCard stream: {err}"));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        return utf16(rest, u16::from_le_bytes);
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        return utf16(rest, u16::from_be_bytes);
+    }
+    if bytes.starts_with(b"B\0E\0") {
+        return utf16(bytes, u16::from_le_bytes);
+    }
+    if bytes.starts_with(b"\0B\0E") {
+        return utf16(bytes, u16::from_be_bytes);
+    }
+
+    let err = match String::from_utf8(bytes.to_vec()) {
+        Ok(text) => return Ok(text),
+        Err(err) => err.utf8_error(),
+    };
+
+    // NOTE: the parameter goes along, the text being UTF-8 from now on.
+    let upper = bytes.to_ascii_uppercase();
+    let mut latin1 = false;
+    let mut text = String::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let charset = LATIN1_CHARSETS
+            .iter()
+            .find(|charset| upper[index..].starts_with(charset.as_bytes()));
+
+        match charset {
+            Some(charset) => {
+                latin1 = true;
+                index += charset.len();
+            }
+            None => {
+                text.push(char::from(bytes[index]));
+                index += 1;
+            }
+        }
+    }
+
+    if latin1 {
+        Ok(text)
+    } else {
+        Err(format!(
+            "vCard stream is not valid UTF-8 ({err}); convert it first, \
+             e.g. with `iconv -f latin1 -t utf-8`"
+        ))
+    }
+}
+
+/// `CHARSET` parameters of the Latin-1 streams [`decode_vcf`] reads.
+const LATIN1_CHARSETS: [&str; 2] = [";CHARSET=ISO-8859-1", ";CHARSET=LATIN1"];
+
+/// Splits a stream of concatenated vCards (a `.vcf` export) into one
+/// document per `BEGIN:VCARD` … `END:VCARD` block, with CRLF line
+/// endings. Nested vCards (vCard 2.1 `AGENT`) stay inside their parent;
+/// anything outside the blocks is ignored.
+pub fn split_vcards(stream: &str) -> Vec<String> {
+    let mut vcards = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+
+    for line in stream.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.eq_ignore_ascii_case("BEGIN:VCARD") {
+            depth += 1;
+        } else if depth == 0 {
+            continue;
+        }
+
+        current.push_str(line.trim_end_matches('\r'));
+        current.push_str("\r\n");
+
+        if trimmed.eq_ignore_ascii_case("END:VCARD") {
+            depth -= 1;
+            if depth == 0 {
+                vcards.push(std::mem::take(&mut current));
+            }
+        }
+    }
+
+    vcards
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::vcard_uid;
+
+    #[test]
+    fn splits_concatenated_vcards() {
+        let stream = "BEGIN:VCARD\nVERSION:3.0\nUID:a\nFN:A\nEND:VCARD\n\n\
+            begin:vcard\r\nVERSION:4.0\r\nFN:B\r\nEND:VCARD\r\ntrailing garbage\n";
+
+        let vcards = split_vcards(stream);
+        assert_eq!(
+            vcards,
+            vec![
+                "BEGIN:VCARD\r\nVERSION:3.0\r\nUID:a\r\nFN:A\r\nEND:VCARD\r\n".to_string(),
+                "begin:vcard\r\nVERSION:4.0\r\nFN:B\r\nEND:VCARD\r\n".to_string(),
+            ]
+        );
+        assert_eq!(vcard_uid(vcards[0].as_bytes()).as_deref(), Some("a"));
+        assert_eq!(vcard_uid(vcards[1].as_bytes()), None);
+    }
+
+    #[test]
+    fn decodes_vcf_encodings() {
+        let text = "BEGIN:VCARD\r\nFN:Zoë\r\nEND:VCARD\r\n";
+        assert_eq!(decode_vcf(text.as_bytes()).as_deref(), Ok(text));
+
+        let mut bom = vec![0xEF, 0xBB, 0xBF];
+        bom.extend_from_slice(text.as_bytes());
+        assert_eq!(decode_vcf(&bom).as_deref(), Ok(text));
+
+        let mut utf16 = vec![0xFF, 0xFE];
+        utf16.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        assert_eq!(decode_vcf(&utf16).as_deref(), Ok(text));
+
+        let bare: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode_vcf(&bare).as_deref(), Ok(text));
+
+        let latin1 = b"BEGIN:VCARD\r\nFN;CHARSET=ISO-8859-1:Zo\xEB\r\nEND:VCARD\r\n";
+        assert_eq!(decode_vcf(latin1).as_deref(), Ok(text));
+        assert!(decode_vcf(b"FN:Zo\xEB\r\n").is_err());
+
+        let folded = "BEGIN:VCARD\r\nUID:urn:uuid:ab\r\n cd\r\nEND:VCARD\r\n";
+        assert_eq!(
+            vcard_uid(folded.as_bytes()).as_deref(),
+            Some("urn:uuid:abcd")
+        );
+    }
+}
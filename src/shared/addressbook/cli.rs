@@ -1,13 +1,20 @@
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
 use clap::Subcommand;
 use pimalaya_cli::printer::Printer;
 
-use crate::shared::{
-    addressbook::{
-        create::AddressbookCreateCommand, delete::AddressbookDeleteCommand,
-        list::AddressbookListCommand, update::AddressbookUpdateCommand,
+use crate::{
+    backend::Backend,
+    cli::{AccountSelection, open_clients},
+    shared::{
+        addressbook::{
+            copy::AddressbookCopyCommand, create::AddressbookCreateCommand,
+            delete::AddressbookDeleteCommand, list::AddressbookListCommand,
+            update::AddressbookUpdateCommand,
+        },
+        client::AddressbookClient,
     },
-    client::AddressbookClient,
 };
 
 /// Manage addressbooks using the shared API.
@@ -22,15 +29,42 @@ pub enum AddressbookCommand {
     Update(AddressbookUpdateCommand),
     #[command(visible_alias = "rm")]
     Delete(AddressbookDeleteCommand),
+    #[command(visible_alias = "cp")]
+    Copy(AddressbookCopyCommand),
 }
 
 impl AddressbookCommand {
-    pub fn execute(self, printer: &mut impl Printer, client: AddressbookClient) -> Result<()> {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: String,
+        client: AddressbookClient,
+    ) -> Result<()> {
         match self {
             Self::List(cmd) => cmd.execute(printer, client),
             Self::Create(cmd) => cmd.execute(printer, client),
             Self::Update(cmd) => cmd.execute(printer, client),
             Self::Delete(cmd) => cmd.execute(printer, client),
+            Self::Copy(cmd) => cmd.execute(printer, config_paths, account_name, client),
+        }
+    }
+
+    /// Runs the command over several accounts; only `addressbook list`
+    /// supports it.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        accounts: &AccountSelection,
+        backend: Backend,
+    ) -> Result<()> {
+        match self {
+            Self::List(cmd) => {
+                let clients = open_clients(printer, config_paths, accounts, backend, None)?;
+                cmd.execute_many(printer, clients)
+            }
+            _ => bail!("Only `addressbook list` runs over several accounts"),
         }
     }
 }
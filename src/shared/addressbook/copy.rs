@@ -0,0 +1,380 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::PathBuf,
+};
+
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
+use comfy_table::{Cell, Row, Table};
+use log::debug;
+use pimalaya_cli::{clap::parsers::path_parser, printer::Printer};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::Backend,
+    cli::resolve_account,
+    shared::{
+        addressbook::Addressbook,
+        arg::AddressbookIdArg,
+        card::Card,
+        client::AddressbookClient,
+        sync::state::{default_state_path, load_state, save_state, version},
+    },
+};
+
+/// Copy addressbooks to another account or backend.
+///
+/// Copies the addressbook `--addressbook` (or, with `--all`, every
+/// addressbook) of the active account to `--to-account` served by
+/// `--to-backend`, recreating the addressbook there, then its cards.
+/// With `--to-addressbook`, the cards land in that existing addressbook
+/// instead. With `--move`, the source cards (and the source addressbook
+/// itself, unless `--to-addressbook` is given) are deleted once copied.
+/// Google user groups only lose their members, who stay in myContacts,
+/// and system addressbooks (myContacts, the default Graph Contacts
+/// folder) are emptied but kept.
+///
+/// A mapping file records which target addressbook and card each
+/// source one was copied to, and the source card version at the time,
+/// so a rerun only copies what is new and updates what changed since.
+///
+/// JSON output: `{"dry-run", "move", "addressbooks": [{"from", "to",
+/// "created", "cards-created", "cards-updated", "cards-unchanged"}]}`.
+#[derive(Debug, Parser)]
+pub struct AddressbookCopyCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Copy every addressbook of the active account.
+    #[arg(long, conflicts_with = "id")]
+    pub all: bool,
+    /// Target account. Defaults to the active account.
+    #[arg(long, value_name = "NAME")]
+    pub to_account: Option<String>,
+    /// Target backend, with the same values as the global `--backend`
+    /// flag.
+    #[arg(long, value_name = "BACKEND", default_value_t)]
+    pub to_backend: Backend,
+    /// Existing target addressbook receiving the cards, instead of a
+    /// new one.
+    #[arg(long, value_name = "ADDRESSBOOK-ID", conflicts_with = "all")]
+    pub to_addressbook: Option<String>,
+    /// Delete the source cards (and addressbook) once copied.
+    #[arg(long = "move")]
+    pub move_: bool,
+    /// Mapping file path. Defaults to a file derived from both accounts
+    /// under `$XDG_DATA_HOME/cardamum/copy/`.
+    #[arg(long, value_name = "PATH", value_parser = path_parser)]
+    pub state: Option<PathBuf>,
+    /// Compute and report the copy without applying it nor touching the
+    /// mapping file.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl AddressbookCopyCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        from_name: String,
+        mut from: AddressbookClient,
+    ) -> Result<()> {
+        let to_account = self.to_account.as_deref().unwrap_or(&from_name);
+        let (config, to_name, account_config) =
+            resolve_account(printer, config_paths, Some(to_account))?;
+        let mut to = AddressbookClient::new(config, &to_name, account_config, self.to_backend)?;
+
+        let from_backend = from.backend().to_string();
+        let to_backend = to.backend().to_string();
+
+        let sources = if self.all {
+            from.list_addressbooks()?
+        } else {
+            let id = from.account.addressbook_id(self.addressbook.id)?;
+            let source = from
+                .list_addressbooks()?
+                .into_iter()
+                .find(|addressbook| addressbook.id == id);
+            vec![source.ok_or_else(|| anyhow!("Addressbook `{id}` not found"))?]
+        };
+
+        if from_name == to_name && from_backend == to_backend {
+            let same = match &self.to_addressbook {
+                Some(id) => sources.iter().any(|source| source.id == *id),
+                None => true,
+            };
+            if same {
+                bail!("Cannot copy an addressbook onto the same account and backend");
+            }
+        }
+
+        let state_path = match self.state {
+            Some(path) => path,
+            None => {
+                default_state_path("copy", &[&from_name, &from_backend, &to_name, &to_backend])?
+            }
+        };
+        debug!("copy mapping: {}", state_path.display());
+
+        let mut state: CopyState = load_state(&state_path)?;
+        let targets: Vec<String> = to
+            .list_addressbooks()?
+            .into_iter()
+            .map(|addressbook| addressbook.id)
+            .collect();
+
+        let mut report = CopyReport {
+            preset: from.account.table_preset().to_string(),
+            dry_run: self.dry_run,
+            move_: self.move_,
+            addressbooks: Vec::new(),
+        };
+
+        for source in sources {
+            let mapping = state.addressbooks.remove(&source.id).unwrap_or_default();
+
+            let mut copy = AddressbookCopy {
+                from: &mut from,
+                to: &mut to,
+                targets: &targets,
+                dry_run: self.dry_run,
+                mapping,
+            };
+
+            let (row, mapping) = copy.run(&source, self.to_addressbook.as_deref())?;
+            report.addressbooks.push(row);
+
+            if self.dry_run {
+                continue;
+            }
+
+            let copied: Vec<String> = mapping.cards.keys().cloned().collect();
+            state.addressbooks.insert(source.id.clone(), mapping);
+            save_state(&state, &state_path)?;
+
+            // NOTE: the mapping is saved before the source cards go, so
+            // an interrupted move resumes without duplicating them.
+            if self.move_ {
+                for id in copied {
+                    from.detach_card(&source.id, &id)?;
+                }
+                if self.to_addressbook.is_none() && from.deletable_addressbook(&source.id) {
+                    from.delete_addressbook(&source.id)?;
+                }
+                state.addressbooks.remove(&source.id);
+                save_state(&state, &state_path)?;
+            }
+        }
+
+        printer.out(report)
+    }
+}
+
+/// Copy of one source addressbook.
+struct AddressbookCopy<'a> {
+    from: &'a mut AddressbookClient,
+    to: &'a mut AddressbookClient,
+    targets: &'a [String],
+    dry_run: bool,
+    mapping: CopiedAddressbook,
+}
+
+impl AddressbookCopy<'_> {
+    /// Copies the addressbook `source`, into `into` when given,
+    /// returning its report row and its updated mapping.
+    fn run(
+        &mut self,
+        source: &Addressbook,
+        into: Option<&str>,
+    ) -> Result<(CopyRow, CopiedAddressbook)> {
+        let mut row = CopyRow {
+            from: source.id.clone(),
+            ..Default::default()
+        };
+
+        // NOTE: a mapped target deleted since the last run is
+        // recreated, its card mapping being stale.
+        let target = match into {
+            Some(id) => Some(id.to_string()),
+            None => Some(self.mapping.id.clone()).filter(|id| self.targets.contains(id)),
+        };
+
+        if target.as_deref() != Some(self.mapping.id.as_str()) {
+            self.mapping.cards.clear();
+        }
+
+        let target = match target {
+            Some(id) => id,
+            None if self.dry_run => {
+                row.created = true;
+                String::new()
+            }
+            None => {
+                row.created = true;
+                self.to.create_addressbook(
+                    &source.name,
+                    source.description.as_deref(),
+                    source.color.as_deref(),
+                )?
+            }
+        };
+
+        self.mapping.id = target.clone();
+        row.to = target.clone();
+
+        let existing: HashMap<String, Card> = if target.is_empty() {
+            HashMap::new()
+        } else {
+            self.to
+                .list_cards(&target, None, None)?
+                .into_iter()
+                .map(|card| (card.id.clone(), card))
+                .collect()
+        };
+
+        let guarded = self.to.guards_updates();
+        let mut cards = BTreeMap::new();
+
+        for card in self.from.list_cards(&source.id, None, None)? {
+            let current = version(&card);
+            let copied = self
+                .mapping
+                .cards
+                .remove(&card.id)
+                .and_then(|copied| Some((existing.get(&copied.id)?, copied)));
+
+            let id = match copied {
+                Some((_, copied)) if copied.version == current => {
+                    row.cards_unchanged += 1;
+                    copied.id
+                }
+                Some((target_card, copied)) => {
+                    row.cards_updated += 1;
+                    if !self.dry_run {
+                        let if_match = target_card.etag.as_deref().filter(|_| guarded);
+                        self.to
+                            .update_card(&target, &copied.id, card.contents, if_match)?;
+                    }
+                    copied.id
+                }
+                None => {
+                    row.cards_created += 1;
+                    if self.dry_run {
+                        continue;
+                    }
+                    self.to.create_card(&target, card.contents)?
+                }
+            };
+
+            cards.insert(
+                card.id,
+                CopiedCard {
+                    id,
+                    version: current,
+                },
+            );
+        }
+
+        let mapping = CopiedAddressbook { id: target, cards };
+        Ok((row, mapping))
+    }
+}
+
+/// Mapping between the source and target ids of a copy, keyed by
+/// source addressbook id.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CopyState {
+    #[serde(default)]
+    addressbooks: BTreeMap<String, CopiedAddressbook>,
+}
+
+/// Target of a source addressbook, with its cards keyed by source card
+/// id.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CopiedAddressbook {
+    id: String,
+    #[serde(default)]
+    cards: BTreeMap<String, CopiedCard>,
+}
+
+/// Target of a source card, with the source version it was copied at.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct CopiedCard {
+    id: String,
+    version: String,
+}
+
+/// Outcome of a copy.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CopyReport {
+    #[serde(skip)]
+    pub preset: String,
+    pub dry_run: bool,
+    #[serde(rename = "move")]
+    pub move_: bool,
+    pub addressbooks: Vec<CopyRow>,
+}
+
+/// Copy of one addressbook. The target id of an addressbook created
+/// during a dry run is unknown, hence empty.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CopyRow {
+    pub from: String,
+    pub to: String,
+    pub created: bool,
+    pub cards_created: usize,
+    pub cards_updated: usize,
+    pub cards_unchanged: usize,
+}
+
+impl fmt::Display for CopyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.dry_run {
+            writeln!(f, "Dry run, nothing applied")?;
+        }
+
+        let mut table = Table::new();
+
+        table
+            .load_preset(&self.preset)
+            .set_header(Row::from([
+                Cell::new("FROM"),
+                Cell::new("TO"),
+                Cell::new("CREATED"),
+                Cell::new("UPDATED"),
+                Cell::new("UNCHANGED"),
+            ]))
+            .add_rows(self.addressbooks.iter().map(|copy| {
+                let to = match (copy.to.as_str(), copy.created) {
+                    ("", _) => "(new)".to_string(),
+                    (to, true) => format!("{to} (new)"),
+                    (to, false) => to.to_string(),
+                };
+
+                let mut row = Row::new();
+                row.max_height(1)
+                    .add_cell(Cell::new(&copy.from))
+                    .add_cell(Cell::new(to))
+                    .add_cell(Cell::new(copy.cards_created))
+                    .add_cell(Cell::new(copy.cards_updated))
+                    .add_cell(Cell::new(copy.cards_unchanged));
+                row
+            }));
+
+        writeln!(f)?;
+        write!(f, "{table}")?;
+        writeln!(f)?;
+
+        if self.move_ && !self.dry_run {
+            writeln!(f, "Source cards deleted")?;
+        }
+
+        Ok(())
+    }
+}
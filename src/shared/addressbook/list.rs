@@ -3,15 +3,23 @@ use std::fmt;
 use anyhow::Result;
 use clap::Parser;
 use comfy_table::{Cell, Color, Row, Table};
+use log::warn;
 use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
-use crate::shared::{addressbook::Addressbook, client::AddressbookClient};
+use crate::{
+    account::context::Account,
+    shared::{addressbook::Addressbook, client::AddressbookClient},
+};
 
 /// List every addressbook available to the active account.
 ///
+/// Over several accounts (`-a` repeated, or `--all-accounts`), the
+/// addressbooks of each account are listed, prefixed with their account
+/// and backend.
+///
 /// JSON output: `{"addressbooks": [{"id", "name", "description",
-/// "color"}]}`.
+/// "color"}]}`, plus `"account"` and `"backend"` over several accounts.
 #[derive(Debug, Parser)]
 pub struct AddressbookListCommand;
 
@@ -19,16 +27,45 @@ impl AddressbookListCommand {
     pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
         let addressbooks = client.list_addressbooks()?;
 
-        let table = AddressbooksTable {
-            preset: client.account.table_preset().to_string(),
-            id_color: client.account.addressbooks_list_table_id_color(),
-            name_color: client.account.addressbooks_list_table_name_color(),
-            description_color: client.account.addressbooks_list_table_description_color(),
-            color_color: client.account.addressbooks_list_table_color_color(),
-            rows: addressbooks.into_iter().map(From::from).collect(),
-        };
+        let rows = addressbooks.into_iter().map(From::from).collect();
 
-        printer.out(table)
+        printer.out(AddressbooksTable::new(&client.account, rows, false))
+    }
+
+    /// Lists the addressbooks of each account, the first one styling
+    /// the table. An account failing to list is skipped with a warning.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        mut clients: Vec<AddressbookClient>,
+    ) -> Result<()> {
+        let mut rows = Vec::new();
+
+        for client in &mut clients {
+            let account = client.account_name.clone();
+            let backend = client.backend().to_string();
+
+            let addressbooks = match client.list_addressbooks() {
+                Ok(addressbooks) => addressbooks,
+                Err(err) => {
+                    warn!("cannot list addressbooks of account `{account}`, skipping it: {err:#}");
+                    continue;
+                }
+            };
+
+            for addressbook in addressbooks {
+                rows.push(AddressbookRow {
+                    account: Some(account.clone()),
+                    backend: Some(backend.clone()),
+                    ..AddressbookRow::from(addressbook)
+                });
+            }
+        }
+
+        let fallback = Account::default();
+        let account = clients.first().map_or(&fallback, |client| &client.account);
+
+        printer.out(AddressbooksTable::new(account, rows, true))
     }
 }
 
@@ -44,21 +81,47 @@ pub struct AddressbooksTable {
     pub description_color: Color,
     #[serde(skip)]
     pub color_color: Color,
+    /// Whether the rows come from several accounts, shown first.
+    #[serde(skip)]
+    pub tagged: bool,
     #[serde(rename = "addressbooks")]
     pub rows: Vec<AddressbookRow>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 pub struct AddressbookRow {
+    /// Account the addressbook belongs to, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Backend the addressbook comes from, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
     pub id: String,
     pub name: String,
     pub description: Option<String>,
     pub color: Option<String>,
 }
 
+impl AddressbooksTable {
+    /// Table of the given rows, styled by the account config.
+    pub fn new(account: &Account, rows: Vec<AddressbookRow>, tagged: bool) -> Self {
+        Self {
+            preset: account.table_preset().to_string(),
+            id_color: account.addressbooks_list_table_id_color(),
+            name_color: account.addressbooks_list_table_name_color(),
+            description_color: account.addressbooks_list_table_description_color(),
+            color_color: account.addressbooks_list_table_color_color(),
+            tagged,
+            rows,
+        }
+    }
+}
+
 impl From<Addressbook> for AddressbookRow {
     fn from(book: Addressbook) -> Self {
         Self {
+            account: None,
+            backend: None,
             id: book.id,
             name: book.name,
             description: book.description,
@@ -73,16 +136,22 @@ impl fmt::Display for AddressbooksTable {
 
         table
             .load_preset(&self.preset)
-            .set_header(Row::from([
-                Cell::new("ID"),
-                Cell::new("NAME"),
-                Cell::new("DESC"),
-                Cell::new("COLOR"),
-            ]))
+            .set_header(Row::from(
+                self.tagged
+                    .then_some(["ACCOUNT", "BACKEND"])
+                    .into_iter()
+                    .flatten()
+                    .chain(["ID", "NAME", "DESC", "COLOR"])
+                    .map(Cell::new),
+            ))
             .add_rows(self.rows.iter().map(|book| {
                 let mut row = Row::new();
-                row.max_height(1)
-                    .add_cell(Cell::new(&book.id).fg(self.id_color))
+                row.max_height(1);
+                if self.tagged {
+                    row.add_cell(Cell::new(book.account.as_deref().unwrap_or("")))
+                        .add_cell(Cell::new(book.backend.as_deref().unwrap_or("")));
+                }
+                row.add_cell(Cell::new(&book.id).fg(self.id_color))
                     .add_cell(Cell::new(&book.name).fg(self.name_color))
                     .add_cell(
                         Cell::new(book.description.as_deref().unwrap_or(""))
@@ -1,6 +1,7 @@
 mod types;
 
 pub mod cli;
+pub mod copy;
 pub mod create;
 pub mod delete;
 pub mod list;
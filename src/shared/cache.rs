@@ -0,0 +1,192 @@
+//! On-disk cache of remote addressbooks: the last-seen cards of each
+//! addressbook, with their ETags and the change token they were
+//! fetched at, so card reads work offline and refresh incrementally.
+
+use std::{collections::BTreeMap, fs, io::ErrorKind, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backend::Backend,
+    project::vcard_matches,
+    shared::{
+        card::{Card, CardQuery},
+        client::paginate,
+        hash::stable_hash,
+    },
+};
+
+/// Cache of the addressbooks of one account on one backend, one file
+/// per addressbook under `$XDG_CACHE_HOME/cardamum/cards/`.
+#[derive(Clone, Debug)]
+pub struct CardCache {
+    account: String,
+    backend: Backend,
+    dir: PathBuf,
+}
+
+/// Cached cards of one addressbook.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CachedAddressbook {
+    /// Change token the cards are up to date with, `None` until the
+    /// first full listing.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Cards by id.
+    #[serde(default)]
+    pub cards: BTreeMap<String, CachedCard>,
+}
+
+/// One cached card. The vCard is kept as text, so the cache file
+/// stays readable.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CachedCard {
+    #[serde(default)]
+    pub etag: Option<String>,
+    pub vcard: String,
+}
+
+impl CardCache {
+    /// Cache of the given account and backend, under the user cache
+    /// dir.
+    pub fn new(account: &str, backend: Backend) -> Result<Self> {
+        let dir = dirs::cache_dir().ok_or_else(|| anyhow!("Cannot locate the user cache dir"))?;
+
+        Ok(Self {
+            account: account.to_string(),
+            backend,
+            dir: dir.join("cardamum").join("cards"),
+        })
+    }
+
+    /// The backend the cached cards come from.
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Cache file of an addressbook. The file name hashes the account,
+    /// the backend and the addressbook, so each gets its own file.
+    fn path(&self, addressbook_id: &str) -> PathBuf {
+        let key = stable_hash(&[
+            self.account.as_str(),
+            &self.backend.to_string(),
+            addressbook_id,
+        ]);
+
+        self.dir.join(format!("{key}.json"))
+    }
+
+    /// Reads the cached cards of `addressbook_id`; a missing file is an
+    /// empty cache.
+    pub fn load(&self, addressbook_id: &str) -> Result<CachedAddressbook> {
+        let path = self.path(addressbook_id);
+
+        match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("Invalid card cache `{}`", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(CachedAddressbook::default()),
+            Err(err) => {
+                Err(err).with_context(|| format!("Cannot read card cache `{}`", path.display()))
+            }
+        }
+    }
+
+    /// Writes the cached cards of `addressbook_id`, aside then renamed
+    /// like the sync state.
+    pub fn save(&self, addressbook_id: &str, addressbook: &CachedAddressbook) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Cannot create card cache dir `{}`", self.dir.display()))?;
+
+        let path = self.path(addressbook_id);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(addressbook)?)
+            .with_context(|| format!("Cannot write card cache `{}`", tmp.display()))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("Cannot write card cache `{}`", path.display()))?;
+
+        Ok(())
+    }
+
+    /// Reads the cached cards of `addressbook_id` when offline: an
+    /// addressbook never listed online is an error rather than an empty
+    /// one.
+    fn load_offline(&self, addressbook_id: &str) -> Result<CachedAddressbook> {
+        let addressbook = self.load(addressbook_id)?;
+
+        if addressbook.token.is_none() && addressbook.cards.is_empty() {
+            bail!(
+                "Addressbook `{addressbook_id}` is not cached yet; list it once online with `card.cache` enabled"
+            );
+        }
+
+        Ok(addressbook)
+    }
+
+    /// Offline `card list`, in card id order.
+    pub fn list_cards(
+        &self,
+        addressbook_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
+    ) -> Result<Vec<Card>> {
+        let cards = self
+            .load_offline(addressbook_id)?
+            .into_cards(addressbook_id);
+        Ok(paginate(cards, page, page_size))
+    }
+
+    /// Offline `card search`.
+    pub fn search_cards(&self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let mut cards = self
+            .load_offline(addressbook_id)?
+            .into_cards(addressbook_id);
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
+    }
+
+    /// Offline `card read`.
+    pub fn get_card(&self, addressbook_id: &str, card_id: &str) -> Result<Card> {
+        self.load_offline(addressbook_id)?
+            .card(addressbook_id, card_id)
+            .ok_or_else(|| anyhow!("Card `{card_id}` not found in the cache of `{addressbook_id}`"))
+    }
+}
+
+impl CachedAddressbook {
+    /// Stores or replaces a card.
+    pub fn insert(&mut self, card: Card) {
+        let cached = CachedCard {
+            etag: card.etag,
+            vcard: String::from_utf8_lossy(&card.contents).into_owned(),
+        };
+        self.cards.insert(card.id, cached);
+    }
+
+    /// The cached card `card_id`, if any.
+    pub fn card(&self, addressbook_id: &str, card_id: &str) -> Option<Card> {
+        let cached = self.cards.get(card_id)?;
+
+        Some(Card {
+            id: card_id.to_string(),
+            addressbook_id: addressbook_id.to_string(),
+            etag: cached.etag.clone(),
+            contents: cached.vcard.clone().into_bytes(),
+        })
+    }
+
+    /// Every cached card, in card id order.
+    pub fn into_cards(self, addressbook_id: &str) -> Vec<Card> {
+        self.cards
+            .into_iter()
+            .map(|(id, cached)| Card {
+                id,
+                addressbook_id: addressbook_id.to_string(),
+                etag: cached.etag,
+                contents: cached.vcard.into_bytes(),
+            })
+            .collect()
+    }
+}
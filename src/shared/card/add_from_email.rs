@@ -0,0 +1,74 @@
+use anyhow::{Result, anyhow};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::{
+    project::{NewCard, build_vcard, parse_mailbox, vcard_values},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{CardQuery, create::fresh_uid_rev, set::untyped},
+        client::AddressbookClient,
+    },
+};
+
+/// Create a minimal card from an email address.
+///
+/// Takes a mailbox as found in email headers (`Jane Doe
+/// <jane@doe.org>`, `"Doe, Jane" <jane@doe.org>` or a bare address)
+/// and appends a vCard 4.0 holding its name (the address when the
+/// mailbox has none) and address, for email clients to bind to an "add
+/// sender" key. Nothing is created when a card of the addressbook
+/// already holds the address.
+///
+/// JSON output: `{"message": "..."}`.
+#[derive(Debug, Parser)]
+pub struct CardAddFromEmailCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Mailbox to create the card from, e.g. `Jane Doe <jane@doe.org>`.
+    #[arg(value_name = "MAILBOX")]
+    pub mailbox: String,
+}
+
+impl CardAddFromEmailCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+
+        let (name, address) = parse_mailbox(&self.mailbox)
+            .ok_or_else(|| anyhow!("Invalid mailbox `{}`", self.mailbox))?;
+
+        let query = CardQuery {
+            email: Some(address.clone()),
+            ..Default::default()
+        };
+
+        for card in client.search_cards(&addressbook_id, &query)? {
+            let known = vcard_values(&card.contents)
+                .into_iter()
+                .any(|(prop, value)| {
+                    let value = value.strip_prefix("mailto:").unwrap_or(&value);
+                    prop == "EMAIL" && value.eq_ignore_ascii_case(&address)
+                });
+
+            if known {
+                return printer.out(Message::new(format!(
+                    "Address `{address}` already in card `{}`, nothing to create",
+                    card.id
+                )));
+            }
+        }
+
+        let (uid, rev) = fresh_uid_rev()?;
+        let card = NewCard {
+            uid,
+            rev,
+            fn_value: name.unwrap_or_else(|| address.clone()),
+            email: vec![untyped(address)],
+            ..Default::default()
+        };
+
+        let id = client.create_card(&addressbook_id, build_vcard(&card).into_bytes())?;
+
+        printer.out(Message::new(format!("Card `{id}` successfully created")))
+    }
+}
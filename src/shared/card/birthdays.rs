@@ -0,0 +1,237 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use pimalaya_cli::{clap::parsers::path_parser, printer::Printer};
+use serde::Serialize;
+
+use crate::{
+    project::{
+        CardDate, CardDateKind, escape_text, fold_line, rev_timestamp, vcard_dates, vcard_preview,
+        vcard_uid,
+    },
+    shared::client::AddressbookClient,
+};
+
+/// Year given to year-less dates in iCalendar, which has no year-less
+/// date: a leap year, so that `--0229` stays a valid date.
+const YEAR_LESS: u16 = 2000;
+
+/// Export the birthdays and anniversaries of the account.
+///
+/// Scans every card of every addressbook of the account for BDAY,
+/// ANNIVERSARY and Apple `X-ABDATE` dates (3.0 spellings included),
+/// which is where the Microsoft Graph and Google People projections put
+/// the dates of their contacts. A card listed under several
+/// addressbooks (Google groups, JMAP books) counts once. Year-less
+/// dates (`--0415`) are kept. The `list` format prints one line per
+/// date, in calendar order. The `ics` format is an iCalendar document
+/// of yearly recurring all-day events, for calendar tools to import or
+/// subscribe to: a year-less date starts in 2000, and a February 29
+/// recurs on the last day of February. The document goes to `--output`,
+/// or to stdout.
+///
+/// JSON output: `{"format", "count", "path", "contents"}`, with the
+/// document in `contents` when no `--output` is given.
+#[derive(Debug, Parser)]
+pub struct CardBirthdaysCommand {
+    /// Format of the document.
+    #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = BirthdaysFormatArg::List)]
+    pub format: BirthdaysFormatArg,
+    /// File the document is written to, instead of stdout.
+    #[arg(short, long, value_name = "PATH", value_parser = path_parser)]
+    pub output: Option<PathBuf>,
+}
+
+/// Document format of `card birthdays --format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum BirthdaysFormatArg {
+    List,
+    Ics,
+}
+
+/// One date of one card.
+struct CardEvent {
+    uid: String,
+    name: String,
+    date: CardDate,
+}
+
+impl CardBirthdaysCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let mut events = Vec::new();
+        let mut seen = HashSet::new();
+
+        for addressbook in client.list_addressbooks()? {
+            for card in client.list_cards(&addressbook.id, None, None)? {
+                let uid = vcard_uid(&card.contents).unwrap_or_else(|| card.id.clone());
+                if !seen.insert(uid.clone()) {
+                    continue;
+                }
+
+                let vcard = String::from_utf8_lossy(&card.contents);
+                let (name, _, _) = vcard_preview(&card.contents);
+                let name = name.unwrap_or_else(|| card.id.clone());
+
+                for date in vcard_dates(&vcard) {
+                    events.push(CardEvent {
+                        uid: uid.clone(),
+                        name: name.clone(),
+                        date,
+                    });
+                }
+            }
+        }
+
+        let contents = match self.format {
+            BirthdaysFormatArg::List => to_list(&mut events),
+            BirthdaysFormatArg::Ics => to_ics(&events),
+        };
+
+        let mut birthdays = CardBirthdays {
+            format: self.format,
+            count: events.len(),
+            path: None,
+            contents: Some(contents),
+        };
+
+        if let Some(path) = self.output {
+            let contents = birthdays.contents.take().unwrap_or_default();
+            fs::write(&path, contents)
+                .with_context(|| format!("Write birthdays to `{}` error", path.display()))?;
+            birthdays.path = Some(path);
+        }
+
+        printer.out(birthdays)
+    }
+}
+
+fn label(kind: &CardDateKind) -> &str {
+    match kind {
+        CardDateKind::Birthday => "Birthday",
+        CardDateKind::Anniversary => "Anniversary",
+        CardDateKind::Custom(label) => label,
+    }
+}
+
+/// One `MM-DD<TAB>YYYY<TAB>LABEL<TAB>NAME` line per date, by month and
+/// day, `----` standing for a missing year.
+fn to_list(events: &mut [CardEvent]) -> String {
+    events.sort_by(|a, b| {
+        let key = |event: &CardEvent| (event.date.date.month, event.date.date.day);
+        key(a).cmp(&key(b)).then_with(|| a.name.cmp(&b.name))
+    });
+
+    let mut out = String::new();
+
+    for event in events.iter() {
+        let date = event.date.date;
+        let year = date
+            .year
+            .map_or_else(|| "----".to_string(), |year| format!("{year:04}"));
+
+        out.push_str(&format!(
+            "{:02}-{:02}\t{year}\t{}\t{}\n",
+            date.month,
+            date.day,
+            label(&event.date.kind),
+            event.name.replace(['\t', '\r', '\n'], " ")
+        ));
+    }
+
+    out
+}
+
+/// Renders the dates as an iCalendar document (RFC 5545) of yearly
+/// all-day events. Event UIDs derive from the card UID and the rank of
+/// the date in the card, so that subscribed calendars update in place.
+fn to_ics(events: &[CardEvent]) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+    let stamp = rev_timestamp(now);
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".into(),
+        "PRODID:-//pimalaya//cardamum//EN".into(),
+        "CALSCALE:GREGORIAN".into(),
+    ];
+
+    let mut rank = 0;
+    let mut previous: Option<&str> = None;
+
+    for event in events {
+        rank = match previous {
+            Some(uid) if uid == event.uid => rank + 1,
+            _ => 0,
+        };
+        previous = Some(&event.uid);
+
+        let date = event.date.date;
+        let mut summary = format!("{}: {}", label(&event.date.kind), event.name);
+        if let Some(year) = date.year {
+            summary.push_str(&format!(" ({year})"));
+        }
+
+        // NOTE: a yearly rule skips the years a February 29 does not
+        // exist (RFC 5545 3.3.10), hence the last day of February.
+        let rrule = match (date.month, date.day) {
+            (2, 29) => "RRULE:FREQ=YEARLY;BYMONTH=2;BYMONTHDAY=-1",
+            _ => "RRULE:FREQ=YEARLY",
+        };
+
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}-{rank}@cardamum", escape_text(&event.uid)),
+            format!("DTSTAMP:{stamp}"),
+            format!(
+                "DTSTART;VALUE=DATE:{:04}{:02}{:02}",
+                date.year.unwrap_or(YEAR_LESS),
+                date.month,
+                date.day
+            ),
+            rrule.into(),
+            format!("SUMMARY:{}", escape_text(&summary)),
+            "TRANSP:TRANSPARENT".into(),
+            "END:VEVENT".into(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".into());
+
+    lines.iter().map(|line| fold_line(line) + "\r\n").collect()
+}
+
+/// Outcome of a birthdays export: the document itself, or the path it
+/// was written to.
+#[derive(Clone, Debug, Serialize)]
+pub struct CardBirthdays {
+    pub format: BirthdaysFormatArg,
+    pub count: usize,
+    pub path: Option<PathBuf>,
+    pub contents: Option<String>,
+}
+
+impl fmt::Display for CardBirthdays {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.contents, &self.path) {
+            (Some(contents), _) => write!(f, "{contents}"),
+            (None, Some(path)) => writeln!(
+                f,
+                "{} date(s) successfully exported to `{}`",
+                self.count,
+                path.display()
+            ),
+            (None, None) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+use anyhow::Result;
+use clap::Parser;
+use comfy_table::{Cell, Color, Row, Table};
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::shared::{arg::AddressbookIdArg, card::CardChanges, client::AddressbookClient};
+
+/// List the vCards changed since a change token.
+///
+/// Without `--since`, every card of the addressbook is reported as
+/// created. Feed the returned token back to `--since` to get the next
+/// round of changes. The token is opaque and backend-specific: a
+/// CardDAV sync-token, a JMAP state, a Graph delta link, a People
+/// sync token or a local vdir snapshot id. A vdir addressbook keeps
+/// the snapshots of its last 16 calls only, whoever made them: an
+/// older token is rejected, and the feed has to restart without one.
+///
+/// JSON output: `{"created": [...], "updated": [...], "deleted": [...],
+/// "token"}`.
+#[derive(Debug, Parser)]
+pub struct CardChangesCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Token returned by a previous call; omit for an initial feed.
+    #[arg(long, value_name = "TOKEN")]
+    pub since: Option<String>,
+}
+
+impl CardChangesCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+        let changes = client.card_changes(&addressbook_id, self.since.as_deref())?;
+
+        printer.out(ChangesTable {
+            preset: client.account.table_preset().to_string(),
+            id_color: client.account.cards_list_table_id_color(),
+            changes,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ChangesTable {
+    #[serde(skip)]
+    pub preset: String,
+    #[serde(skip)]
+    pub id_color: Color,
+    #[serde(flatten)]
+    pub changes: CardChanges,
+}
+
+impl fmt::Display for ChangesTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+
+        let rows = [
+            ("created", &self.changes.created),
+            ("updated", &self.changes.updated),
+            ("deleted", &self.changes.deleted),
+        ];
+
+        table
+            .load_preset(&self.preset)
+            .set_header(Row::from([Cell::new("STATUS"), Cell::new("ID")]))
+            .add_rows(rows.into_iter().flat_map(|(status, ids)| {
+                ids.iter().map(move |id| {
+                    let mut row = Row::new();
+                    row.max_height(1)
+                        .add_cell(Cell::new(status))
+                        .add_cell(Cell::new(id).fg(self.id_color));
+                    row
+                })
+            }));
+
+        writeln!(f)?;
+        write!(f, "{table}")?;
+        writeln!(f)?;
+        writeln!(f, "token: {}", self.changes.token)?;
+        Ok(())
+    }
+}
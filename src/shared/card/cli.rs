@@ -1,15 +1,28 @@
-use anyhow::Result;
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
 use clap::Subcommand;
 use pimalaya_cli::printer::Printer;
 
-use crate::shared::{
-    card::{
-        create::CardCreateCommand, delete::CardDeleteCommand, list::CardListCommand,
-        read::CardReadCommand, update::CardUpdateCommand,
+use crate::{
+    backend::Backend,
+    cli::{AccountSelection, open_clients},
+    shared::{
+        card::{
+            add_from_email::CardAddFromEmailCommand, birthdays::CardBirthdaysCommand,
+            changes::CardChangesCommand, create::CardCreateCommand, dedupe::CardDedupeCommand,
+            delete::CardDeleteCommand, edit::CardEditCommand, export::CardExportCommand,
+            import::CardImportCommand, list::CardListCommand, photo::cli::CardPhotoCommand,
+            query_addresses::CardQueryAddressesCommand, read::CardReadCommand,
+            search::CardSearchCommand, set::CardSetCommand, update::CardUpdateCommand,
+        },
+        client::AddressbookClient,
     },
-    client::AddressbookClient,
 };
 
+/// Error of the `--cached` commands the card cache cannot serve.
+pub const CACHED_ONLY: &str = "Only `card list`, `card read` and `card search` run with --cached";
+
 /// Manage vCards using the shared API.
 ///
 /// Behind the scene, uses the first backend defined for the default account (or
@@ -21,19 +34,97 @@ pub enum CardCommand {
     Read(CardReadCommand),
     #[command(visible_alias = "new")]
     Create(CardCreateCommand),
+    Import(CardImportCommand),
+    Export(CardExportCommand),
     Update(CardUpdateCommand),
+    Set(CardSetCommand),
+    Edit(CardEditCommand),
     #[command(visible_alias = "rm")]
     Delete(CardDeleteCommand),
+    Changes(CardChangesCommand),
+    #[command(visible_alias = "find")]
+    Search(CardSearchCommand),
+    Dedupe(CardDedupeCommand),
+    QueryAddresses(CardQueryAddressesCommand),
+    AddFromEmail(CardAddFromEmailCommand),
+    Birthdays(CardBirthdaysCommand),
+    #[command(subcommand)]
+    Photo(CardPhotoCommand),
 }
 
 impl CardCommand {
-    pub fn execute(self, printer: &mut impl Printer, client: AddressbookClient) -> Result<()> {
+    /// Runs the command. `json` tells whether the printer outputs
+    /// JSON, which keeps `card export` from streaming to stdout.
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        client: AddressbookClient,
+        json: bool,
+    ) -> Result<()> {
         match self {
             Self::List(cmd) => cmd.execute(printer, client),
             Self::Read(cmd) => cmd.execute(printer, client),
             Self::Create(cmd) => cmd.execute(printer, client),
+            Self::Import(cmd) => cmd.execute(printer, client),
+            Self::Export(cmd) => cmd.execute(printer, client, json),
             Self::Update(cmd) => cmd.execute(printer, client),
+            Self::Set(cmd) => cmd.execute(printer, client),
+            Self::Edit(cmd) => cmd.execute(printer, client),
             Self::Delete(cmd) => cmd.execute(printer, client),
+            Self::Changes(cmd) => cmd.execute(printer, client),
+            Self::Search(cmd) => cmd.execute(printer, client),
+            Self::Dedupe(cmd) => cmd.execute(printer, client),
+            Self::QueryAddresses(cmd) => cmd.execute(printer, client),
+            Self::AddFromEmail(cmd) => cmd.execute(printer, client),
+            Self::Birthdays(cmd) => cmd.execute(printer, client),
+            Self::Photo(cmd) => cmd.execute(printer, client),
+        }
+    }
+
+    /// Whether the command only reads cards, hence may go through the
+    /// card cache: `card list`, `card read` and `card search`. The
+    /// others always talk to the backend.
+    pub fn reads_cache(&self) -> bool {
+        matches!(self, Self::List(_) | Self::Read(_) | Self::Search(_))
+    }
+
+    /// Runs the command over several accounts; only `card list`, `card
+    /// search` and `card export` support it.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        accounts: &AccountSelection,
+        backend: Backend,
+        cached: bool,
+        json: bool,
+    ) -> Result<()> {
+        let offline = if self.reads_cache() {
+            Some(cached)
+        } else if cached {
+            bail!(CACHED_ONLY);
+        } else {
+            None
+        };
+        let open =
+            |printer: &mut _| open_clients(printer, config_paths, accounts, backend, offline);
+
+        match self {
+            Self::List(cmd) => {
+                let clients = open(printer)?;
+                cmd.execute_many(printer, clients)
+            }
+            Self::Search(cmd) => {
+                let clients = open(printer)?;
+                cmd.execute_many(printer, clients)
+            }
+            Self::Export(cmd) => {
+                let clients = open(printer)?;
+                cmd.execute_many(printer, clients, json)
+            }
+            _ => {
+                bail!("Only `card list`, `card search` and `card export` run over several accounts")
+            }
         }
     }
 }
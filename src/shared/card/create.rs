@@ -1,24 +1,207 @@
-use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Result, anyhow, bail};
 use clap::Parser;
 use pimalaya_cli::printer::{Message, Printer};
 
-use crate::shared::{arg::AddressbookIdArg, card::vcard::VcardArg, client::AddressbookClient};
+use crate::{
+    project::{NewAddress, NewCard, build_vcard, parse_date, rev_timestamp, uuid_v4},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{set::parse_typed, vcard::read_vcard},
+        client::AddressbookClient,
+    },
+};
 
 /// Append a vCard to the given addressbook.
 ///
+/// The vCard is either given as is (a path, raw contents or `-` for
+/// stdin), or built from the property flags: a vCard 4.0 with a fresh
+/// UID and REV. Multi-valued flags take an optional comma-separated
+/// TYPE prefix: `--email work=jane@acme.org`, `--tel cell,pref=+33…`.
+///
 /// JSON output: `{"message": "..."}`.
 #[derive(Debug, Parser)]
 pub struct CardCreateCommand {
     #[command(flatten)]
     pub addressbook: AddressbookIdArg,
+    /// A path to a vCard file, raw vCard contents, or `-` for stdin.
+    #[arg(value_name = "VCARD")]
+    pub vcard: Option<String>,
     #[command(flatten)]
-    pub vcard: VcardArg,
+    pub fields: CardFieldsArg,
+}
+
+/// Property flags of `card create`, building the vCard when no
+/// positional one is given.
+#[derive(Debug, Default, PartialEq, Eq, Parser)]
+pub struct CardFieldsArg {
+    /// Formatted name. Defaults to the name parts, space-separated.
+    #[arg(long = "fn", value_name = "TEXT")]
+    pub fn_value: Option<String>,
+    /// Given (first) name.
+    #[arg(long, value_name = "TEXT")]
+    pub given: Option<String>,
+    /// Family (last) name.
+    #[arg(long, value_name = "TEXT")]
+    pub family: Option<String>,
+    /// Additional (middle) name.
+    #[arg(long, value_name = "TEXT")]
+    pub middle: Option<String>,
+    /// Honorific prefix (e.g. `Dr.`).
+    #[arg(long, value_name = "TEXT")]
+    pub prefix: Option<String>,
+    /// Honorific suffix (e.g. `Jr.`).
+    #[arg(long, value_name = "TEXT")]
+    pub suffix: Option<String>,
+    /// Nickname, repeatable.
+    #[arg(long, value_name = "TEXT")]
+    pub nickname: Vec<String>,
+    /// Email address, repeatable.
+    #[arg(long, value_name = "[TYPE=]EMAIL")]
+    pub email: Vec<String>,
+    /// Phone number, repeatable.
+    #[arg(long, value_name = "[TYPE=]NUMBER")]
+    pub tel: Vec<String>,
+    /// Street of the postal address.
+    #[arg(long, value_name = "TEXT")]
+    pub street: Option<String>,
+    /// Locality (city) of the postal address.
+    #[arg(long, value_name = "TEXT")]
+    pub locality: Option<String>,
+    /// Region (state, province) of the postal address.
+    #[arg(long, value_name = "TEXT")]
+    pub region: Option<String>,
+    /// Postal code of the postal address.
+    #[arg(long, value_name = "TEXT")]
+    pub postal_code: Option<String>,
+    /// Country of the postal address.
+    #[arg(long, value_name = "TEXT")]
+    pub country: Option<String>,
+    /// TYPE of the postal address (e.g. `home`, `work`).
+    #[arg(long, value_name = "TYPE", value_delimiter = ',')]
+    pub adr_type: Vec<String>,
+    /// Organization.
+    #[arg(long, value_name = "TEXT")]
+    pub org: Option<String>,
+    /// Job title.
+    #[arg(long, value_name = "TEXT")]
+    pub title: Option<String>,
+    /// Birthday, as `yyyy-mm-dd`, or `--mmdd` without a year.
+    #[arg(long, value_name = "DATE")]
+    pub bday: Option<String>,
+    /// Free-form note.
+    #[arg(long, value_name = "TEXT")]
+    pub note: Option<String>,
+    /// Categories (tags), comma-separated.
+    #[arg(long, value_name = "CATEGORY", value_delimiter = ',')]
+    pub categories: Vec<String>,
+}
+
+impl CardFieldsArg {
+    /// Builds the vCard 4.0 of the flags, with a fresh UID and REV.
+    pub fn build(self) -> Result<Vec<u8>> {
+        let n = [
+            self.family.unwrap_or_default(),
+            self.given.unwrap_or_default(),
+            self.middle.unwrap_or_default(),
+            self.prefix.unwrap_or_default(),
+            self.suffix.unwrap_or_default(),
+        ];
+
+        let fn_value = match self.fn_value {
+            Some(fn_value) => fn_value,
+            None => {
+                // NOTE: display order is prefix, given, middle, family,
+                // suffix.
+                let parts = [&n[3], &n[1], &n[2], &n[0], &n[4]];
+                let parts: Vec<&str> = parts
+                    .into_iter()
+                    .map(String::as_str)
+                    .filter(|part| !part.is_empty())
+                    .collect();
+                parts.join(" ")
+            }
+        };
+
+        if fn_value.is_empty() {
+            bail!("Missing name: pass a vCard, or at least --fn or a name part");
+        }
+
+        let bday = match self.bday {
+            Some(bday) => {
+                let date = parse_date(&bday).ok_or_else(|| anyhow!("Invalid birthday `{bday}`"))?;
+                Some(date.to_string())
+            }
+            None => None,
+        };
+
+        let address = [
+            &self.street,
+            &self.locality,
+            &self.region,
+            &self.postal_code,
+            &self.country,
+        ];
+        let adr = address
+            .iter()
+            .any(|part| part.is_some())
+            .then(|| NewAddress {
+                types: self.adr_type,
+                street: self.street.unwrap_or_default(),
+                locality: self.locality.unwrap_or_default(),
+                region: self.region.unwrap_or_default(),
+                postal_code: self.postal_code.unwrap_or_default(),
+                country: self.country.unwrap_or_default(),
+            });
+
+        let (uid, rev) = fresh_uid_rev()?;
+
+        let card = NewCard {
+            uid,
+            rev,
+            fn_value,
+            n,
+            nickname: self.nickname,
+            email: self.email.into_iter().map(parse_typed).collect(),
+            tel: self.tel.into_iter().map(parse_typed).collect(),
+            adr,
+            org: self.org,
+            title: self.title,
+            bday,
+            note: self.note,
+            categories: self.categories,
+        };
+
+        Ok(build_vcard(&card).into_bytes())
+    }
+}
+
+/// Mints the UID and REV of a new card: a random `urn:uuid:` and the
+/// current time.
+pub fn fresh_uid_rev() -> Result<(String, String)> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|err| anyhow!("Gather randomness error: {err}"))?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    Ok((format!("urn:uuid:{}", uuid_v4(bytes)), rev_timestamp(now)))
 }
 
 impl CardCreateCommand {
     pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
         let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
-        let contents = self.vcard.read()?;
+
+        let contents = match self.vcard {
+            Some(_) if self.fields != CardFieldsArg::default() => {
+                bail!("Cannot combine a vCard with property flags")
+            }
+            Some(vcard) => read_vcard(vcard)?,
+            None => self.fields.build()?,
+        };
+
         let id = client.create_card(&addressbook_id, contents)?;
 
         printer.out(Message::new(format!("Card `{id}` successfully created")))
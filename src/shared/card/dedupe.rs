@@ -0,0 +1,242 @@
+use std::{collections::HashSet, fmt};
+
+use anyhow::{Error, Result};
+use clap::{Parser, ValueEnum};
+use comfy_table::{Cell, Color, Row, Table};
+use pimalaya_cli::{printer::Printer, prompt};
+use serde::Serialize;
+
+use crate::{
+    project::{DedupeKey, duplicate_groups, merge_duplicates},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{Card, list::CardRow},
+        client::AddressbookClient,
+    },
+};
+
+/// Find and merge duplicate vCards.
+///
+/// Cards are grouped as likely duplicates when they share an email
+/// address (case-insensitive) or a phone number (E.164-normalized), or
+/// when their names match fuzzily (same words in any order, up to a
+/// typo). Each group merges into the card with the newest REV, which
+/// gains the EMAIL, TEL and ADR properties only the others carry; the
+/// others are then deleted. Every group is confirmed interactively
+/// unless `--yes` is given.
+///
+/// JSON output: `{"dry-run", "groups": [{"kept": {"id",
+/// "addressbook_id", "etag", "fn_value", ...}, "merged": [...],
+/// "skipped"}]}`, cards in the `card list` shape.
+#[derive(Debug, Parser)]
+pub struct CardDedupeCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Other addressbook of the account to search for duplicates, in
+    /// addition to the main one. A card kept stays in its addressbook.
+    #[arg(long, value_name = "ADDRESSBOOK-ID")]
+    pub across: Vec<String>,
+    /// Properties cards are matched on. Defaults to all of them.
+    #[arg(
+        long,
+        value_name = "KEY",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [DedupeKeyArg::Email, DedupeKeyArg::Tel, DedupeKeyArg::Name],
+    )]
+    pub by: Vec<DedupeKeyArg>,
+    /// Country calling code (e.g. `33`) given to national phone numbers
+    /// (leading `0`), so that they match their international form.
+    #[arg(long, value_name = "CODE")]
+    pub country_code: Option<String>,
+    /// Merge every group without asking for confirmation.
+    #[arg(short, long)]
+    pub yes: bool,
+    /// Report the duplicate groups without merging them.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Matching key selector for `card dedupe --by`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum DedupeKeyArg {
+    Email,
+    Tel,
+    Name,
+}
+
+impl From<DedupeKeyArg> for DedupeKey {
+    fn from(arg: DedupeKeyArg) -> Self {
+        match arg {
+            DedupeKeyArg::Email => Self::Email,
+            DedupeKeyArg::Tel => Self::Tel,
+            DedupeKeyArg::Name => Self::Name,
+        }
+    }
+}
+
+impl CardDedupeCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+
+        let mut cards = client.list_cards(&addressbook_id, None, None)?;
+        for id in &self.across {
+            if *id != addressbook_id {
+                cards.extend(client.list_cards(id, None, None)?);
+            }
+        }
+
+        // NOTE: a Google person lists under myContacts and each of its
+        // groups, a JMAP card under each of its books: the same card
+        // must not be grouped with itself. Other backends reuse ids
+        // (file names) across addressbooks for distinct cards.
+        if client.shares_cards() {
+            let mut seen = HashSet::new();
+            cards.retain(|card| seen.insert(card.id.clone()));
+        }
+
+        let keys: Vec<DedupeKey> = self.by.into_iter().map(DedupeKey::from).collect();
+        let contents: Vec<&[u8]> = cards.iter().map(|card| card.contents.as_slice()).collect();
+        let groups = duplicate_groups(&contents, &keys, self.country_code.as_deref());
+
+        let mut report = DedupeReport {
+            preset: client.account.table_preset().to_string(),
+            id_color: client.account.cards_list_table_id_color(),
+            fn_color: client.account.cards_list_table_fn_color(),
+            dry_run: self.dry_run,
+            groups: Vec::with_capacity(groups.len()),
+        };
+
+        for group in groups {
+            let mut group: Vec<&Card> = group.into_iter().map(|index| &cards[index]).collect();
+
+            let vcards: Vec<String> = group
+                .iter()
+                .map(|card| String::from_utf8_lossy(&card.contents).into_owned())
+                .collect();
+            let vcards: Vec<&str> = vcards.iter().map(String::as_str).collect();
+            let merge =
+                merge_duplicates(&vcards, self.country_code.as_deref()).map_err(Error::msg)?;
+
+            let kept = group.remove(merge.kept);
+            let kept_row = CardRow::from(kept.clone());
+
+            let confirmed = self.dry_run
+                || self.yes
+                || prompt::bool(
+                    format!(
+                        "Merge {} duplicate(s) into `{}` ({})?",
+                        group.len(),
+                        kept.id,
+                        kept_row.fn_value.as_deref().unwrap_or("no name"),
+                    ),
+                    true,
+                )?;
+
+            if confirmed && !self.dry_run {
+                if merge.vcard.as_bytes() != kept.contents.as_slice() {
+                    let guarded = client.guards_updates();
+                    let if_match = kept.etag.as_deref().filter(|_| guarded);
+                    client.update_card(
+                        &kept.addressbook_id,
+                        &kept.id,
+                        merge.vcard.into_bytes(),
+                        if_match,
+                    )?;
+                }
+
+                for card in group.iter().filter(|card| card.id != kept.id) {
+                    client.delete_card(&card.addressbook_id, &card.id)?;
+                }
+            }
+
+            report.groups.push(DedupeGroup {
+                kept: kept_row,
+                merged: group.into_iter().cloned().map(CardRow::from).collect(),
+                skipped: !confirmed,
+            });
+        }
+
+        printer.out(report)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct DedupeReport {
+    #[serde(skip)]
+    pub preset: String,
+    #[serde(skip)]
+    pub id_color: Color,
+    #[serde(skip)]
+    pub fn_color: Color,
+    pub dry_run: bool,
+    pub groups: Vec<DedupeGroup>,
+}
+
+/// One group of duplicates: the card kept and the ones merged into it.
+#[derive(Clone, Debug, Serialize)]
+pub struct DedupeGroup {
+    pub kept: CardRow,
+    pub merged: Vec<CardRow>,
+    /// Whether the merge was declined at the confirmation prompt.
+    pub skipped: bool,
+}
+
+impl fmt::Display for DedupeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut table = Table::new();
+
+        table
+            .load_preset(&self.preset)
+            .set_header(Row::from([
+                Cell::new("GROUP"),
+                Cell::new("ACTION"),
+                Cell::new("ADDRESSBOOK"),
+                Cell::new("ID"),
+                Cell::new("FN"),
+            ]))
+            .add_rows(self.groups.iter().enumerate().flat_map(|(index, group)| {
+                let merged = if group.skipped { "skip" } else { "merge" };
+                let kept = std::iter::once(("keep", &group.kept));
+                let merged = group.merged.iter().map(move |card| (merged, card));
+
+                kept.chain(merged).map(move |(action, card)| {
+                    let mut row = Row::new();
+                    row.max_height(1)
+                        .add_cell(Cell::new(index + 1))
+                        .add_cell(Cell::new(action))
+                        .add_cell(Cell::new(&card.addressbook_id))
+                        .add_cell(Cell::new(&card.id).fg(self.id_color))
+                        .add_cell(
+                            Cell::new(card.fn_value.as_deref().unwrap_or("")).fg(self.fn_color),
+                        );
+                    row
+                })
+            }));
+
+        let merged: usize = self
+            .groups
+            .iter()
+            .filter(|group| !group.skipped)
+            .map(|group| group.merged.len())
+            .sum();
+
+        writeln!(f)?;
+        write!(f, "{table}")?;
+        writeln!(f)?;
+
+        if self.dry_run {
+            writeln!(
+                f,
+                "Dry run: {} group(s), {merged} card(s) would be merged",
+                self.groups.len()
+            )?;
+        } else {
+            writeln!(f, "{} group(s), {merged} card(s) merged", self.groups.len())?;
+        }
+
+        Ok(())
+    }
+}
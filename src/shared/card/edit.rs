@@ -0,0 +1,120 @@
+use std::{
+    env,
+    fs::{self, DirBuilder, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::shared::{arg::AddressbookIdArg, client::AddressbookClient};
+
+/// Edit a vCard in a text editor.
+///
+/// Writes the card to a temporary file, readable by the current user
+/// only, opens it with `$VISUAL` (or `$EDITOR`, or `vi`), and uploads
+/// it back once the editor exits, guarded by the ETag read when the
+/// backend supports it. Nothing is uploaded when the file is left
+/// unchanged.
+///
+/// JSON output: `{"message": "..."}`.
+#[derive(Debug, Parser)]
+pub struct CardEditCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Card UID.
+    #[arg(value_name = "CARD-ID")]
+    pub card_id: String,
+}
+
+impl CardEditCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+        let card = client.get_card(&addressbook_id, &self.card_id)?;
+
+        let dir = private_temp_dir()?;
+        let path = dir.join(format!("{}.vcf", card.id.replace(['/', '\\'], "_")));
+
+        let edited = write_private(&path, &card.contents)
+            .and_then(|()| open_editor(&path.to_string_lossy()))
+            .and_then(|()| fs::read(&path).context("Read edited card error"));
+        let _ = fs::remove_dir_all(&dir);
+        let contents = edited?;
+
+        if contents == card.contents {
+            return printer.out(Message::new(format!(
+                "Card `{}` unchanged, nothing to update",
+                self.card_id
+            )));
+        }
+
+        let if_match = card.etag.as_deref().filter(|_| client.guards_updates());
+        client.update_card(&addressbook_id, &self.card_id, contents, if_match)?;
+
+        printer.out(Message::new(format!(
+            "Card `{}` successfully updated",
+            self.card_id
+        )))
+    }
+}
+
+/// Creates a fresh directory with an unpredictable name under the
+/// system temp dir, accessible by the current user only, so no other
+/// user can read the card nor plant a file in its place.
+fn private_temp_dir() -> Result<PathBuf> {
+    let mut bytes = [0u8; 8];
+    getrandom::fill(&mut bytes).map_err(|err| anyhow!("Gather randomness error: {err}"))?;
+    let name: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    let dir = env::temp_dir().join(format!("cardamum-{name}"));
+
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(&dir)
+        .with_context(|| format!("Create temporary dir `{}` error", dir.display()))?;
+
+    Ok(dir)
+}
+
+/// Writes `contents` to a new file at `path`, readable and writable by
+/// the current user only. Fails when the file already exists.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .with_context(|| format!("Write card to `{}` error", path.display()))
+}
+
+/// Runs the user's editor on the given file and waits for it. The
+/// editor variable may carry arguments (`code --wait`).
+fn open_editor(path: &str) -> Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".into());
+
+    let mut words = editor.split_whitespace();
+    let Some(program) = words.next() else {
+        bail!("Editor command is empty, check $VISUAL and $EDITOR");
+    };
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Run editor `{editor}` error"))?;
+
+    if !status.success() {
+        bail!("Editor `{editor}` exited with {status}, card left untouched");
+    }
+
+    Ok(())
+}
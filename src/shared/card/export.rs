@@ -0,0 +1,568 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use clap::{Parser, ValueEnum};
+use log::warn;
+use pimalaya_cli::{clap::parsers::path_parser, printer::Printer};
+use serde::Serialize;
+
+use crate::{
+    project::{convert_vcard, vcard_values},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{Card, vcard::VcardVersionArg},
+        client::AddressbookClient,
+    },
+};
+
+/// Export every vCard of an addressbook into a single document.
+///
+/// The `vcf` format concatenates the raw vCards, ready for `card
+/// import` or any other contact manager. The `json` format is an array
+/// of one object per card with its parsed fields and raw vCard. The
+/// `csv` format holds one row per card with the `--columns` fields,
+/// multiple values being joined with `; `. With `--vcard-version`,
+/// cards are converted to that vCard version first. With `--all`, every
+/// addressbook of the account is exported, a card listed under several
+/// of them (Google groups, JMAP books) once. Over several accounts (`-a`
+/// repeated, or `--all-accounts`), the addressbook of each account (or
+/// every one of them with `--all`) is exported, the `json` cards carry
+/// their account and backend, and the `csv` rows start with them unless
+/// `--columns` places them. The document goes to `--output`, or to
+/// stdout, written card after card as each addressbook is listed.
+///
+/// JSON output: `{"format", "count", "path", "contents"}`, with the
+/// exported document in `contents` when no `--output` is given, the
+/// document being kept in memory then.
+#[derive(Debug, Parser)]
+pub struct CardExportCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Export the cards of every addressbook of the account.
+    #[arg(long, conflicts_with = "id")]
+    pub all: bool,
+    /// Format of the exported document.
+    #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = ExportFormatArg::Vcf)]
+    pub format: ExportFormatArg,
+    /// Fields exported as CSV columns, in order.
+    #[arg(
+        long,
+        value_name = "COLUMN",
+        value_enum,
+        value_delimiter = ',',
+        default_values_t = [
+            ExportColumnArg::Fn,
+            ExportColumnArg::Email,
+            ExportColumnArg::Tel,
+            ExportColumnArg::Org,
+        ],
+    )]
+    pub columns: Vec<ExportColumnArg>,
+    /// vCard version to convert the cards to.
+    #[arg(long, value_name = "VERSION", value_enum)]
+    pub vcard_version: Option<VcardVersionArg>,
+    /// File the document is written to, instead of stdout.
+    #[arg(short, long, value_name = "PATH", value_parser = path_parser)]
+    pub output: Option<PathBuf>,
+}
+
+/// Document format of `card export --format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum ExportFormatArg {
+    Vcf,
+    Json,
+    Csv,
+}
+
+/// CSV column of `card export --columns`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum ExportColumnArg {
+    Account,
+    Backend,
+    Id,
+    Addressbook,
+    Uid,
+    Fn,
+    N,
+    Nickname,
+    Email,
+    Tel,
+    Adr,
+    Org,
+    Title,
+    Bday,
+    Url,
+    Note,
+}
+
+impl CardExportCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        client: AddressbookClient,
+        json: bool,
+    ) -> Result<()> {
+        self.export(printer, vec![client], false, json)
+    }
+
+    /// Exports the addressbooks of each account into one document. An
+    /// account or addressbook failing to list is skipped with a
+    /// warning.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        clients: Vec<AddressbookClient>,
+        json: bool,
+    ) -> Result<()> {
+        self.export(printer, clients, true, json)
+    }
+
+    /// Writes the document to `--output`, to stdout, or under `json`
+    /// into memory for the printer.
+    fn export(
+        self,
+        printer: &mut impl Printer,
+        clients: Vec<AddressbookClient>,
+        tagged: bool,
+        json: bool,
+    ) -> Result<()> {
+        let mut columns = self.columns;
+        if tagged
+            && !columns.contains(&ExportColumnArg::Account)
+            && !columns.contains(&ExportColumnArg::Backend)
+        {
+            columns.splice(0..0, [ExportColumnArg::Account, ExportColumnArg::Backend]);
+        }
+
+        let out: Option<Box<dyn Write>> = match &self.output {
+            Some(path) => {
+                let file = File::create(path)
+                    .with_context(|| format!("Create export file `{}` error", path.display()))?;
+                Some(Box::new(BufWriter::new(file)))
+            }
+            None if json => None,
+            None => Some(Box::new(BufWriter::new(io::stdout().lock()))),
+        };
+        let mut writer = ExportWriter::new(out, self.format, columns);
+
+        for mut client in clients {
+            self.write_cards(&mut writer, &mut client, tagged)?;
+        }
+
+        let count = writer.count;
+        let contents = writer.finish()?;
+
+        let mut export = CardExport {
+            format: self.format,
+            count,
+            path: None,
+            contents: None,
+        };
+
+        match self.output {
+            Some(path) => export.path = Some(path),
+            None if json => export.contents = Some(String::from_utf8_lossy(&contents).into_owned()),
+            // NOTE: already streamed to stdout.
+            None => return Ok(()),
+        }
+
+        printer.out(export)
+    }
+
+    /// Writes the cards of the addressbooks of `client`. Over several
+    /// accounts, an account or addressbook failing to list is skipped
+    /// with a warning.
+    fn write_cards(
+        &self,
+        writer: &mut ExportWriter,
+        client: &mut AddressbookClient,
+        tagged: bool,
+    ) -> Result<()> {
+        let addressbook_ids = match self.addressbook_ids(client) {
+            Ok(ids) => ids,
+            Err(err) if tagged => {
+                warn!(
+                    "cannot list addressbooks of account `{}`, skipping it: {err:#}",
+                    client.account_name
+                );
+                return Ok(());
+            }
+            Err(err) => return Err(err),
+        };
+
+        // NOTE: a Google person lists under myContacts and each of
+        // its groups, a JMAP card under each of its books; other
+        // backends reuse ids (file names) across addressbooks.
+        let shares_cards = client.shares_cards();
+        let mut seen = HashSet::new();
+
+        for id in &addressbook_ids {
+            let cards = match client.list_cards(id, None, None) {
+                Ok(cards) => cards,
+                Err(err) if tagged => {
+                    warn!(
+                        "cannot list addressbook `{id}` of account `{}`, skipping it: {err:#}",
+                        client.account_name
+                    );
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            for mut card in cards {
+                if shares_cards && !seen.insert(card.id.clone()) {
+                    continue;
+                }
+
+                if let Some(version) = self.vcard_version {
+                    let vcard =
+                        convert_vcard(&String::from_utf8_lossy(&card.contents), version.into());
+                    card.contents = vcard.into_bytes();
+                }
+
+                let mut exported = ExportedCard::from(&card);
+                if tagged {
+                    exported.account = Some(client.account_name.clone());
+                    exported.backend = Some(client.backend().to_string());
+                }
+
+                writer.write(&card, &exported)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Addressbooks of `client` to export: all of them with `--all`,
+    /// the given or default one otherwise.
+    fn addressbook_ids(&self, client: &mut AddressbookClient) -> Result<Vec<String>> {
+        if !self.all {
+            return Ok(vec![
+                client.account.addressbook_id(self.addressbook.id.clone())?,
+            ]);
+        }
+
+        let addressbooks = client.list_addressbooks()?;
+        Ok(addressbooks
+            .into_iter()
+            .map(|addressbook| addressbook.id)
+            .collect())
+    }
+}
+
+/// Writes the exported document card after card, so that an export to
+/// a file or stdout holds one addressbook listing at a time rather than
+/// the whole document.
+struct ExportWriter {
+    /// File of `--output`, or stdout; without it, the document is kept
+    /// in `buffer` for the printer.
+    out: Option<Box<dyn Write>>,
+    buffer: Vec<u8>,
+    format: ExportFormatArg,
+    columns: Vec<ExportColumnArg>,
+    count: usize,
+}
+
+impl ExportWriter {
+    fn new(
+        out: Option<Box<dyn Write>>,
+        format: ExportFormatArg,
+        columns: Vec<ExportColumnArg>,
+    ) -> Self {
+        Self {
+            out,
+            buffer: Vec::new(),
+            format,
+            columns,
+            count: 0,
+        }
+    }
+
+    fn emit(&mut self, out: &str) -> Result<()> {
+        match &mut self.out {
+            Some(writer) => writer
+                .write_all(out.as_bytes())
+                .context("Write export error"),
+            None => {
+                self.buffer.extend_from_slice(out.as_bytes());
+                Ok(())
+            }
+        }
+    }
+
+    /// Appends one card, opening the document (JSON bracket, CSV
+    /// header) first.
+    fn write(&mut self, card: &Card, exported: &ExportedCard) -> Result<()> {
+        let mut out = String::new();
+
+        if self.count == 0 {
+            match self.format {
+                ExportFormatArg::Vcf => {}
+                ExportFormatArg::Json => out.push('['),
+                ExportFormatArg::Csv => push_csv_header(&mut out, &self.columns),
+            }
+        }
+
+        match self.format {
+            ExportFormatArg::Vcf => {
+                out.push_str(&String::from_utf8_lossy(&card.contents));
+                if !out.ends_with('\n') {
+                    out.push_str("\r\n");
+                }
+            }
+            ExportFormatArg::Json => {
+                if self.count > 0 {
+                    out.push(',');
+                }
+                // NOTE: indented as a pretty-printed array item.
+                for line in serde_json::to_string_pretty(exported)?.lines() {
+                    out.push_str("\n  ");
+                    out.push_str(line);
+                }
+            }
+            ExportFormatArg::Csv => {
+                let row: Vec<String> = self
+                    .columns
+                    .iter()
+                    .map(|column| exported.column(*column))
+                    .collect();
+                push_csv_row(&mut out, &row);
+            }
+        }
+
+        self.count += 1;
+        self.emit(&out)
+    }
+
+    /// Closes the document and flushes it. Returns the document when
+    /// kept in memory, nothing when written out.
+    fn finish(mut self) -> Result<Vec<u8>> {
+        let out = match (self.format, self.count) {
+            (ExportFormatArg::Json, 0) => "[]\n".to_string(),
+            (ExportFormatArg::Json, _) => "\n]\n".to_string(),
+            (ExportFormatArg::Csv, 0) => {
+                let mut out = String::new();
+                push_csv_header(&mut out, &self.columns);
+                out
+            }
+            _ => String::new(),
+        };
+
+        self.emit(&out)?;
+
+        if let Some(writer) = &mut self.out {
+            writer.flush().context("Write export error")?;
+        }
+
+        Ok(self.buffer)
+    }
+}
+
+fn push_csv_header(out: &mut String, columns: &[ExportColumnArg]) {
+    let header: Vec<String> = columns
+        .iter()
+        .filter_map(|column| column.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    push_csv_row(out, &header);
+}
+
+fn push_csv_row(out: &mut String, fields: &[String]) {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+
+    out.push_str(&fields.join(","));
+    out.push_str("\r\n");
+}
+
+/// Parsed fields of an exported card, as found in the `json` format.
+/// Structured values (N, ADR, ORG) have their components joined with
+/// spaces.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ExportedCard {
+    /// Account the card comes from, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Backend the card comes from, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    pub id: String,
+    pub addressbook_id: String,
+    pub etag: Option<String>,
+    pub uid: Option<String>,
+    #[serde(rename = "fn")]
+    pub fn_value: Option<String>,
+    pub n: Option<String>,
+    pub nickname: Vec<String>,
+    pub email: Vec<String>,
+    pub tel: Vec<String>,
+    pub adr: Vec<String>,
+    pub org: Option<String>,
+    pub title: Option<String>,
+    pub bday: Option<String>,
+    pub url: Vec<String>,
+    pub note: Option<String>,
+    /// Whether the card carries a PHOTO.
+    pub photo: bool,
+    pub vcard: String,
+}
+
+impl ExportedCard {
+    /// Value of the given CSV column.
+    fn column(&self, column: ExportColumnArg) -> String {
+        let one = |value: &Option<String>| value.clone().unwrap_or_default();
+
+        match column {
+            ExportColumnArg::Account => one(&self.account),
+            ExportColumnArg::Backend => one(&self.backend),
+            ExportColumnArg::Id => self.id.clone(),
+            ExportColumnArg::Addressbook => self.addressbook_id.clone(),
+            ExportColumnArg::Uid => one(&self.uid),
+            ExportColumnArg::Fn => one(&self.fn_value),
+            ExportColumnArg::N => one(&self.n),
+            ExportColumnArg::Nickname => self.nickname.join("; "),
+            ExportColumnArg::Email => self.email.join("; "),
+            ExportColumnArg::Tel => self.tel.join("; "),
+            ExportColumnArg::Adr => self.adr.join("; "),
+            ExportColumnArg::Org => one(&self.org),
+            ExportColumnArg::Title => one(&self.title),
+            ExportColumnArg::Bday => one(&self.bday),
+            ExportColumnArg::Url => self.url.join("; "),
+            ExportColumnArg::Note => one(&self.note),
+        }
+    }
+}
+
+impl From<&Card> for ExportedCard {
+    fn from(card: &Card) -> Self {
+        let mut exported = Self {
+            id: card.id.clone(),
+            addressbook_id: card.addressbook_id.clone(),
+            etag: card.etag.clone(),
+            vcard: String::from_utf8_lossy(&card.contents).into_owned(),
+            ..Default::default()
+        };
+
+        for (name, value) in vcard_values(&card.contents) {
+            // NOTE: structured components leave runs of spaces behind;
+            // only notes keep their line breaks.
+            let value = match name.as_str() {
+                "NOTE" => value,
+                _ => value.split_whitespace().collect::<Vec<_>>().join(" "),
+            };
+            if value.is_empty() {
+                continue;
+            }
+
+            match name.as_str() {
+                "UID" => set_first(&mut exported.uid, value),
+                "FN" => set_first(&mut exported.fn_value, value),
+                "N" => set_first(&mut exported.n, value),
+                "ORG" => set_first(&mut exported.org, value),
+                "TITLE" => set_first(&mut exported.title, value),
+                "BDAY" => set_first(&mut exported.bday, value),
+                "NOTE" => set_first(&mut exported.note, value),
+                "NICKNAME" => exported.nickname.push(value),
+                "EMAIL" => exported.email.push(value),
+                "TEL" => exported.tel.push(value),
+                "ADR" => exported.adr.push(value),
+                "URL" => exported.url.push(value),
+                "PHOTO" => exported.photo = true,
+                _ => {}
+            }
+        }
+
+        exported
+    }
+}
+
+/// Fills a single-valued field with its first occurrence.
+fn set_first(slot: &mut Option<String>, value: String) {
+    if slot.is_none() {
+        *slot = Some(value);
+    }
+}
+
+/// Outcome of an export: the document itself, or the path it was
+/// written to.
+#[derive(Clone, Debug, Serialize)]
+pub struct CardExport {
+    pub format: ExportFormatArg,
+    pub count: usize,
+    pub path: Option<PathBuf>,
+    pub contents: Option<String>,
+}
+
+impl fmt::Display for CardExport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.contents, &self.path) {
+            (Some(contents), _) => write!(f, "{contents}"),
+            (None, Some(path)) => writeln!(
+                f,
+                "{} card(s) successfully exported to `{}`",
+                self.count,
+                path.display()
+            ),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "vdir"))]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::*;
+    use crate::{
+        backend::Backend,
+        config::{AccountConfig, Config, VdirConfig},
+    };
+
+    #[test]
+    fn exports_vdir_books_sharing_file_names() {
+        let home = env::temp_dir().join(format!("cardamum-export-test-{}", process::id()));
+        for (book, name) in [("family", "Alice"), ("work", "Bob")] {
+            fs::create_dir_all(home.join(book)).unwrap();
+            let vcard =
+                format!("BEGIN:VCARD\r\nVERSION:4.0\r\nUID:{name}\r\nFN:{name}\r\nEND:VCARD\r\n");
+            fs::write(home.join(book).join("contact.vcf"), vcard).unwrap();
+        }
+
+        let account_config = AccountConfig {
+            vdir: Some(VdirConfig {
+                home_dir: home.to_string_lossy().into_owned(),
+                vcard_version: None,
+            }),
+            ..Default::default()
+        };
+        let mut client =
+            AddressbookClient::new(Config::default(), "test", account_config, Backend::Vdir)
+                .unwrap();
+
+        let cmd = CardExportCommand::try_parse_from(["export", "--all"]).unwrap();
+        let mut writer = ExportWriter::new(None, cmd.format, Vec::new());
+        cmd.write_cards(&mut writer, &mut client, false).unwrap();
+        let document = String::from_utf8(writer.finish().unwrap()).unwrap();
+        fs::remove_dir_all(&home).unwrap();
+
+        assert!(document.contains("FN:Alice\r\n"));
+        assert!(document.contains("FN:Bob\r\n"));
+    }
+}
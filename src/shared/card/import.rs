@@ -0,0 +1,170 @@
+use std::{collections::HashMap, fmt};
+
+use anyhow::{Error, Result, bail};
+use clap::{Parser, ValueEnum};
+use log::warn;
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::{
+    project::{decode_vcf, split_vcards, vcard_uid},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{Card, vcard::VcardArg},
+        client::AddressbookClient,
+    },
+};
+
+/// Import every vCard of a multi-card `.vcf` stream.
+///
+/// The source (a file, raw contents or `-` for stdin) is split on its
+/// `BEGIN:VCARD` … `END:VCARD` blocks, as found in phone exports and
+/// Thunderbird dumps, and each card is created in the addressbook. The
+/// stream is UTF-8 or UTF-16 (byte order mark, as Outlook exports), or
+/// Latin-1 when its cards declare `CHARSET=ISO-8859-1`; any other
+/// encoding is rejected rather than imported garbled. A card whose UID
+/// already exists in the addressbook (or earlier in the stream) is
+/// handled by `--on-conflict`. A card failing to import is reported
+/// and does not stop the others.
+///
+/// JSON output: `{"created": [...], "replaced": [...], "skipped": [...],
+/// "failed": [{"index", "uid", "error"}]}`.
+#[derive(Debug, Parser)]
+pub struct CardImportCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// What to do with a card whose UID already exists: keep the
+    /// existing card, replace its contents, or create a duplicate.
+    #[arg(long, value_name = "POLICY", value_enum, default_value_t = ImportConflictArg::Skip)]
+    pub on_conflict: ImportConflictArg,
+    #[command(flatten)]
+    pub vcard: VcardArg,
+}
+
+/// UID conflict policy of `card import --on-conflict`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum ImportConflictArg {
+    Skip,
+    Replace,
+    Duplicate,
+}
+
+impl CardImportCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+        let stream = self.vcard.read()?;
+        let vcards = split_vcards(&decode_vcf(&stream).map_err(Error::msg)?);
+
+        if vcards.is_empty() {
+            bail!("No vCard found in the import source");
+        }
+
+        // NOTE: existing cards are only needed to detect UID conflicts,
+        // which the duplicate policy ignores.
+        let mut existing: HashMap<String, Card> = HashMap::new();
+        if self.on_conflict != ImportConflictArg::Duplicate {
+            for card in client.list_cards(&addressbook_id, None, None)? {
+                if let Some(uid) = vcard_uid(&card.contents) {
+                    existing.insert(uid, card);
+                }
+            }
+        }
+
+        let guarded = client.guards_updates();
+        let mut report = ImportReport::default();
+
+        for (index, vcard) in vcards.into_iter().enumerate() {
+            let contents = vcard.into_bytes();
+            let uid = vcard_uid(&contents);
+            let twin = uid.as_ref().and_then(|uid| existing.get(uid));
+
+            let result = match (twin, self.on_conflict) {
+                (Some(card), ImportConflictArg::Skip) => {
+                    report.skipped.push(card.id.clone());
+                    continue;
+                }
+                (Some(card), ImportConflictArg::Replace) => {
+                    let if_match = card.etag.as_deref().filter(|_| guarded);
+                    client
+                        .update_card(&addressbook_id, &card.id, contents.clone(), if_match)
+                        .map(|()| {
+                            report.replaced.push(card.id.clone());
+                            card.id.clone()
+                        })
+                }
+                _ => client
+                    .create_card(&addressbook_id, contents.clone())
+                    .inspect(|id| report.created.push(id.clone())),
+            };
+
+            match result {
+                Ok(id) => {
+                    if let Some(uid) = uid {
+                        // NOTE: the ETag changed with the write; a later
+                        // card of the stream sharing this UID replaces
+                        // it unguarded.
+                        let card = Card {
+                            id,
+                            addressbook_id: addressbook_id.clone(),
+                            etag: None,
+                            contents,
+                        };
+                        existing.insert(uid, card);
+                    }
+                }
+                Err(err) => {
+                    warn!("cannot import vCard #{}: {err:#}", index + 1);
+                    report.failed.push(ImportFailure {
+                        index: index + 1,
+                        uid,
+                        error: format!("{err:#}"),
+                    });
+                }
+            }
+        }
+
+        printer.out(report)
+    }
+}
+
+/// Outcome of an import: ids of the cards created, replaced and kept
+/// (skipped), and the cards that failed.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub created: Vec<String>,
+    pub replaced: Vec<String>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
+
+/// A card of the stream that failed to import. The index is 1-based,
+/// in stream order.
+#[derive(Clone, Debug, Serialize)]
+pub struct ImportFailure {
+    pub index: usize,
+    pub uid: Option<String>,
+    pub error: String,
+}
+
+impl fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} created, {} replaced, {} skipped, {} failed",
+            self.created.len(),
+            self.replaced.len(),
+            self.skipped.len(),
+            self.failed.len(),
+        )?;
+
+        for failure in &self.failed {
+            match &failure.uid {
+                Some(uid) => writeln!(f, "  #{} ({uid}): {}", failure.index, failure.error)?,
+                None => writeln!(f, "  #{}: {}", failure.index, failure.error)?,
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,17 +1,35 @@
-use std::fmt;
+use std::{cmp::Ordering, fmt};
 
 use anyhow::Result;
 use clap::Parser;
 use comfy_table::{Cell, Color, Row, Table};
+use log::warn;
 use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
-use crate::shared::{arg::AddressbookIdArg, card::Card, client::AddressbookClient};
+use crate::{
+    account::context::Account,
+    project::{vcard_preview, vcard_values},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{Card, CardColumn, CardSort},
+        client::AddressbookClient,
+    },
+};
 
 /// List vCards inside the given addressbook.
 ///
+/// The table shows the `--columns` fields (`card.list.columns` in the
+/// config, ID, FN, EMAIL and TEL otherwise). With `--sort`
+/// (`card.list.sort`), the whole addressbook is sorted by formatted
+/// name, by structured name, or newest revision first, then the page is
+/// cut out of it. Over several accounts (`-a` repeated, or
+/// `--all-accounts`), the page is taken from the addressbook of each
+/// account and the rows are prefixed with their account and backend.
+///
 /// JSON output: `{"cards": [{"id", "addressbook_id", "etag", "fn_value",
-/// "email", "tel"}]}`.
+/// "n", "email", "tel", "org", "bday", "categories", "rev"}]}`, plus
+/// `"account"` and `"backend"` over several accounts.
 #[derive(Debug, Parser)]
 pub struct CardListCommand {
     #[command(flatten)]
@@ -22,26 +40,130 @@ pub struct CardListCommand {
     /// Maximum number of cards returned per page.
     #[arg(short = 's', long, value_name = "N")]
     pub page_size: Option<u32>,
+    #[command(flatten)]
+    pub table: CardsTableArg,
+}
+
+/// Columns and sort order of the cards table, shared by `card list`
+/// and `card search`.
+#[derive(Debug, Parser)]
+pub struct CardsTableArg {
+    /// Columns of the table, in order.
+    #[arg(long, value_name = "COLUMN", value_enum, value_delimiter = ',')]
+    pub columns: Vec<CardColumn>,
+    /// Sort order of the rows. `card list` sorts the whole addressbook
+    /// before cutting the page, so it fetches every card.
+    #[arg(long, value_name = "SORT", value_enum)]
+    pub sort: Option<CardSort>,
+}
+
+impl CardsTableArg {
+    /// Builds the cards table of the given cards, the flags winning
+    /// over the account config.
+    pub fn table(self, client: &AddressbookClient, cards: Vec<Card>) -> CardsTable {
+        let rows = cards.into_iter().map(CardRow::from).collect();
+        self.rows_table(&client.account, rows, false)
+    }
+
+    /// Builds the cards table of rows gathered over several accounts,
+    /// each tagged with its account and backend (see
+    /// [`CardRow::tagged`]). The first client styles the table.
+    pub fn tagged_table(self, clients: &[AddressbookClient], rows: Vec<CardRow>) -> CardsTable {
+        let fallback = Account::default();
+        let account = clients.first().map_or(&fallback, |client| &client.account);
+        self.rows_table(account, rows, true)
+    }
+
+    fn rows_table(self, account: &Account, mut rows: Vec<CardRow>, tagged: bool) -> CardsTable {
+        let columns = if self.columns.is_empty() {
+            account.cards_list_columns()
+        } else {
+            self.columns
+        };
+
+        if let Some(sort) = self.sort.or(account.cards_list_sort()) {
+            sort_rows(&mut rows, sort);
+        }
+
+        CardsTable {
+            preset: account.table_preset().to_string(),
+            columns,
+            id_color: account.cards_list_table_id_color(),
+            fn_color: account.cards_list_table_fn_color(),
+            email_color: account.cards_list_table_email_color(),
+            tel_color: account.cards_list_table_tel_color(),
+            tagged,
+            rows,
+        }
+    }
 }
 
 impl CardListCommand {
     pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
-        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+        let rows = self.rows(&mut client, false)?;
+        printer.out(self.table.rows_table(&client.account, rows, false))
+    }
+
+    /// Lists the addressbook of each account. An account failing to
+    /// list is skipped with a warning.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        mut clients: Vec<AddressbookClient>,
+    ) -> Result<()> {
+        let mut rows = Vec::new();
+
+        for client in &mut clients {
+            match self.rows(client, true) {
+                Ok(client_rows) => rows.extend(client_rows),
+                Err(err) => warn!(
+                    "cannot list cards of account `{}`, skipping it: {err:#}",
+                    client.account_name
+                ),
+            }
+        }
+
+        printer.out(self.table.tagged_table(&clients, rows))
+    }
+
+    /// Rows of the requested page of the addressbook of `client`. With
+    /// a sort order, every card is fetched and sorted before the page
+    /// is cut, so that pages follow the sort order rather than the
+    /// backend listing order.
+    fn rows(&self, client: &mut AddressbookClient, tagged: bool) -> Result<Vec<CardRow>> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id.clone())?;
         let page_size = self
             .page_size
             .unwrap_or(client.account.cards_list_page_size());
-        let cards = client.list_cards(&addressbook_id, Some(self.page), Some(page_size))?;
-
-        let table = CardsTable {
-            preset: client.account.table_preset().to_string(),
-            id_color: client.account.cards_list_table_id_color(),
-            fn_color: client.account.cards_list_table_fn_color(),
-            email_color: client.account.cards_list_table_email_color(),
-            tel_color: client.account.cards_list_table_tel_color(),
-            rows: cards.into_iter().map(CardRow::from).collect(),
+        let sort = self.table.sort.or(client.account.cards_list_sort());
+
+        let cards = match sort {
+            Some(_) => client.list_cards(&addressbook_id, None, None)?,
+            None => client.list_cards(&addressbook_id, Some(self.page), Some(page_size))?,
         };
 
-        printer.out(table)
+        let mut rows: Vec<CardRow> = cards
+            .into_iter()
+            .map(|card| {
+                if tagged {
+                    CardRow::tagged(card, client)
+                } else {
+                    CardRow::from(card)
+                }
+            })
+            .collect();
+
+        if let Some(sort) = sort {
+            sort_rows(&mut rows, sort);
+            let skip = (self.page.max(1) as usize - 1).saturating_mul(page_size as usize);
+            rows = rows
+                .into_iter()
+                .skip(skip)
+                .take(page_size as usize)
+                .collect();
+        }
+
+        Ok(rows)
     }
 }
 
@@ -50,6 +172,8 @@ pub struct CardsTable {
     #[serde(skip)]
     pub preset: String,
     #[serde(skip)]
+    pub columns: Vec<CardColumn>,
+    #[serde(skip)]
     pub id_color: Color,
     #[serde(skip)]
     pub fn_color: Color,
@@ -57,31 +181,128 @@ pub struct CardsTable {
     pub email_color: Color,
     #[serde(skip)]
     pub tel_color: Color,
+    /// Whether the rows come from several accounts, shown first.
+    #[serde(skip)]
+    pub tagged: bool,
     #[serde(rename = "cards")]
     pub rows: Vec<CardRow>,
 }
 
 #[derive(Clone, Debug, Serialize)]
 pub struct CardRow {
+    /// Account the card comes from, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Backend the card comes from, over several accounts only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
     pub id: String,
     pub addressbook_id: String,
     pub etag: Option<String>,
     pub fn_value: Option<String>,
+    pub n: Option<String>,
     pub email: Option<String>,
     pub tel: Option<String>,
+    pub org: Option<String>,
+    pub bday: Option<String>,
+    pub categories: Option<String>,
+    pub rev: Option<String>,
 }
 
 impl From<Card> for CardRow {
     fn from(card: Card) -> Self {
         let (fn_value, email, tel) = vcard_preview(&card.contents);
-        Self {
+
+        let mut row = Self {
+            account: None,
+            backend: None,
             id: card.id,
             addressbook_id: card.addressbook_id,
             etag: card.etag,
             fn_value,
+            n: None,
             email,
             tel,
+            org: None,
+            bday: None,
+            categories: None,
+            rev: None,
+        };
+
+        for (name, value) in vcard_values(&card.contents) {
+            let slot = match name.as_str() {
+                "N" => &mut row.n,
+                "ORG" => &mut row.org,
+                "BDAY" => &mut row.bday,
+                "CATEGORIES" => &mut row.categories,
+                "REV" => &mut row.rev,
+                _ => continue,
+            };
+            if slot.is_none() && !value.is_empty() {
+                *slot = Some(value.split_whitespace().collect::<Vec<_>>().join(" "));
+            }
         }
+
+        row
+    }
+}
+
+impl CardRow {
+    /// Row of a card tagged with the account and backend of the client
+    /// it was read through.
+    pub fn tagged(card: Card, client: &AddressbookClient) -> Self {
+        Self {
+            account: Some(client.account_name.clone()),
+            backend: Some(client.backend().to_string()),
+            ..Self::from(card)
+        }
+    }
+
+    /// Text of the given column.
+    fn column(&self, column: CardColumn) -> &str {
+        let value = match column {
+            CardColumn::Id => return &self.id,
+            CardColumn::Fn => &self.fn_value,
+            CardColumn::N => &self.n,
+            CardColumn::Email => &self.email,
+            CardColumn::Tel => &self.tel,
+            CardColumn::Org => &self.org,
+            CardColumn::Bday => &self.bday,
+            CardColumn::Categories => &self.categories,
+            CardColumn::Rev => &self.rev,
+            CardColumn::Etag => &self.etag,
+        };
+
+        value.as_deref().unwrap_or("")
+    }
+}
+
+/// Sorts the rows in place, the ones missing the sort key last.
+fn sort_rows(rows: &mut [CardRow], sort: CardSort) {
+    let key = |row: &CardRow| match sort {
+        CardSort::Fn => row.fn_value.as_ref().map(|name| name.to_lowercase()),
+        CardSort::N => row
+            .n
+            .as_ref()
+            .or(row.fn_value.as_ref())
+            .map(|name| name.to_lowercase()),
+        // NOTE: REV timestamps compare as text once their separators
+        // are dropped, basic and extended ISO 8601 forms alike.
+        CardSort::Rev => row
+            .rev
+            .as_ref()
+            .map(|rev| rev.chars().filter(char::is_ascii_digit).collect()),
+    };
+
+    rows.sort_by_cached_key(|row| {
+        let key = key(row);
+        (key.is_none(), key)
+    });
+
+    if sort == CardSort::Rev {
+        // NOTE: newest first, still keeping the rows without REV last.
+        let dated = rows.iter().take_while(|row| row.rev.is_some()).count();
+        rows[..dated].reverse();
     }
 }
 
@@ -91,19 +312,32 @@ impl fmt::Display for CardsTable {
 
         table
             .load_preset(&self.preset)
-            .set_header(Row::from([
-                Cell::new("ID"),
-                Cell::new("FN"),
-                Cell::new("EMAIL"),
-                Cell::new("TEL"),
-            ]))
+            .set_header(Row::from(
+                self.tagged
+                    .then_some(["ACCOUNT", "BACKEND"])
+                    .into_iter()
+                    .flatten()
+                    .chain(self.columns.iter().map(|column| column_header(*column)))
+                    .map(Cell::new),
+            ))
             .add_rows(self.rows.iter().map(|card| {
                 let mut row = Row::new();
-                row.max_height(1)
-                    .add_cell(Cell::new(&card.id).fg(self.id_color))
-                    .add_cell(Cell::new(card.fn_value.as_deref().unwrap_or("")).fg(self.fn_color))
-                    .add_cell(Cell::new(card.email.as_deref().unwrap_or("")).fg(self.email_color))
-                    .add_cell(Cell::new(card.tel.as_deref().unwrap_or("")).fg(self.tel_color));
+                row.max_height(1);
+                if self.tagged {
+                    row.add_cell(Cell::new(card.account.as_deref().unwrap_or("")))
+                        .add_cell(Cell::new(card.backend.as_deref().unwrap_or("")));
+                }
+                for column in &self.columns {
+                    let cell = Cell::new(card.column(*column));
+                    let cell = match column {
+                        CardColumn::Id => cell.fg(self.id_color),
+                        CardColumn::Fn => cell.fg(self.fn_color),
+                        CardColumn::Email => cell.fg(self.email_color),
+                        CardColumn::Tel => cell.fg(self.tel_color),
+                        _ => cell,
+                    };
+                    row.add_cell(cell);
+                }
                 row
             }));
 
@@ -114,43 +348,17 @@ impl fmt::Display for CardsTable {
     }
 }
 
-/// Quick-and-dirty vCard preview: pulls the first `FN`, `EMAIL` and
-/// `TEL` line out of the raw bytes for the cards listing. Avoids
-/// parsing the whole vCard just to render three columns.
-fn vcard_preview(bytes: &[u8]) -> (Option<String>, Option<String>, Option<String>) {
-    let text = match std::str::from_utf8(bytes) {
-        Ok(s) => s,
-        Err(_) => return (None, None, None),
-    };
-
-    let mut fn_value = None;
-    let mut email = None;
-    let mut tel = None;
-
-    for line in text.lines() {
-        let line = line.trim();
-        if fn_value.is_none() && line.starts_with("FN:") {
-            fn_value = Some(line[3..].to_string());
-        } else if email.is_none() {
-            if let Some(rest) = pick_property(line, "EMAIL") {
-                email = Some(rest);
-            }
-        } else if tel.is_none()
-            && let Some(rest) = pick_property(line, "TEL")
-        {
-            tel = Some(rest);
-        }
+fn column_header(column: CardColumn) -> &'static str {
+    match column {
+        CardColumn::Id => "ID",
+        CardColumn::Fn => "FN",
+        CardColumn::N => "N",
+        CardColumn::Email => "EMAIL",
+        CardColumn::Tel => "TEL",
+        CardColumn::Org => "ORG",
+        CardColumn::Bday => "BDAY",
+        CardColumn::Categories => "CATEGORIES",
+        CardColumn::Rev => "REV",
+        CardColumn::Etag => "ETAG",
     }
-
-    (fn_value, email, tel)
-}
-
-fn pick_property(line: &str, key: &str) -> Option<String> {
-    if !line.starts_with(key) {
-        return None;
-    }
-
-    let after_key = &line[key.len()..];
-    let colon = after_key.find(':')?;
-    Some(after_key[colon + 1..].to_string())
 }
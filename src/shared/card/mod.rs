@@ -1,10 +1,21 @@
 mod types;
 
+pub mod add_from_email;
+pub mod birthdays;
+pub mod changes;
 pub mod cli;
 pub mod create;
+pub mod dedupe;
 pub mod delete;
+pub mod edit;
+pub mod export;
+pub mod import;
 pub mod list;
+pub mod photo;
+pub mod query_addresses;
 pub mod read;
+pub mod search;
+pub mod set;
 pub mod update;
 pub mod vcard;
 
@@ -0,0 +1,29 @@
+use anyhow::Result;
+use clap::Subcommand;
+use pimalaya_cli::printer::Printer;
+
+use crate::shared::{
+    card::photo::{
+        get::CardPhotoGetCommand, remove::CardPhotoRemoveCommand, set::CardPhotoSetCommand,
+    },
+    client::AddressbookClient,
+};
+
+/// Manage the photo of a vCard.
+#[derive(Debug, Subcommand)]
+pub enum CardPhotoCommand {
+    Get(CardPhotoGetCommand),
+    Set(CardPhotoSetCommand),
+    #[command(visible_alias = "rm")]
+    Remove(CardPhotoRemoveCommand),
+}
+
+impl CardPhotoCommand {
+    pub fn execute(self, printer: &mut impl Printer, client: AddressbookClient) -> Result<()> {
+        match self {
+            Self::Get(cmd) => cmd.execute(printer, client),
+            Self::Set(cmd) => cmd.execute(printer, client),
+            Self::Remove(cmd) => cmd.execute(printer, client),
+        }
+    }
+}
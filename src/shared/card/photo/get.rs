@@ -0,0 +1,156 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Parser;
+use pimalaya_cli::{
+    clap::parsers::path_parser,
+    printer::{Message, Printer},
+};
+use url::Url;
+
+use crate::{
+    project::{VcardPhoto, vcard_photo},
+    shared::{arg::AddressbookIdArg, client::AddressbookClient},
+};
+
+/// Save the photo of a vCard to a file.
+///
+/// Writes the picture of the first PHOTO property: an inline one (4.0
+/// `data:` URI or 3.0 `ENCODING=b` value) is decoded, one held by an
+/// `https:` URI is downloaded, following redirects, up to 10 MiB.
+/// Google People contact photos come along with the card, as a URI;
+/// the Microsoft Graph contact photo, which card reads and listings
+/// leave out, is fetched on its own.
+///
+/// JSON output: `{"message": "..."}`.
+#[derive(Debug, Parser)]
+pub struct CardPhotoGetCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Card UID.
+    #[arg(value_name = "CARD-ID")]
+    pub card_id: String,
+    /// File to write the photo to.
+    #[arg(value_name = "PATH", value_parser = path_parser)]
+    pub path: PathBuf,
+}
+
+impl CardPhotoGetCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+
+        let card = client.get_card_with_photo(&addressbook_id, &self.card_id)?;
+        let vcard = String::from_utf8_lossy(&card.contents);
+
+        let bytes = match vcard_photo(&vcard) {
+            Some(VcardPhoto::Inline { bytes, .. }) => bytes,
+            Some(VcardPhoto::Uri(uri)) => fetch_photo(&uri)?,
+            None => bail!("Card `{}` has no photo", self.card_id),
+        };
+
+        fs::write(&self.path, bytes)
+            .with_context(|| format!("Cannot write photo to `{}`", self.path.display()))?;
+
+        printer.out(Message::new(format!(
+            "Photo of card `{}` saved to `{}`",
+            self.card_id,
+            self.path.display()
+        )))
+    }
+}
+
+/// Redirects `get` follows before giving up on a photo URL.
+const MAX_REDIRECTS: usize = 5;
+
+/// Downloads the picture a PHOTO URI points to, following redirects.
+fn fetch_photo(uri: &str) -> Result<Vec<u8>> {
+    let mut url = Url::parse(uri).with_context(|| format!("Invalid photo URI `{uri}`"))?;
+
+    for _ in 0..=MAX_REDIRECTS {
+        if url.scheme() != "https" {
+            bail!("Cannot fetch photo `{url}`: only `https` URIs are supported");
+        }
+
+        match download(&url)? {
+            (Some(location), _) => url = location,
+            (None, bytes) => return Ok(bytes),
+        }
+    }
+
+    bail!("Cannot fetch photo `{uri}`: too many redirects")
+}
+
+/// Sends an unauthenticated GET to `url` over a fresh TLS stream,
+/// through the redirect-aware coroutine of the CardDAV `.well-known`
+/// probe, returning the redirect target if any, and the body. Bails
+/// once the response outgrows 10 MiB.
+#[cfg(any(
+    feature = "carddav",
+    feature = "jmap",
+    feature = "msgraph",
+    feature = "google"
+))]
+fn download(url: &Url) -> Result<(Option<Url>, Vec<u8>)> {
+    use std::io::{Read, Write};
+
+    use io_http::{
+        coroutine::{HttpCoroutine, HttpCoroutineState, HttpYield},
+        rfc8615::well_known::Http11WellKnown,
+        rfc9110::request::HttpRequest,
+    };
+    use pimalaya_stream::std::stream::StreamStd;
+
+    use crate::config::TlsConfig;
+
+    /// Largest photo response read, in bytes.
+    const MAX_PHOTO_SIZE: usize = 10 * 1024 * 1024;
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| anyhow!("Photo URL `{url}` has no host"))?;
+    let port = url.port_or_known_default().unwrap_or(443);
+    let tls = TlsConfig::default().into_tls(vec!["http/1.1".into()]);
+
+    let mut stream = StreamStd::connect_tls(host, port, &tls)?;
+    let mut coroutine = Http11WellKnown::new(HttpRequest::get(url.clone()));
+    let mut buf = [0u8; 8 * 1024];
+    let mut arg: Option<&[u8]> = None;
+    let mut received = 0;
+
+    let output = loop {
+        match coroutine.resume(arg.take()) {
+            HttpCoroutineState::Complete(Ok(output)) => break output,
+            HttpCoroutineState::Complete(Err(err)) => return Err(err.into()),
+            HttpCoroutineState::Yielded(HttpYield::WantsWrite(bytes)) => {
+                stream.write_all(&bytes)?;
+            }
+            HttpCoroutineState::Yielded(HttpYield::WantsRead) => {
+                let n = stream.read(&mut buf)?;
+                received += n;
+                if received > MAX_PHOTO_SIZE {
+                    bail!("Cannot download photo `{url}`: larger than {MAX_PHOTO_SIZE} bytes");
+                }
+                arg = Some(&buf[..n]);
+            }
+        }
+    };
+
+    if output.redirect_url.is_none() && !output.response.status.is_success() {
+        bail!(
+            "Cannot download photo `{url}`: HTTP {}",
+            output.response.status
+        );
+    }
+
+    Ok((output.redirect_url, output.response.body))
+}
+
+#[cfg(not(any(
+    feature = "carddav",
+    feature = "jmap",
+    feature = "msgraph",
+    feature = "google"
+)))]
+fn download(url: &Url) -> Result<(Option<Url>, Vec<u8>)> {
+    bail!("Cannot download photo `{url}`: cardamum was built without any HTTP backend")
+}
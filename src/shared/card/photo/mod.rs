@@ -0,0 +1,4 @@
+pub mod cli;
+pub mod get;
+pub mod remove;
+pub mod set;
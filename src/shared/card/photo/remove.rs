@@ -0,0 +1,48 @@
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::{
+    project::{replace_photo, vcard_photo},
+    shared::{arg::AddressbookIdArg, client::AddressbookClient},
+};
+
+/// Remove the photo of a vCard.
+///
+/// Drops every PHOTO property of the card and writes it back, guarded
+/// by the ETag read when the backend supports it. The Microsoft Graph
+/// and Google People contact photo is deleted along.
+///
+/// JSON output: `{"message": "..."}`.
+#[derive(Debug, Parser)]
+pub struct CardPhotoRemoveCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Card UID.
+    #[arg(value_name = "CARD-ID")]
+    pub card_id: String,
+}
+
+impl CardPhotoRemoveCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+
+        let card = client.get_card_with_photo(&addressbook_id, &self.card_id)?;
+        let vcard = String::from_utf8(card.contents)
+            .map_err(|_| anyhow!("Card contents are not valid UTF-8"))?;
+
+        if vcard_photo(&vcard).is_none() {
+            bail!("Card `{}` has no photo", self.card_id);
+        }
+
+        let vcard = replace_photo(&vcard, None);
+        let if_match = card.etag.as_deref().filter(|_| client.guards_updates());
+        client.update_card(&addressbook_id, &self.card_id, vcard.into_bytes(), if_match)?;
+        client.delete_card_photo(&addressbook_id, &self.card_id)?;
+
+        printer.out(Message::new(format!(
+            "Photo of card `{}` successfully removed",
+            self.card_id
+        )))
+    }
+}
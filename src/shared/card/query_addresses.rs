@@ -0,0 +1,153 @@
+use std::{collections::HashSet, fmt};
+
+use anyhow::Result;
+use clap::Parser;
+use pimalaya_cli::printer::Printer;
+use serde::Serialize;
+
+use crate::{
+    project::vcard_values,
+    shared::{card::CardQuery, client::AddressbookClient},
+};
+
+/// Print the email addresses matching a query, for email clients.
+///
+/// Searches every addressbook of the account and prints one
+/// `EMAIL<TAB>NAME<TAB>ADDRESSBOOK` line per email address whose
+/// address or card name (FN, N, NICKNAME) contains the query
+/// (case-insensitive), the format of mutt's `query_command` and aerc's
+/// `address-book-cmd`. Without a query, every address is printed.
+/// mutt skips the first line of the output, which `--header` fills
+/// with a status line.
+///
+/// JSON output: `{"addresses": [{"email", "name", "addressbook"}]}`.
+#[derive(Debug, Parser)]
+pub struct CardQueryAddressesCommand {
+    /// Text matched against the email addresses and names.
+    #[arg(value_name = "QUERY")]
+    pub query: Option<String>,
+    /// Print a status line first, as mutt's `query_command` expects.
+    #[arg(long)]
+    pub header: bool,
+}
+
+impl CardQueryAddressesCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let needle = self
+            .query
+            .as_deref()
+            .map(str::trim)
+            .filter(|query| !query.is_empty())
+            .map(str::to_lowercase);
+
+        let mut addresses = Vec::new();
+        let mut seen = HashSet::new();
+
+        for addressbook in client.list_addressbooks()? {
+            let cards = match &needle {
+                Some(needle) => {
+                    let query = CardQuery {
+                        text: Some(needle.clone()),
+                        ..Default::default()
+                    };
+                    client.search_cards(&addressbook.id, &query)?
+                }
+                None => client.list_cards(&addressbook.id, None, None)?,
+            };
+
+            for card in cards {
+                let mut names = Vec::new();
+                let mut emails = Vec::new();
+
+                for (prop, value) in vcard_values(&card.contents) {
+                    match prop.as_str() {
+                        "FN" | "N" | "NICKNAME" => names.push(value),
+                        "EMAIL" => {
+                            let email = value.strip_prefix("mailto:").unwrap_or(&value);
+                            emails.push(email.trim().to_string());
+                        }
+                        _ => {}
+                    }
+                }
+
+                // NOTE: the first non-empty of FN, N and NICKNAME, in
+                // card order; structured components leave runs of
+                // spaces behind.
+                let name = names
+                    .iter()
+                    .map(|name| name.split_whitespace().collect::<Vec<_>>().join(" "))
+                    .find(|name| !name.is_empty())
+                    .unwrap_or_default();
+
+                let card_matches = needle.as_deref().is_none_or(|needle| {
+                    names
+                        .iter()
+                        .any(|name| name.to_lowercase().contains(needle))
+                });
+
+                for email in emails {
+                    let matches = card_matches
+                        || needle
+                            .as_deref()
+                            .is_some_and(|needle| email.to_lowercase().contains(needle));
+
+                    if email.is_empty() || !matches {
+                        continue;
+                    }
+
+                    if seen.insert((email.to_lowercase(), name.clone())) {
+                        addresses.push(AddressLine {
+                            email,
+                            name: name.clone(),
+                            addressbook: addressbook.name.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        printer.out(AddressLines {
+            header: self.header,
+            addresses,
+        })
+    }
+}
+
+/// Matching email addresses, one line each.
+#[derive(Clone, Debug, Serialize)]
+pub struct AddressLines {
+    /// Whether a status line comes first.
+    #[serde(skip)]
+    pub header: bool,
+    pub addresses: Vec<AddressLine>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AddressLine {
+    pub email: String,
+    pub name: String,
+    pub addressbook: String,
+}
+
+impl fmt::Display for AddressLines {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // NOTE: tabs and line breaks would shift the fields.
+        let field = |value: &str| value.replace(['\t', '\r', '\n'], " ");
+
+        if self.header {
+            writeln!(f, "{} matching address(es)", self.addresses.len())?;
+        }
+
+        for line in &self.addresses {
+            writeln!(
+                f,
+                "{}\t{}\t{}",
+                field(&line.email),
+                field(&line.name),
+                field(&line.addressbook)
+            )?;
+        }
+
+        Ok(())
+    }
+}
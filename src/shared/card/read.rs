@@ -1,16 +1,30 @@
 use core::fmt;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use pimalaya_cli::printer::Printer;
 use serde::Serialize;
 
-use crate::shared::{arg::AddressbookIdArg, client::AddressbookClient};
+use crate::{
+    project::{convert_vcard, vcard_types},
+    shared::{
+        arg::AddressbookIdArg,
+        card::{export::ExportedCard, vcard::VcardVersionArg},
+        client::AddressbookClient,
+    },
+};
 
-/// Read the raw vCard bytes of the given card.
+/// Read the given card.
 ///
-/// JSON output: `{"id", "etag", "contents"}`, with the raw vCard in
-/// `contents`.
+/// The `raw` format prints the vCard bytes as stored. The `pretty`
+/// format renders the card for humans: name, emails, phones and
+/// addresses with their TYPE labels, organization, birthday, notes and
+/// photo presence. With `--vcard-version`, the card is converted to
+/// that vCard version first.
+///
+/// JSON output (global `--json`, whatever the format): the parsed
+/// fields, in the same schema as `card export --format json`, with the
+/// raw vCard in `vcard`.
 #[derive(Debug, Parser)]
 pub struct CardReadCommand {
     #[command(flatten)]
@@ -18,31 +32,121 @@ pub struct CardReadCommand {
     /// Card UID.
     #[arg(value_name = "CARD-ID")]
     pub card_id: String,
+    /// Rendering of the card.
+    #[arg(short, long, value_name = "FORMAT", value_enum, default_value_t = ReadFormatArg::Raw)]
+    pub format: ReadFormatArg,
+    /// vCard version to convert the card to.
+    #[arg(long, value_name = "VERSION", value_enum)]
+    pub vcard_version: Option<VcardVersionArg>,
+}
+
+/// Rendering of `card read --format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum ReadFormatArg {
+    Raw,
+    Pretty,
 }
 
 impl CardReadCommand {
     pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
         let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
-        let card = client.get_card(&addressbook_id, &self.card_id)?;
-        let card = Card {
-            id: card.id,
-            etag: card.etag,
-            contents: String::from_utf8(card.contents)?,
-        };
-
-        printer.out(card)
+        let mut card = client.get_card(&addressbook_id, &self.card_id)?;
+
+        if let Some(version) = self.vcard_version {
+            let contents = String::from_utf8(card.contents)?;
+            card.contents = convert_vcard(&contents, version.into()).into_bytes();
+        }
+
+        let card = ExportedCard::from(&card);
+        let types = |name| vcard_types(&card.vcard, name);
+
+        printer.out(CardView {
+            format: self.format,
+            email_types: types("EMAIL"),
+            tel_types: types("TEL"),
+            adr_types: types("ADR"),
+            card,
+        })
     }
 }
 
+/// Parsed card, printed as JSON or rendered by `--format`.
 #[derive(Clone, Debug, Serialize)]
-pub struct Card {
-    pub id: String,
-    pub etag: Option<String>,
-    pub contents: String,
+pub struct CardView {
+    #[serde(skip)]
+    pub format: ReadFormatArg,
+    /// TYPE labels of the EMAIL, TEL and ADR values, in card order.
+    #[serde(skip)]
+    pub email_types: Vec<Vec<String>>,
+    #[serde(skip)]
+    pub tel_types: Vec<Vec<String>>,
+    #[serde(skip)]
+    pub adr_types: Vec<Vec<String>>,
+    #[serde(flatten)]
+    pub card: ExportedCard,
 }
 
-impl fmt::Display for Card {
+impl fmt::Display for CardView {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.contents)
+        let card = &self.card;
+
+        if self.format == ReadFormatArg::Raw {
+            return write!(f, "{}", card.vcard);
+        }
+
+        let name = card.fn_value.as_deref().or(card.n.as_deref());
+        writeln!(f, "{}", name.unwrap_or("(no name)"))?;
+
+        writeln!(f)?;
+
+        let fields: [(&str, Vec<String>); 10] = [
+            ("Nickname", card.nickname.clone()),
+            ("Email", labelled(&card.email, &self.email_types)),
+            ("Phone", labelled(&card.tel, &self.tel_types)),
+            ("Address", labelled(&card.adr, &self.adr_types)),
+            ("Org", card.org.clone().into_iter().collect()),
+            ("Title", card.title.clone().into_iter().collect()),
+            ("Birthday", card.bday.clone().into_iter().collect()),
+            ("Website", card.url.clone()),
+            (
+                "Photo",
+                if card.photo {
+                    vec!["yes".into()]
+                } else {
+                    vec![]
+                },
+            ),
+            (
+                "Note",
+                card.note
+                    .iter()
+                    .flat_map(|note| note.lines())
+                    .map(String::from)
+                    .collect(),
+            ),
+        ];
+
+        for (label, values) in fields {
+            for (index, value) in values.into_iter().enumerate() {
+                let label = if index == 0 { label } else { "" };
+                writeln!(f, "  {label:<10} {value}")?;
+            }
+        }
+
+        Ok(())
     }
 }
+
+/// Values followed by their TYPE labels, when they have any:
+/// `jane@acme.org (work, internet)`.
+fn labelled(values: &[String], types: &[Vec<String>]) -> Vec<String> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| match types.get(index) {
+            Some(types) if !types.is_empty() => format!("{value} ({})", types.join(", ")),
+            _ => value.clone(),
+        })
+        .collect()
+}
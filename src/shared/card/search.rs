@@ -0,0 +1,115 @@
+use anyhow::{Result, bail};
+use clap::Parser;
+use log::warn;
+use pimalaya_cli::printer::Printer;
+
+use crate::shared::{
+    arg::AddressbookIdArg,
+    card::{
+        CardQuery,
+        list::{CardRow, CardsTableArg},
+    },
+    client::AddressbookClient,
+};
+
+/// Search vCards inside the given addressbook.
+///
+/// Every criterion must match (case-insensitive substring; phone
+/// numbers compare digits only). The free-text query matches any
+/// property. JMAP narrows the candidates server-side with its text
+/// filter; the other backends list the addressbook (the prefix-only
+/// searches of Microsoft Graph and Google People would miss substring
+/// matches). The final matching always runs over the vCards, so every
+/// backend returns the same cards. The table takes the `card list`
+/// columns and sort order. Over several accounts (`-a` repeated, or
+/// `--all-accounts`), the addressbook of each account is searched and
+/// the rows are prefixed with their account and backend.
+///
+/// JSON output: `{"cards": [{"id", "addressbook_id", "etag", "fn_value",
+/// "n", "email", "tel", "org", "bday", "categories", "rev"}]}`, plus
+/// `"account"` and `"backend"` over several accounts.
+#[derive(Debug, Parser)]
+pub struct CardSearchCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Free text matched against every property.
+    #[arg(value_name = "QUERY")]
+    pub query: Option<String>,
+    /// Text matched against the formatted, structured and nick names.
+    #[arg(long, value_name = "TEXT")]
+    pub name: Option<String>,
+    /// Text matched against the email addresses.
+    #[arg(long, value_name = "TEXT")]
+    pub email: Option<String>,
+    /// Phone number matched against the phone numbers.
+    #[arg(long, value_name = "NUMBER")]
+    pub tel: Option<String>,
+    /// Text matched against the organization.
+    #[arg(long, value_name = "TEXT")]
+    pub org: Option<String>,
+    #[command(flatten)]
+    pub table: CardsTableArg,
+}
+
+impl CardSearchCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let query = self.query()?;
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+        let cards = client.search_cards(&addressbook_id, &query)?;
+
+        printer.out(self.table.table(&client, cards))
+    }
+
+    /// Searches the addressbook of each account. An account failing to
+    /// search is skipped with a warning.
+    pub fn execute_many(
+        self,
+        printer: &mut impl Printer,
+        mut clients: Vec<AddressbookClient>,
+    ) -> Result<()> {
+        let query = self.query()?;
+        let mut rows = Vec::new();
+
+        for client in &mut clients {
+            let cards = client
+                .account
+                .addressbook_id(self.addressbook.id.clone())
+                .and_then(|addressbook_id| client.search_cards(&addressbook_id, &query));
+            let cards = match cards {
+                Ok(cards) => cards,
+                Err(err) => {
+                    warn!(
+                        "cannot search cards of account `{}`, skipping it: {err:#}",
+                        client.account_name
+                    );
+                    continue;
+                }
+            };
+
+            rows.extend(
+                cards
+                    .into_iter()
+                    .map(|card| CardRow::tagged(card, &*client)),
+            );
+        }
+
+        printer.out(self.table.tagged_table(&clients, rows))
+    }
+
+    /// The search criteria, at least one being required.
+    fn query(&self) -> Result<CardQuery> {
+        let query = CardQuery {
+            text: self.query.clone(),
+            name: self.name.clone(),
+            email: self.email.clone(),
+            tel: self.tel.clone(),
+            org: self.org.clone(),
+        };
+
+        if query.is_empty() {
+            bail!("Missing search criteria: pass a query, --name, --email, --tel or --org");
+        }
+
+        Ok(query)
+    }
+}
@@ -0,0 +1,134 @@
+use anyhow::{Error, Result, anyhow, bail};
+use clap::Parser;
+use pimalaya_cli::printer::{Message, Printer};
+
+use crate::{
+    project::{PatchValue, VcardPatch, patch_vcard},
+    shared::{arg::AddressbookIdArg, client::AddressbookClient},
+};
+
+/// Set or unset individual properties of a vCard.
+///
+/// Reads the card, replaces every property given by a flag (all its
+/// existing values make way for the new ones), drops the `--unset`
+/// ones, and writes it back, guarded by the ETag read when the backend
+/// supports it. Multi-valued flags take an optional comma-separated
+/// TYPE prefix: `--email work=jane@acme.org`, `--tel cell,pref=+33…`.
+///
+/// JSON output: `{"message": "..."}`.
+#[derive(Debug, Parser)]
+pub struct CardSetCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Card UID.
+    #[arg(value_name = "CARD-ID")]
+    pub card_id: String,
+
+    /// Formatted name.
+    #[arg(long = "fn", value_name = "TEXT")]
+    pub fn_value: Option<String>,
+    /// Email address, repeatable.
+    #[arg(long, value_name = "[TYPE=]EMAIL")]
+    pub email: Vec<String>,
+    /// Phone number, repeatable.
+    #[arg(long, value_name = "[TYPE=]NUMBER")]
+    pub tel: Vec<String>,
+    /// Nickname, repeatable.
+    #[arg(long, value_name = "TEXT")]
+    pub nickname: Vec<String>,
+    /// Organization.
+    #[arg(long, value_name = "TEXT")]
+    pub org: Option<String>,
+    /// Job title.
+    #[arg(long, value_name = "TEXT")]
+    pub title: Option<String>,
+    /// Website, repeatable.
+    #[arg(long, value_name = "[TYPE=]URL")]
+    pub url: Vec<String>,
+    /// Free-form note.
+    #[arg(long, value_name = "TEXT")]
+    pub note: Option<String>,
+    /// Property to remove entirely (e.g. `NOTE`, `X-FOO`), repeatable.
+    #[arg(long, value_name = "PROPERTY")]
+    pub unset: Vec<String>,
+}
+
+impl CardSetCommand {
+    pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
+        let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
+
+        let mut patch = VcardPatch {
+            unset: self.unset,
+            ..Default::default()
+        };
+
+        let single = [
+            ("FN", self.fn_value),
+            ("ORG", self.org),
+            ("TITLE", self.title),
+            ("NOTE", self.note),
+        ];
+        for (name, value) in single {
+            if let Some(value) = value {
+                patch.set.push((name.into(), vec![untyped(value)]));
+            }
+        }
+
+        let multi = [
+            ("EMAIL", self.email, true),
+            ("TEL", self.tel, true),
+            ("NICKNAME", self.nickname, false),
+            ("URL", self.url, true),
+        ];
+        for (name, values, typed) in multi {
+            if !values.is_empty() {
+                let values = if typed {
+                    values.into_iter().map(parse_typed).collect()
+                } else {
+                    values.into_iter().map(untyped).collect()
+                };
+                patch.set.push((name.into(), values));
+            }
+        }
+
+        if patch.set.is_empty() && patch.unset.is_empty() {
+            bail!("Nothing to change: pass at least one property flag or --unset");
+        }
+
+        let card = client.get_card(&addressbook_id, &self.card_id)?;
+        let vcard = String::from_utf8(card.contents)
+            .map_err(|_| anyhow!("Card contents are not valid UTF-8"))?;
+        let vcard = patch_vcard(&vcard, &patch).map_err(Error::msg)?;
+
+        let if_match = card.etag.as_deref().filter(|_| client.guards_updates());
+        client.update_card(&addressbook_id, &self.card_id, vcard.into_bytes(), if_match)?;
+
+        printer.out(Message::new(format!(
+            "Card `{}` successfully updated",
+            self.card_id
+        )))
+    }
+}
+
+pub fn untyped(value: String) -> PatchValue {
+    PatchValue {
+        types: vec![],
+        value,
+    }
+}
+
+/// Parses a `[TYPE[,TYPE…]=]VALUE` flag value. The prefix only counts
+/// as types when made of plain words, so values holding a `=` (URL
+/// query strings) stay intact.
+pub fn parse_typed(raw: String) -> PatchValue {
+    let plain =
+        |ty: &str| !ty.is_empty() && ty.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+
+    match raw.split_once('=') {
+        Some((types, value)) if types.split(',').all(plain) => PatchValue {
+            types: types.split(',').map(str::to_string).collect(),
+            value: value.to_string(),
+        },
+        _ => untyped(raw),
+    }
+}
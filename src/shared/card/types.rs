@@ -1,5 +1,6 @@
 //! Card types shared across every backend.
 
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 
 /// A single card inside an addressbook.
@@ -29,3 +30,105 @@ pub struct Card {
     /// Raw vCard bytes.
     pub contents: Vec<u8>,
 }
+
+/// Cards changed inside an addressbook since a change token.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CardChanges {
+    /// Ids of the cards created since the token. Without a token
+    /// (initial feed), every card of the addressbook. Backends whose
+    /// feed does not tell creates from updates (CardDAV, Graph,
+    /// Google) report later creates under `updated`.
+    pub created: Vec<String>,
+
+    /// Ids of the cards updated since the token.
+    pub updated: Vec<String>,
+
+    /// Ids of the cards deleted, or moved out of the addressbook,
+    /// since the token. Backends with account-wide feeds (JMAP,
+    /// Google) may list ids the addressbook never held.
+    pub deleted: Vec<String>,
+
+    /// Opaque token to resume the feed from.
+    pub token: String,
+}
+
+/// Card search criteria, all of which must match. Each one is a
+/// case-insensitive substring match against the vCard properties it
+/// names.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CardQuery {
+    /// Free text, matched against every property value.
+    pub text: Option<String>,
+    /// Matched against FN, N and NICKNAME.
+    pub name: Option<String>,
+    /// Matched against EMAIL.
+    pub email: Option<String>,
+    /// Matched against TEL, digits only when it holds any.
+    pub tel: Option<String>,
+    /// Matched against ORG.
+    pub org: Option<String>,
+}
+
+impl CardQuery {
+    /// Whether no criterion is set.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_none()
+            && self.name.is_none()
+            && self.email.is_none()
+            && self.tel.is_none()
+            && self.org.is_none()
+    }
+
+    /// The most selective free-form term, to narrow a native search
+    /// before the exact matching: the free text, then the name, email
+    /// and organization.
+    pub fn term(&self) -> Option<&str> {
+        self.text
+            .as_deref()
+            .or(self.name.as_deref())
+            .or(self.email.as_deref())
+            .or(self.org.as_deref())
+    }
+}
+
+/// vCard version a card is converted to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+pub enum VcardVersion {
+    /// vCard 3.0 (RFC 2426), the one older servers and phones accept.
+    #[serde(rename = "3.0", alias = "3")]
+    V3,
+    /// vCard 4.0 (RFC 6350).
+    #[serde(rename = "4.0", alias = "4")]
+    V4,
+}
+
+/// Column of the cards table (`card list`, `card search`).
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum CardColumn {
+    Id,
+    Fn,
+    N,
+    Email,
+    Tel,
+    Org,
+    Bday,
+    Categories,
+    Rev,
+    Etag,
+}
+
+/// Sort order of the cards table rows.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[value(rename_all = "kebab-case")]
+pub enum CardSort {
+    /// By formatted name, case-insensitive.
+    Fn,
+    /// By structured name (family name first), case-insensitive.
+    N,
+    /// By revision, newest first.
+    Rev,
+}
@@ -1,11 +1,26 @@
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{Error, Result, anyhow, bail};
+use clap::{Parser, ValueEnum};
 use pimalaya_cli::printer::{Message, Printer};
 
-use crate::shared::{arg::AddressbookIdArg, card::vcard::VcardArg, client::AddressbookClient};
+use crate::{
+    project::{MergeSide, merge_vcards},
+    shared::{
+        arg::AddressbookIdArg,
+        card::vcard::{VcardArg, read_vcard},
+        client::AddressbookClient,
+    },
+};
 
 /// Replace the bytes of an existing vCard.
 ///
+/// When an `--if-match` guarded update is rejected because the card
+/// changed on the server (CardDAV, Google People), passing `--base`
+/// (the vCard the edit started from, the version the ETag refers to)
+/// turns the rejection into a property-level three-way merge between
+/// that base, the new contents and the current server card. Properties
+/// changed on one side only merge cleanly; properties changed on both
+/// sides abort the update unless `--on-conflict` picks a winner.
+///
 /// JSON output: `{"message": "..."}`.
 #[derive(Debug, Parser)]
 pub struct CardUpdateCommand {
@@ -15,6 +30,15 @@ pub struct CardUpdateCommand {
     /// gated on a server-side match (RFC 9110 If-Match).
     #[arg(long, value_name = "ETAG")]
     pub if_match: Option<String>,
+    /// vCard the edit started from (a path, raw vCard contents, or `-`
+    /// for stdin), used as merge base when the `--if-match` guard
+    /// fails.
+    #[arg(long, value_name = "VCARD", requires = "if_match")]
+    pub base: Option<String>,
+    /// Side winning the properties changed on both sides during a
+    /// merge; without it, such conflicts abort the update.
+    #[arg(long, value_name = "SIDE", value_enum, requires = "base")]
+    pub on_conflict: Option<MergeSideArg>,
 
     /// Card UID.
     #[arg(value_name = "CARD-ID")]
@@ -23,21 +47,89 @@ pub struct CardUpdateCommand {
     pub vcard: VcardArg,
 }
 
+/// Merge side selector for `card update --on-conflict`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum MergeSideArg {
+    Ours,
+    Theirs,
+}
+
+impl From<MergeSideArg> for MergeSide {
+    fn from(arg: MergeSideArg) -> Self {
+        match arg {
+            MergeSideArg::Ours => Self::Ours,
+            MergeSideArg::Theirs => Self::Theirs,
+        }
+    }
+}
+
 impl CardUpdateCommand {
     pub fn execute(self, printer: &mut impl Printer, mut client: AddressbookClient) -> Result<()> {
         let addressbook_id = client.account.addressbook_id(self.addressbook.id)?;
         let contents = self.vcard.read()?;
 
-        client.update_card(
+        let result = client.update_card(
             &addressbook_id,
             &self.card_id,
-            contents,
+            contents.clone(),
             self.if_match.as_deref(),
+        );
+
+        let Err(err) = result else {
+            return printer.out(Message::new(format!(
+                "Card `{}` successfully updated",
+                self.card_id
+            )));
+        };
+
+        let (Some(if_match), Some(base)) = (self.if_match, self.base) else {
+            return Err(err);
+        };
+
+        if !client.guards_updates() {
+            return Err(err);
+        }
+
+        // NOTE: the update failed for another reason than a stale
+        // guard when the server card still carries the expected ETag.
+        let theirs = client.get_card(&addressbook_id, &self.card_id)?;
+        let Some(etag) = theirs.etag.filter(|etag| *etag != if_match) else {
+            return Err(err);
+        };
+
+        let base = into_vcard_text(read_vcard(base)?)?;
+        let ours = into_vcard_text(contents)?;
+        let theirs = into_vcard_text(theirs.contents)?;
+        let prefer = self.on_conflict.map(MergeSide::from);
+
+        let merge = merge_vcards(&base, &ours, &theirs, prefer.unwrap_or(MergeSide::Theirs))
+            .map_err(Error::msg)?;
+
+        if prefer.is_none() && !merge.conflicts.is_empty() {
+            bail!(
+                "Card `{}` changed on the server since ETag `{if_match}`, and both sides \
+                 changed {}; pass --on-conflict ours|theirs to settle them",
+                self.card_id,
+                merge.conflicts.join(", ")
+            );
+        }
+
+        client.update_card(
+            &addressbook_id,
+            &self.card_id,
+            merge.vcard.into_bytes(),
+            Some(&etag),
         )?;
 
         printer.out(Message::new(format!(
-            "Card `{}` successfully updated",
+            "Card `{}` successfully updated (merged with the server changes)",
             self.card_id
         )))
     }
 }
+
+/// Decodes raw card bytes as UTF-8 vCard text.
+fn into_vcard_text(contents: Vec<u8>) -> Result<String> {
+    String::from_utf8(contents).map_err(|_| anyhow!("Card contents are not valid UTF-8"))
+}
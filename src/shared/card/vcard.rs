@@ -5,9 +5,11 @@ use std::{
 };
 
 use anyhow::{Context, Result, bail};
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-/// Positional vCard source shared by `card create` and `card update`.
+use crate::shared::card::VcardVersion;
+
+/// Positional vCard source shared by `card update` and `card import`.
 #[derive(Debug, Parser)]
 pub struct VcardArg {
     /// A path to a vCard file, raw vCard contents, or `-` for stdin.
@@ -16,32 +18,52 @@ pub struct VcardArg {
 }
 
 impl VcardArg {
-    /// Resolves the source into raw vCard bytes: `-` reads stdin, an
-    /// existing file is read, otherwise the value is treated as literal
-    /// vCard contents.
+    /// Resolves the source into raw vCard bytes (see [`read_vcard`]).
     pub fn read(self) -> Result<Vec<u8>> {
-        if self.vcard == "-" {
-            let mut buf = Vec::new();
-            stdin()
-                .read_to_end(&mut buf)
-                .context("Read vCard from stdin error")?;
-            return Ok(buf);
-        }
+        read_vcard(self.vcard)
+    }
+}
 
-        let path = PathBuf::from(&self.vcard);
+/// Resolves a vCard source into raw vCard bytes: `-` reads stdin, an
+/// existing file is read, otherwise the value is treated as literal
+/// vCard contents.
+pub fn read_vcard(source: String) -> Result<Vec<u8>> {
+    if source == "-" {
+        let mut buf = Vec::new();
+        stdin()
+            .read_to_end(&mut buf)
+            .context("Read vCard from stdin error")?;
+        return Ok(buf);
+    }
 
-        if path.is_file() {
-            return fs::read(&path)
-                .with_context(|| format!("Read vCard from `{}` error", path.display()));
-        }
+    let path = PathBuf::from(&source);
 
-        if self.vcard.trim_start().starts_with("BEGIN:VCARD") {
-            return Ok(self.vcard.into_bytes());
-        }
+    if path.is_file() {
+        return fs::read(&path)
+            .with_context(|| format!("Read vCard from `{}` error", path.display()));
+    }
 
-        bail!(
-            "Source `{}` is neither a readable file nor vCard contents",
-            self.vcard
-        )
+    if source.trim_start().starts_with("BEGIN:VCARD") {
+        return Ok(source.into_bytes());
+    }
+
+    bail!("Source `{source}` is neither a readable file nor vCard contents")
+}
+
+/// vCard version selector for `--vcard-version`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum VcardVersionArg {
+    #[value(name = "3")]
+    V3,
+    #[value(name = "4")]
+    V4,
+}
+
+impl From<VcardVersionArg> for VcardVersion {
+    fn from(arg: VcardVersionArg) -> Self {
+        match arg {
+            VcardVersionArg::V3 => Self::V3,
+            VcardVersionArg::V4 => Self::V4,
+        }
     }
 }
@@ -5,17 +5,26 @@
 //! Graph, Google People); a value always holds exactly one. Each
 //! shared-API method dispatches to the active backend's matching
 //! method; the per-backend glue lives in each protocol module's
-//! backend submodule.
+//! backend submodule. A client may also go through the on-disk card
+//! cache of [`crate::shared::cache`], or serve it alone when offline.
 
-use anyhow::{Result, bail};
+use std::io;
+
+use anyhow::{Result, anyhow, bail};
+use log::warn;
 
 use crate::{
     account::context::Account,
     backend::Backend,
     config::{AccountConfig, Config},
+    project::{
+        VcardGroup, convert_vcard, group_vcard, set_categories, set_group_member, vcard_categories,
+        vcard_group, vcard_matches, vcard_uid,
+    },
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
+        cache::{CachedAddressbook, CardCache},
+        card::{Card, CardChanges, CardQuery, VcardVersion, create::fresh_uid_rev},
     },
 };
 
@@ -23,7 +32,15 @@ use crate::{
 /// the merged runtime [`Account`].
 pub struct AddressbookClient {
     inner: BackendClient,
+    /// Name of the account the client runs against.
+    pub account_name: String,
     pub account: Account,
+    /// vCard version cards are converted to before being written, from
+    /// the backend's `vcard-version` config.
+    write_version: Option<VcardVersion>,
+    /// On-disk cache the card reads go through, see
+    /// [`Self::new_cached`].
+    cache: Option<CardCache>,
 }
 
 /// The active backend of an [`AddressbookClient`]: exactly one of the
@@ -39,6 +56,8 @@ enum BackendClient {
     Msgraph(Box<crate::msgraph::backend::MsgraphBackend>),
     #[cfg(feature = "google")]
     Google(Box<crate::google::backend::GoogleBackend>),
+    /// Offline client serving the cached cards of a remote backend.
+    Cache(CardCache),
 }
 
 impl AddressbookClient {
@@ -46,68 +65,111 @@ impl AddressbookClient {
     /// configured backend allowed by `backend` wins.
     pub fn new(
         config: Config,
+        account_name: &str,
         #[allow(unused_mut)] mut account_config: AccountConfig,
         backend: Backend,
     ) -> Result<Self> {
-        #[allow(unused_mut)]
-        let mut inner: Option<BackendClient> = None;
-
-        #[cfg(feature = "vdir")]
-        if inner.is_none()
-            && backend.allows_vdir()
-            && let Some(vdir_config) = account_config.vdir.take()
-        {
-            use crate::vdir::backend::VdirBackend;
-            inner = Some(BackendClient::Vdir(VdirBackend::new(vdir_config)));
-        }
-
-        #[cfg(feature = "carddav")]
-        if inner.is_none()
-            && backend.allows_carddav()
-            && let Some(carddav_config) = account_config.carddav.take()
-        {
-            use crate::carddav::backend::CarddavBackend;
-            let client = CarddavBackend::new(carddav_config)?;
-            inner = Some(BackendClient::Carddav(Box::new(client)));
-        }
-
-        #[cfg(feature = "jmap")]
-        if inner.is_none()
-            && backend.allows_jmap()
-            && let Some(jmap_config) = account_config.jmap.take()
-        {
-            use crate::jmap::backend::JmapBackend;
-            let client = JmapBackend::new(jmap_config)?;
-            inner = Some(BackendClient::Jmap(Box::new(client)));
-        }
-
-        #[cfg(feature = "msgraph")]
-        if inner.is_none()
-            && backend.allows_msgraph()
-            && let Some(msgraph_config) = account_config.msgraph.take()
-        {
-            use crate::msgraph::backend::MsgraphBackend;
-            let client = MsgraphBackend::new(msgraph_config)?;
-            inner = Some(BackendClient::Msgraph(Box::new(client)));
-        }
-
-        #[cfg(feature = "google")]
-        if inner.is_none()
-            && backend.allows_google()
-            && let Some(google_config) = account_config.google.take()
-        {
-            use crate::google::backend::GoogleBackend;
-            let client = GoogleBackend::new(google_config)?;
-            inner = Some(BackendClient::Google(Box::new(client)));
-        }
-
-        let Some(inner) = inner else {
-            bail!("No backend matching `{backend}` is configured for this account");
-        };
+        let (mut inner, write_version) = open_backend(account_name, &mut account_config, backend)?;
+        let account = Account::from(config).merge(Account::from(account_config));
+        set_groups(&mut inner, account.cards_groups());
 
+        Ok(Self {
+            inner,
+            account_name: account_name.to_string(),
+            account,
+            write_version,
+            cache: None,
+        })
+    }
+
+    /// Builds the client of the read-only card commands (list, read,
+    /// search), going through the on-disk card cache of remote backends
+    /// when `card.cache` is enabled: reads refresh the cache incrementally
+    /// then serve from it, and fall back to the cached cards when the
+    /// backend cannot be reached. With `offline`, the backend is not even
+    /// opened and only the cached reads are served.
+    pub fn new_cached(
+        config: Config,
+        account_name: &str,
+        #[allow(unused_mut)] mut account_config: AccountConfig,
+        backend: Backend,
+        offline: bool,
+    ) -> Result<Self> {
+        let cached_backend = configured_backend(&account_config, backend).filter(is_remote);
+        let opened = (!offline).then(|| open_backend(account_name, &mut account_config, backend));
         let account = Account::from(config).merge(Account::from(account_config));
 
-        Ok(Self { inner, account })
+        let cache = match cached_backend {
+            Some(backend) if offline || account.cards_cache() => {
+                Some(CardCache::new(account_name, backend)?)
+            }
+            _ => None,
+        };
+
+        let (mut inner, write_version, cache) = match (opened, cache) {
+            (Some(Ok((inner, write_version))), cache) => (inner, write_version, cache),
+            (Some(Err(err)), Some(cache)) if is_transport_error(&err) => {
+                warn!("cannot open the backend, serving cached cards: {err:#}");
+                (BackendClient::Cache(cache), None, None)
+            }
+            (Some(Err(err)), _) => return Err(err),
+            (None, Some(cache)) => (BackendClient::Cache(cache), None, None),
+            (None, None) => bail!("No remote backend matching `{backend}` to read cached cards of"),
+        };
+        set_groups(&mut inner, account.cards_groups());
+
+        Ok(Self {
+            inner,
+            account_name: account_name.to_string(),
+            account,
+            write_version,
+            cache,
+        })
+    }
+
+    /// The backend actually serving this client, never
+    /// [`Backend::Auto`].
+    pub fn backend(&self) -> Backend {
+        match &self.inner {
+            #[cfg(feature = "vdir")]
+            BackendClient::Vdir(_) => Backend::Vdir,
+            #[cfg(feature = "carddav")]
+            BackendClient::Carddav(_) => Backend::Carddav,
+            #[cfg(feature = "jmap")]
+            BackendClient::Jmap(_) => Backend::Jmap,
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(_) => Backend::Msgraph,
+            #[cfg(feature = "google")]
+            BackendClient::Google(_) => Backend::Google,
+            BackendClient::Cache(cache) => cache.backend(),
+        }
+    }
+
+    /// Whether [`Self::update_card`] honours an `if_match` guard
+    /// (CardDAV, Google People) rather than ignoring it (vdir) or
+    /// bailing (JMAP, Microsoft Graph).
+    pub fn guards_updates(&self) -> bool {
+        match &self.inner {
+            #[cfg(feature = "vdir")]
+            BackendClient::Vdir(_) => false,
+            #[cfg(feature = "carddav")]
+            BackendClient::Carddav(_) => true,
+            #[cfg(feature = "jmap")]
+            BackendClient::Jmap(_) => false,
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(_) => false,
+            #[cfg(feature = "google")]
+            BackendClient::Google(_) => true,
+            BackendClient::Cache(_) => false,
+        }
+    }
+
+    /// Whether one card can list under several addressbooks with the
+    /// same id (Google groups, JMAP books). Elsewhere card ids are only
+    /// unique within their addressbook (vdir file names, CardDAV URL
+    /// segments, Graph folders).
+    pub fn shares_cards(&self) -> bool {
+        matches!(self.backend(), Backend::Jmap | Backend::Google)
     }
 
     /// Lists every addressbook available to the active account.
@@ -123,6 +185,7 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.list_addressbooks(),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.list_addressbooks(),
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 
@@ -145,6 +208,7 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.create_addressbook(name, description, color),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.create_addressbook(name, description, color),
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 
@@ -162,6 +226,7 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.update_addressbook(id, patch),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.update_addressbook(id, patch),
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 
@@ -179,17 +244,56 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.delete_addressbook(id),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.delete_addressbook(id),
+            BackendClient::Cache(_) => Err(offline_error()),
+        }
+    }
+
+    /// Whether `id` can be deleted: the system addressbooks (Google
+    /// myContacts, the default Graph Contacts folder) cannot.
+    pub fn deletable_addressbook(&self, id: &str) -> bool {
+        match &self.inner {
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(_) => id != crate::msgraph::backend::CONTACTS_FOLDER,
+            #[cfg(feature = "google")]
+            BackendClient::Google(_) => id != crate::google::backend::MY_CONTACTS_GROUP,
+            _ => true,
         }
     }
 
     /// Lists cards inside `addressbook_id`. `page` is 1-indexed; pass
     /// `None` to default to page 1. `page_size = None` returns the full
-    /// window.
+    /// window. With the card cache, the page is cut out of the
+    /// refreshed cache, in card id order. With [`Self::folds_groups`],
+    /// the page is cut out of every card, group cards folded, in card
+    /// id order as well.
     pub fn list_cards(
         &mut self,
         addressbook_id: &str,
         page: Option<u32>,
         page_size: Option<u32>,
+    ) -> Result<Vec<Card>> {
+        if self.folds_groups() {
+            // NOTE: group cards can sit on any page and list members of
+            // any other, so folding needs every card; sorting keeps the
+            // pages of the paged backends.
+            let mut cards = fold_groups(self.all_cards(addressbook_id)?);
+            cards.sort_by(|a, b| a.id.cmp(&b.id));
+            return Ok(paginate(cards, page, page_size));
+        }
+
+        if let Some(cached) = self.refresh_cache(addressbook_id)? {
+            let cards = cached.into_cards(addressbook_id);
+            return Ok(paginate(cards, page, page_size));
+        }
+
+        self.fetch_cards(addressbook_id, page, page_size)
+    }
+
+    fn fetch_cards(
+        &mut self,
+        addressbook_id: &str,
+        page: Option<u32>,
+        page_size: Option<u32>,
     ) -> Result<Vec<Card>> {
         match &mut self.inner {
             #[cfg(feature = "vdir")]
@@ -202,11 +306,194 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.list_cards(addressbook_id, page, page_size),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.list_cards(addressbook_id, page, page_size),
+            BackendClient::Cache(cache) => cache.list_cards(addressbook_id, page, page_size),
         }
     }
 
-    /// Fetches the card `card_id` from `addressbook_id`.
+    /// Lists the cards of `addressbook_id` matching every criterion of
+    /// `query`. Backends with a native search narrow the candidates
+    /// server-side; the final matching always runs over the vCards, so
+    /// every backend returns the same cards. With the card cache, the
+    /// matching runs over the refreshed cache. With
+    /// [`Self::folds_groups`], it runs over every card, group cards
+    /// folded.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        if self.folds_groups() {
+            let mut cards = fold_groups(self.all_cards(addressbook_id)?);
+            cards.retain(|card| vcard_matches(&card.contents, query));
+            return Ok(cards);
+        }
+
+        if let Some(cached) = self.refresh_cache(addressbook_id)? {
+            let mut cards = cached.into_cards(addressbook_id);
+            cards.retain(|card| vcard_matches(&card.contents, query));
+            return Ok(cards);
+        }
+
+        match &mut self.inner {
+            #[cfg(feature = "vdir")]
+            BackendClient::Vdir(client) => client.search_cards(addressbook_id, query),
+            #[cfg(feature = "carddav")]
+            BackendClient::Carddav(client) => client.search_cards(addressbook_id, query),
+            #[cfg(feature = "jmap")]
+            BackendClient::Jmap(client) => client.search_cards(addressbook_id, query),
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(client) => client.search_cards(addressbook_id, query),
+            #[cfg(feature = "google")]
+            BackendClient::Google(client) => client.search_cards(addressbook_id, query),
+            BackendClient::Cache(cache) => cache.search_cards(addressbook_id, query),
+        }
+    }
+
+    /// Fetches the card `card_id` from `addressbook_id`. With the card
+    /// cache, the fetched card is stored in it, and the cached one is
+    /// served when the backend cannot be reached (see
+    /// [`is_transport_error`]). Group cards are not folded into it:
+    /// that takes every card, and writing the folded names back would
+    /// pin memberships removed elsewhere.
     pub fn get_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
+        self.read_card(addressbook_id, card_id)
+    }
+
+    /// Fetches the card `card_id` like [`Self::get_card`], along with
+    /// the photo kept beside its vCard. Only Microsoft Graph has one:
+    /// its contact photo is embedded as an inline PHOTO, at the cost of
+    /// a second request, which is why plain reads and listings leave it
+    /// out. Elsewhere the vCard is the photo of record.
+    pub fn get_card_with_photo(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
+        match &mut self.inner {
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(client) => client.get_card_with_photo(addressbook_id, card_id),
+            _ => self.read_card(addressbook_id, card_id),
+        }
+    }
+
+    fn read_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
+        let Some(cache) = self.cache.clone() else {
+            return self.fetch_card(addressbook_id, card_id);
+        };
+
+        let mut cached = cache.load(addressbook_id)?;
+
+        match self.fetch_card(addressbook_id, card_id) {
+            Ok(card) => {
+                cached.insert(card.clone());
+                cache.save(addressbook_id, &cached)?;
+                Ok(card)
+            }
+            Err(err) if is_transport_error(&err) => match cached.card(addressbook_id, card_id) {
+                Some(card) => {
+                    warn!("cannot fetch card `{card_id}`, serving the cached one: {err:#}");
+                    Ok(card)
+                }
+                None => Err(err),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Whether the group cards of the backend (CardDAV, vdir) are
+    /// folded into the CATEGORIES of their members, from the
+    /// `card.groups` config: listings and searches then hide them, and
+    /// card writes keep them in step with the changed CATEGORIES.
+    pub fn folds_groups(&self) -> bool {
+        self.account.cards_groups() && matches!(self.backend(), Backend::Vdir | Backend::Carddav)
+    }
+
+    /// Every card of `addressbook_id` as stored, group cards included
+    /// and not folded, through the card cache when enabled.
+    pub fn all_cards(&mut self, addressbook_id: &str) -> Result<Vec<Card>> {
+        match self.refresh_cache(addressbook_id)? {
+            Some(cached) => Ok(cached.into_cards(addressbook_id)),
+            None => self.fetch_cards(addressbook_id, None, None),
+        }
+    }
+
+    /// Brings the group cards of `addressbook_id` in step with a change
+    /// of the CATEGORIES of the card of UID `uid`: a group card named
+    /// after an `added` category lists the card, one named after a
+    /// `removed` category no longer does, and an added category without
+    /// a group card gets a new one. The other group cards are left
+    /// alone, so that memberships made elsewhere survive. Names match
+    /// case insensitively.
+    fn sync_groups(
+        &mut self,
+        addressbook_id: &str,
+        uid: &str,
+        added: &[String],
+        removed: &[String],
+    ) -> Result<()> {
+        if added.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut missing: Vec<&String> = added.iter().collect();
+
+        self.set_memberships(addressbook_id, uid, |group| {
+            missing.retain(|name| !name.eq_ignore_ascii_case(&group.name));
+
+            if has_name(added, &group.name) {
+                Some(true)
+            } else if has_name(removed, &group.name) {
+                Some(false)
+            } else {
+                None
+            }
+        })?;
+
+        for name in missing {
+            let (group_uid, rev) = fresh_uid_rev()?;
+            let vcard = group_vcard(&group_uid, &rev, name, &[uid.to_string()]);
+            self.insert_card(addressbook_id, vcard.into_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the card of UID `uid` to the group cards of
+    /// `addressbook_id` for which `member` says `Some(true)`, and takes
+    /// it out of the ones it says `Some(false)` for.
+    fn set_memberships(
+        &mut self,
+        addressbook_id: &str,
+        uid: &str,
+        mut member: impl FnMut(&VcardGroup) -> Option<bool>,
+    ) -> Result<()> {
+        let guarded = self.guards_updates();
+
+        for (card, group) in group_cards(&self.all_cards(addressbook_id)?) {
+            let Some(member) = member(&group) else {
+                continue;
+            };
+
+            if member != group.contains(uid) {
+                let vcard = String::from_utf8_lossy(&card.contents);
+                let contents = set_group_member(&vcard, uid, member).into_bytes();
+                let if_match = card.etag.as_deref().filter(|_| guarded);
+                self.write_card(addressbook_id, &card.id, contents, if_match)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The UID and CATEGORIES of written card contents, `None` for a
+    /// group card or a card without UID, which leave the group cards
+    /// alone.
+    fn group_sync_of(&self, contents: &[u8]) -> Option<(String, Vec<String>)> {
+        if !self.folds_groups() {
+            return None;
+        }
+
+        let vcard = String::from_utf8_lossy(contents);
+        if vcard_group(&vcard).is_some() {
+            return None;
+        }
+
+        Some((vcard_uid(contents)?, vcard_categories(&vcard)))
+    }
+
+    fn fetch_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
         match &mut self.inner {
             #[cfg(feature = "vdir")]
             BackendClient::Vdir(client) => client.get_card(addressbook_id, card_id),
@@ -218,12 +505,97 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.get_card(addressbook_id, card_id),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.get_card(addressbook_id, card_id),
+            BackendClient::Cache(cache) => cache.get_card(addressbook_id, card_id),
+        }
+    }
+
+    /// Brings the cache of `addressbook_id` up to date with the backend
+    /// and returns it, or `None` without a card cache. A cache that
+    /// cannot be refreshed because the backend is unreachable is served
+    /// as is, unless it is empty.
+    fn refresh_cache(&mut self, addressbook_id: &str) -> Result<Option<CachedAddressbook>> {
+        let Some(cache) = self.cache.clone() else {
+            return Ok(None);
+        };
+
+        let mut cached = cache.load(addressbook_id)?;
+
+        match self.apply_changes(addressbook_id, &mut cached) {
+            Ok(()) => cache.save(addressbook_id, &cached)?,
+            Err(err) if cached.token.is_some() && is_transport_error(&err) => {
+                warn!("cannot refresh the card cache, serving cached cards: {err:#}");
+            }
+            Err(err) => return Err(err),
+        }
+
+        Ok(Some(cached))
+    }
+
+    /// Applies the changes since the cached token: deleted cards are
+    /// dropped, created and updated ones fetched again. Without a
+    /// token, the token is taken first then every card listed, so
+    /// changes made in between are replayed on the next refresh.
+    fn apply_changes(
+        &mut self,
+        addressbook_id: &str,
+        cached: &mut CachedAddressbook,
+    ) -> Result<()> {
+        let Some(token) = cached.token.clone() else {
+            let changes = self.card_changes(addressbook_id, None)?;
+            cached.cards.clear();
+            for card in self.fetch_cards(addressbook_id, None, None)? {
+                cached.insert(card);
+            }
+            cached.token = Some(changes.token);
+            return Ok(());
+        };
+
+        let changes = self.card_changes(addressbook_id, Some(&token))?;
+
+        for id in &changes.deleted {
+            cached.cards.remove(id);
+        }
+
+        for id in changes.created.iter().chain(&changes.updated) {
+            let card = self.fetch_card(addressbook_id, id)?;
+            cached.insert(card);
+        }
+
+        cached.token = Some(changes.token);
+        Ok(())
+    }
+
+    /// Converts card contents to the backend's configured vCard
+    /// version, leaving them untouched when none is configured or when
+    /// they are not UTF-8.
+    fn convert_on_write(&self, contents: Vec<u8>) -> Vec<u8> {
+        let Some(version) = self.write_version else {
+            return contents;
+        };
+
+        match String::from_utf8(contents) {
+            Ok(vcard) => convert_vcard(&vcard, version).into_bytes(),
+            Err(err) => err.into_bytes(),
         }
     }
 
     /// Appends a raw vCard to `addressbook_id`. Returns the identifier
-    /// the backend assigned to the stored card.
+    /// the backend assigned to the stored card. With
+    /// [`Self::folds_groups`], the group cards named after the
+    /// CATEGORIES of the card then list it.
     pub fn create_card(&mut self, addressbook_id: &str, contents: Vec<u8>) -> Result<String> {
+        let sync = self.group_sync_of(&contents);
+        let id = self.insert_card(addressbook_id, contents)?;
+
+        if let Some((uid, categories)) = sync {
+            self.sync_groups(addressbook_id, &uid, &categories, &[])?;
+        }
+
+        Ok(id)
+    }
+
+    fn insert_card(&mut self, addressbook_id: &str, contents: Vec<u8>) -> Result<String> {
+        let contents = self.convert_on_write(contents);
         match &mut self.inner {
             #[cfg(feature = "vdir")]
             BackendClient::Vdir(client) => client.create_card(addressbook_id, contents),
@@ -235,6 +607,7 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.create_card(addressbook_id, contents),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.create_card(addressbook_id, contents),
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 
@@ -242,7 +615,9 @@ impl AddressbookClient {
     ///
     /// `if_match` is the entity tag to gate the update on; pass `None`
     /// to overwrite unconditionally. Backends without a guard concept
-    /// either ignore it (vdir) or bail (JMAP, Microsoft Graph).
+    /// either ignore it (vdir) or bail (JMAP, Microsoft Graph). With
+    /// [`Self::folds_groups`], the group cards are then brought in step
+    /// with the categories added to or removed from the stored card.
     pub fn update_card(
         &mut self,
         addressbook_id: &str,
@@ -250,6 +625,42 @@ impl AddressbookClient {
         contents: Vec<u8>,
         if_match: Option<&str>,
     ) -> Result<()> {
+        let sync = match self.group_sync_of(&contents) {
+            Some((uid, categories)) => {
+                let stored = self.read_card(addressbook_id, card_id)?;
+                let previous = vcard_categories(&String::from_utf8_lossy(&stored.contents));
+                Some((uid, categories, previous))
+            }
+            None => None,
+        };
+
+        self.write_card(addressbook_id, card_id, contents, if_match)?;
+
+        let Some((uid, categories, previous)) = sync else {
+            return Ok(());
+        };
+
+        let added: Vec<String> = categories
+            .iter()
+            .filter(|name| !has_name(&previous, name))
+            .cloned()
+            .collect();
+        let removed: Vec<String> = previous
+            .into_iter()
+            .filter(|name| !has_name(&categories, name))
+            .collect();
+
+        self.sync_groups(addressbook_id, &uid, &added, &removed)
+    }
+
+    fn write_card(
+        &mut self,
+        addressbook_id: &str,
+        card_id: &str,
+        contents: Vec<u8>,
+        if_match: Option<&str>,
+    ) -> Result<()> {
+        let contents = self.convert_on_write(contents);
         match &mut self.inner {
             #[cfg(feature = "vdir")]
             BackendClient::Vdir(client) => {
@@ -271,11 +682,40 @@ impl AddressbookClient {
             BackendClient::Google(client) => {
                 client.update_card(addressbook_id, card_id, contents, if_match)
             }
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 
-    /// Permanently deletes `card_id` from `addressbook_id`.
+    /// Permanently deletes `card_id` from `addressbook_id`. With
+    /// [`Self::folds_groups`], the card is also dropped from the group
+    /// cards listing it.
     pub fn delete_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<()> {
+        let uid = if self.folds_groups() {
+            vcard_uid(&self.read_card(addressbook_id, card_id)?.contents)
+        } else {
+            None
+        };
+
+        self.remove_card(addressbook_id, card_id)?;
+
+        match uid {
+            Some(uid) => self.set_memberships(addressbook_id, &uid, |_| Some(false)),
+            None => Ok(()),
+        }
+    }
+
+    /// Takes `card_id` out of `addressbook_id`. Google user groups only
+    /// drop the membership, the contact living on in myContacts; every
+    /// other addressbook owns its cards, so this is [`Self::delete_card`].
+    pub fn detach_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<()> {
+        match &mut self.inner {
+            #[cfg(feature = "google")]
+            BackendClient::Google(client) => client.detach_card(addressbook_id, card_id),
+            _ => self.delete_card(addressbook_id, card_id),
+        }
+    }
+
+    fn remove_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<()> {
         match &mut self.inner {
             #[cfg(feature = "vdir")]
             BackendClient::Vdir(client) => client.delete_card(addressbook_id, card_id),
@@ -287,28 +727,292 @@ impl AddressbookClient {
             BackendClient::Msgraph(client) => client.delete_card(addressbook_id, card_id),
             #[cfg(feature = "google")]
             BackendClient::Google(client) => client.delete_card(addressbook_id, card_id),
+            BackendClient::Cache(_) => Err(offline_error()),
+        }
+    }
+
+    /// Deletes the photo of `card_id` kept beside its vCard. Only
+    /// Microsoft Graph has one to delete: its updates leave the contact
+    /// photo alone when the vCard carries no PHOTO, reads carrying
+    /// none. Elsewhere the vCard is the photo of record (Google People
+    /// updates already drop the contact photo along), so this does
+    /// nothing.
+    pub fn delete_card_photo(&mut self, addressbook_id: &str, card_id: &str) -> Result<()> {
+        match &mut self.inner {
+            #[cfg(feature = "vdir")]
+            BackendClient::Vdir(_) => Ok(()),
+            #[cfg(feature = "carddav")]
+            BackendClient::Carddav(_) => Ok(()),
+            #[cfg(feature = "jmap")]
+            BackendClient::Jmap(_) => Ok(()),
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(client) => client.delete_card_photo(addressbook_id, card_id),
+            #[cfg(feature = "google")]
+            BackendClient::Google(_) => Ok(()),
+            BackendClient::Cache(_) => Err(offline_error()),
+        }
+    }
+
+    /// Lists the cards of `addressbook_id` changed since the opaque
+    /// token `since`, as returned by a previous call. Pass `None` for
+    /// an initial feed, which reports every card as created.
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        match &mut self.inner {
+            #[cfg(feature = "vdir")]
+            BackendClient::Vdir(client) => client.card_changes(addressbook_id, since),
+            #[cfg(feature = "carddav")]
+            BackendClient::Carddav(client) => client.card_changes(addressbook_id, since),
+            #[cfg(feature = "jmap")]
+            BackendClient::Jmap(client) => client.card_changes(addressbook_id, since),
+            #[cfg(feature = "msgraph")]
+            BackendClient::Msgraph(client) => client.card_changes(addressbook_id, since),
+            #[cfg(feature = "google")]
+            BackendClient::Google(client) => client.card_changes(addressbook_id, since),
+            BackendClient::Cache(_) => Err(offline_error()),
         }
     }
 }
 
+/// Opens the first configured backend allowed by `backend`, taking its
+/// config block out of `account_config`. Returns it with the vCard
+/// version cards are converted to before being written.
+fn open_backend(
+    #[allow(unused)] account_name: &str,
+    #[allow(unused)] account_config: &mut AccountConfig,
+    backend: Backend,
+) -> Result<(BackendClient, Option<VcardVersion>)> {
+    #[cfg(feature = "vdir")]
+    if backend.allows_vdir()
+        && let Some(vdir_config) = account_config.vdir.take()
+    {
+        use crate::vdir::backend::VdirBackend;
+        let write_version = vdir_config.vcard_version;
+        let client = VdirBackend::new(vdir_config);
+        return Ok((BackendClient::Vdir(client), write_version));
+    }
+
+    #[cfg(feature = "carddav")]
+    if backend.allows_carddav()
+        && let Some(carddav_config) = account_config.carddav.take()
+    {
+        use crate::carddav::backend::CarddavBackend;
+        let write_version = carddav_config.vcard_version;
+        let client = CarddavBackend::new(carddav_config, account_name)?;
+        return Ok((BackendClient::Carddav(Box::new(client)), write_version));
+    }
+
+    #[cfg(feature = "jmap")]
+    if backend.allows_jmap()
+        && let Some(jmap_config) = account_config.jmap.take()
+    {
+        use crate::jmap::backend::JmapBackend;
+        let client = JmapBackend::new(jmap_config, account_name)?;
+        return Ok((BackendClient::Jmap(Box::new(client)), None));
+    }
+
+    #[cfg(feature = "msgraph")]
+    if backend.allows_msgraph()
+        && let Some(msgraph_config) = account_config.msgraph.take()
+    {
+        use crate::msgraph::backend::MsgraphBackend;
+        let client = MsgraphBackend::new(msgraph_config)?;
+        return Ok((BackendClient::Msgraph(Box::new(client)), None));
+    }
+
+    #[cfg(feature = "google")]
+    if backend.allows_google()
+        && let Some(google_config) = account_config.google.take()
+    {
+        use crate::google::backend::GoogleBackend;
+        let client = GoogleBackend::new(google_config)?;
+        return Ok((BackendClient::Google(Box::new(client)), None));
+    }
+
+    bail!("No backend matching `{backend}` is configured for this account")
+}
+
+/// The backend [`open_backend`] would pick, without opening it.
+#[allow(unused)]
+fn configured_backend(account_config: &AccountConfig, backend: Backend) -> Option<Backend> {
+    #[cfg(feature = "vdir")]
+    if backend.allows_vdir() && account_config.vdir.is_some() {
+        return Some(Backend::Vdir);
+    }
+
+    #[cfg(feature = "carddav")]
+    if backend.allows_carddav() && account_config.carddav.is_some() {
+        return Some(Backend::Carddav);
+    }
+
+    #[cfg(feature = "jmap")]
+    if backend.allows_jmap() && account_config.jmap.is_some() {
+        return Some(Backend::Jmap);
+    }
+
+    #[cfg(feature = "msgraph")]
+    if backend.allows_msgraph() && account_config.msgraph.is_some() {
+        return Some(Backend::Msgraph);
+    }
+
+    #[cfg(feature = "google")]
+    if backend.allows_google() && account_config.google.is_some() {
+        return Some(Backend::Google);
+    }
+
+    None
+}
+
+/// Whether the backend is reached over the network, hence worth
+/// caching.
+#[allow(unused)]
+fn is_remote(backend: &Backend) -> bool {
+    #[cfg(feature = "vdir")]
+    if *backend == Backend::Vdir {
+        return false;
+    }
+
+    true
+}
+
+/// Hands the `card.groups` config to the backends mapping their own
+/// contact groups (Google People); the group cards of CardDAV and vdir
+/// are folded by the client itself, see
+/// [`AddressbookClient::folds_groups`].
+#[allow(unused_variables)]
+fn set_groups(inner: &mut BackendClient, groups: bool) {
+    #[cfg(feature = "google")]
+    if let BackendClient::Google(client) = inner {
+        client.groups = groups;
+    }
+}
+
+/// Whether `name` is among `names`, case insensitively.
+fn has_name(names: &[String], name: &str) -> bool {
+    names.iter().any(|other| other.eq_ignore_ascii_case(name))
+}
+
+/// The group cards among `cards`, with the group each stands for.
+fn group_cards(cards: &[Card]) -> Vec<(&Card, VcardGroup)> {
+    cards
+        .iter()
+        .filter_map(|card| Some((card, vcard_group(&String::from_utf8_lossy(&card.contents))?)))
+        .collect()
+}
+
+/// Drops the group cards of `cards` and adds their names to the
+/// CATEGORIES of their members.
+fn fold_groups(cards: Vec<Card>) -> Vec<Card> {
+    let groups: Vec<VcardGroup> = group_cards(&cards)
+        .into_iter()
+        .map(|(_, group)| group)
+        .collect();
+
+    cards
+        .into_iter()
+        .filter(|card| vcard_group(&String::from_utf8_lossy(&card.contents)).is_none())
+        .map(|card| with_groups(card, &groups))
+        .collect()
+}
+
+/// Adds to the CATEGORIES of `card` the names of the groups listing
+/// it, leaving the card untouched when they already are.
+fn with_groups(mut card: Card, groups: &[VcardGroup]) -> Card {
+    let Some(uid) = vcard_uid(&card.contents) else {
+        return card;
+    };
+
+    let vcard = String::from_utf8_lossy(&card.contents).into_owned();
+    let mut categories = vcard_categories(&vcard);
+    let known = categories.len();
+
+    for group in groups.iter().filter(|group| group.contains(&uid)) {
+        if !has_name(&categories, &group.name) && !group.name.is_empty() {
+            categories.push(group.name.clone());
+        }
+    }
+
+    if categories.len() > known {
+        card.contents = set_categories(&vcard, &categories).into_bytes();
+    }
+
+    card
+}
+
+/// Whether `err` comes from the transport (connection refused, DNS,
+/// TLS, timeout), the only failures the card cache stands in for.
+/// Authentication and server errors surface as they are, so that stale
+/// cards never hide them.
+fn is_transport_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| cause.is::<io::Error>())
+}
+
+/// Error of the operations an offline client cannot serve.
+fn offline_error() -> anyhow::Error {
+    anyhow!("Offline: only card list, read and search are served from the card cache")
+}
+
 /// 1-indexed pagination on an in-memory list. `page_size = None`
 /// returns the full slice; `page_size = 0` or a page past the end
 /// returns an empty vector.
 pub fn paginate<T>(items: Vec<T>, page: Option<u32>, page_size: Option<u32>) -> Vec<T> {
-    let Some(size) = page_size else {
-        return items;
-    };
+    PageWindow::new(page, page_size).apply(items)
+}
+
+/// Window of a 1-indexed page over a stream of items, letting the
+/// backends with native paging stop fetching once the requested page
+/// is covered instead of materializing the whole addressbook.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PageWindow {
+    /// Number of items before the page.
+    pub skip: usize,
+    /// Number of items of the page, `None` for every remaining one.
+    pub take: Option<usize>,
+}
+
+impl PageWindow {
+    /// Window of the given page. `page_size = None` spans the whole
+    /// stream, whatever the page.
+    pub fn new(page: Option<u32>, page_size: Option<u32>) -> Self {
+        let Some(size) = page_size else {
+            return Self::default();
+        };
+
+        let page = page.unwrap_or(1).max(1);
 
-    if size == 0 {
-        return Vec::new();
+        Self {
+            skip: ((page - 1) as usize).saturating_mul(size as usize),
+            take: Some(size as usize),
+        }
     }
 
-    let page = page.unwrap_or(1).max(1);
-    let skip = ((page - 1) as usize).saturating_mul(size as usize);
+    /// Whether the first `fetched` items of the stream cover the
+    /// window.
+    pub fn is_covered(&self, fetched: usize) -> bool {
+        self.take
+            .is_some_and(|take| fetched >= self.skip.saturating_add(take))
+    }
 
-    if skip >= items.len() {
-        return Vec::new();
+    /// Native page size to request once `fetched` items were received:
+    /// the items still missing to cover the window, capped to `max`
+    /// (the backend's largest page).
+    pub fn batch(&self, fetched: usize, max: u32) -> u32 {
+        match self.take {
+            Some(take) => {
+                let missing = self.skip.saturating_add(take).saturating_sub(fetched);
+                missing.clamp(1, max as usize) as u32
+            }
+            None => max,
+        }
     }
 
-    items.into_iter().skip(skip).take(size as usize).collect()
+    /// Cuts the window out of the items fetched from the start of the
+    /// stream.
+    pub fn apply<T>(&self, items: Vec<T>) -> Vec<T> {
+        let take = self.take.unwrap_or(usize::MAX);
+        items.into_iter().skip(self.skip).take(take).collect()
+    }
 }
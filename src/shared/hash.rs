@@ -0,0 +1,28 @@
+//! Stable hashing of persisted keys and versions.
+//!
+//! State file names (sync, copy, card cache, discovery, vdir change
+//! snapshots) and the content versions of vdir cards outlive the
+//! binary, so they cannot come from [`std::hash::DefaultHasher`], whose
+//! output may change with any Rust release. FNV-1a (64 bits) over the
+//! raw bytes is fixed forever.
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a hash of `parts` as 16 lowercase hex digits. Parts are
+/// length-prefixed, so `["ab", "c"]` and `["a", "bc"]` differ.
+pub fn stable_hash<T: AsRef<[u8]>>(parts: &[T]) -> String {
+    let mut hash = FNV_OFFSET_BASIS;
+
+    for part in parts {
+        let part = part.as_ref();
+        let len = (part.len() as u64).to_le_bytes();
+
+        for byte in len.iter().chain(part) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    format!("{hash:016x}")
+}
@@ -1,4 +1,7 @@
 pub mod addressbook;
 pub mod arg;
+pub mod cache;
 pub mod card;
 pub mod client;
+pub mod hash;
+pub mod sync;
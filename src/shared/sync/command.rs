@@ -0,0 +1,518 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+};
+
+use anyhow::{Result, bail};
+use clap::{Parser, ValueEnum};
+use log::{debug, warn};
+use pimalaya_cli::{clap::parsers::path_parser, printer::Printer};
+use serde::Serialize;
+
+use crate::{
+    backend::Backend,
+    cli::resolve_account,
+    project::{same_vcard, vcard_uid},
+    shared::{
+        arg::AddressbookIdArg,
+        card::Card,
+        client::AddressbookClient,
+        sync::state::{
+            SyncPair, SyncSide, SyncState, default_state_path, load_state, save_state, version,
+        },
+    },
+};
+
+/// Synchronize two addressbooks in both directions.
+///
+/// The left side is the addressbook of the active account (global
+/// `--account`, `--backend` and `-k/--addressbook`). The right side is
+/// the addressbook `--to-addressbook` of `--to-account` served by
+/// `--to-backend`; the account defaults to the left one, the
+/// addressbook to the right account's `addressbook.default`, then to
+/// the left addressbook id. Syncing two backends of one account thus
+/// reads `cardamum -b vdir sync --to-backend carddav`.
+///
+/// A state file pairs the card ids of both sides with the version each
+/// had at the last run (the ETag, or a content hash for vdir), so
+/// creates, updates and deletes made on either side since then are
+/// propagated to the other. On the first run, cards sharing a vCard UID
+/// are paired instead of duplicated, right away when they differ only
+/// by property order, folding, PRODID or REV. A card changed on both
+/// sides is a conflict, settled by `--conflict`; a card changed on one
+/// side and deleted on the other is restored from the changed side.
+///
+/// JSON output: `{"dry-run", "left": {"account", "backend",
+/// "addressbook", "created", "updated", "deleted"}, "right": {...},
+/// "conflicts": [{"left", "right"}]}`.
+#[derive(Debug, Parser)]
+pub struct SyncCommand {
+    #[command(flatten)]
+    pub addressbook: AddressbookIdArg,
+    /// Account of the right side. Defaults to the active account.
+    #[arg(long, value_name = "NAME")]
+    pub to_account: Option<String>,
+    /// Backend of the right side, with the same values as the global
+    /// `--backend` flag.
+    #[arg(long, value_name = "BACKEND", default_value_t)]
+    pub to_backend: Backend,
+    /// Addressbook of the right side.
+    #[arg(long, value_name = "ADDRESSBOOK-ID")]
+    pub to_addressbook: Option<String>,
+    /// How to settle cards changed on both sides since the last run:
+    /// keep the left version, keep the right version, or skip them
+    /// (reported, retried on the next run).
+    #[arg(long, value_name = "SIDE", value_enum, default_value_t = SyncConflictArg::Skip)]
+    pub conflict: SyncConflictArg,
+    /// State file path. Defaults to a file derived from both sides
+    /// under `$XDG_DATA_HOME/cardamum/sync/`.
+    #[arg(long, value_name = "PATH", value_parser = path_parser)]
+    pub state: Option<PathBuf>,
+    /// Compute and report the changes without applying them nor
+    /// touching the state file.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Conflict policy of `sync --conflict`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "kebab-case")]
+pub enum SyncConflictArg {
+    Left,
+    Right,
+    Skip,
+}
+
+impl SyncCommand {
+    pub fn execute(
+        self,
+        printer: &mut impl Printer,
+        config_paths: &[PathBuf],
+        account_name: Option<&str>,
+        backend: Backend,
+    ) -> Result<()> {
+        let (config, left_name, account_config) =
+            resolve_account(printer, config_paths, account_name)?;
+        let left_client = AddressbookClient::new(config, &left_name, account_config, backend)?;
+        let left_id = left_client.account.addressbook_id(self.addressbook.id)?;
+
+        let to_account = self.to_account.as_deref().unwrap_or(&left_name);
+        let (config, right_name, account_config) =
+            resolve_account(printer, config_paths, Some(to_account))?;
+        let right_client =
+            AddressbookClient::new(config, &right_name, account_config, self.to_backend)?;
+        let right_id = match self.to_addressbook {
+            Some(id) => id,
+            None => right_client
+                .account
+                .addressbook_default
+                .clone()
+                .unwrap_or_else(|| left_id.clone()),
+        };
+
+        let left_backend = left_client.backend().to_string();
+        let right_backend = right_client.backend().to_string();
+
+        if left_name == right_name && left_backend == right_backend && left_id == right_id {
+            bail!("Cannot sync addressbook `{left_id}` with itself");
+        }
+
+        let state_path = match self.state {
+            Some(path) => path,
+            None => default_state_path(
+                "sync",
+                &[
+                    &left_name,
+                    &left_backend,
+                    &left_id,
+                    &right_name,
+                    &right_backend,
+                    &right_id,
+                ],
+            )?,
+        };
+        debug!("sync state: {}", state_path.display());
+
+        let state: SyncState = load_state(&state_path)?;
+
+        let mut sync = SyncRun {
+            left: Endpoint {
+                client: left_client,
+                report: SideReport::new(left_name, left_backend, left_id.clone()),
+                addressbook_id: left_id,
+            },
+            right: Endpoint {
+                client: right_client,
+                report: SideReport::new(right_name, right_backend, right_id.clone()),
+                addressbook_id: right_id,
+            },
+            conflict: self.conflict,
+            dry_run: self.dry_run,
+            conflicts: Vec::new(),
+        };
+
+        let next = sync.run(state)?;
+
+        if !self.dry_run {
+            save_state(&next, &state_path)?;
+        }
+
+        printer.out(SyncReport {
+            dry_run: self.dry_run,
+            left: sync.left.report,
+            right: sync.right.report,
+            conflicts: sync.conflicts,
+        })
+    }
+}
+
+/// One side of a running sync.
+struct Endpoint {
+    client: AddressbookClient,
+    addressbook_id: String,
+    report: SideReport,
+}
+
+impl Endpoint {
+    /// Fetches every card of the addressbook, keyed by id.
+    fn list(&mut self) -> Result<HashMap<String, Card>> {
+        let mut cards = self.client.list_cards(&self.addressbook_id, None, None)?;
+
+        // NOTE: folded group CATEGORIES must not change the content
+        // hash of a card (vdir), or a group membership change would
+        // read as a card edit: the hash of the stored card stands in
+        // for the missing ETag instead.
+        if self.client.folds_groups() {
+            let stored: HashMap<String, String> = self
+                .client
+                .all_cards(&self.addressbook_id)?
+                .iter()
+                .map(|card| (card.id.clone(), version(card)))
+                .collect();
+
+            for card in cards.iter_mut().filter(|card| card.etag.is_none()) {
+                card.etag = stored.get(&card.id).cloned();
+            }
+        }
+
+        Ok(cards
+            .into_iter()
+            .map(|card| (card.id.clone(), card))
+            .collect())
+    }
+
+    /// Stores `contents` as a new card, returning its side of the pair.
+    fn create(&mut self, contents: Vec<u8>, dry_run: bool) -> Result<SyncSide> {
+        if dry_run {
+            self.report.created.push(String::new());
+            return Ok(SyncSide::default());
+        }
+
+        let id = self.client.create_card(&self.addressbook_id, contents)?;
+        self.report.created.push(id.clone());
+        self.refresh(id)
+    }
+
+    /// Replaces the card `card` with `contents`, guarded by its ETag
+    /// when the backend supports it.
+    fn update(&mut self, card: &Card, contents: Vec<u8>, dry_run: bool) -> Result<SyncSide> {
+        self.report.updated.push(card.id.clone());
+
+        if dry_run {
+            return Ok(SyncSide {
+                id: card.id.clone(),
+                version: version(card),
+            });
+        }
+
+        let guarded = self.client.guards_updates();
+        let if_match = card.etag.as_deref().filter(|_| guarded);
+
+        self.client
+            .update_card(&self.addressbook_id, &card.id, contents, if_match)?;
+        self.refresh(card.id.clone())
+    }
+
+    /// Deletes the card `id`.
+    fn delete(&mut self, id: &str, dry_run: bool) -> Result<()> {
+        self.report.deleted.push(id.to_string());
+
+        if !dry_run {
+            self.client.delete_card(&self.addressbook_id, id)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads back a freshly written card: backends assign the version
+    /// (and projection backends may normalize the contents) on write.
+    fn refresh(&mut self, id: String) -> Result<SyncSide> {
+        let card = self.client.get_card(&self.addressbook_id, &id)?;
+        Ok(SyncSide {
+            id,
+            version: version(&card),
+        })
+    }
+}
+
+/// A running sync between two endpoints.
+struct SyncRun {
+    left: Endpoint,
+    right: Endpoint,
+    conflict: SyncConflictArg,
+    dry_run: bool,
+    conflicts: Vec<ConflictReport>,
+}
+
+impl SyncRun {
+    /// Reconciles both sides against the previous `state`, returning
+    /// the state to persist.
+    fn run(&mut self, state: SyncState) -> Result<SyncState> {
+        let dry_run = self.dry_run;
+        let mut left_cards = self.left.list()?;
+        let mut right_cards = self.right.list()?;
+        let mut next = SyncState::default();
+
+        for pair in state.cards {
+            let left = left_cards.remove(&pair.left.id);
+            let right = right_cards.remove(&pair.right.id);
+
+            match (left, right) {
+                // NOTE: gone on both sides, the pair is dropped.
+                (None, None) => (),
+                (Some(left), None) => {
+                    if version(&left) == pair.left.version {
+                        self.left.delete(&left.id, dry_run)?;
+                    } else {
+                        // NOTE: a change beats a deletion, so the card
+                        // comes back on the side that deleted it.
+                        let right = self.right.create(left.contents.clone(), dry_run)?;
+                        next.cards.push(SyncPair {
+                            left: side(&left),
+                            right,
+                        });
+                    }
+                }
+                (None, Some(right)) => {
+                    if version(&right) == pair.right.version {
+                        self.right.delete(&right.id, dry_run)?;
+                    } else {
+                        let left = self.left.create(right.contents.clone(), dry_run)?;
+                        next.cards.push(SyncPair {
+                            left,
+                            right: side(&right),
+                        });
+                    }
+                }
+                (Some(left), Some(right)) => {
+                    let left_changed = version(&left) != pair.left.version;
+                    let right_changed = version(&right) != pair.right.version;
+
+                    let pair = match (left_changed, right_changed) {
+                        (false, false) => pair,
+                        (true, false) => self.push_left(&left, &right)?,
+                        (false, true) => self.push_right(&left, &right)?,
+                        (true, true) => self.settle(&left, &right, pair)?,
+                    };
+
+                    next.cards.push(pair);
+                }
+            }
+        }
+
+        // NOTE: cards unknown to the state are new on their side,
+        // unless the other side holds a card with the same UID (first
+        // run against pre-filled addressbooks, or a run interrupted
+        // before its state was saved).
+        let mut right_by_uid: HashMap<String, String> = right_cards
+            .values()
+            .filter_map(|card| Some((vcard_uid(&card.contents)?, card.id.clone())))
+            .collect();
+        let mut paired_right = HashSet::new();
+
+        let mut left_new: Vec<Card> = left_cards.into_values().collect();
+        left_new.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for left in left_new {
+            let twin = vcard_uid(&left.contents)
+                .and_then(|uid| right_by_uid.remove(&uid))
+                .and_then(|id| right_cards.get(&id));
+
+            // NOTE: twins differing only by the normalization of a
+            // backend (property order, folding, PRODID, REV) agree.
+            let pair = match twin {
+                Some(right)
+                    if same_vcard(
+                        &String::from_utf8_lossy(&left.contents),
+                        &String::from_utf8_lossy(&right.contents),
+                    ) =>
+                {
+                    SyncPair {
+                        left: side(&left),
+                        right: side(right),
+                    }
+                }
+                Some(right) => {
+                    let pair = SyncPair {
+                        left: SyncSide {
+                            id: left.id.clone(),
+                            version: String::new(),
+                        },
+                        right: SyncSide {
+                            id: right.id.clone(),
+                            version: String::new(),
+                        },
+                    };
+                    self.settle(&left, right, pair)?
+                }
+                None => SyncPair {
+                    left: side(&left),
+                    right: self.right.create(left.contents.clone(), dry_run)?,
+                },
+            };
+
+            if let Some(right) = twin {
+                paired_right.insert(right.id.clone());
+            }
+
+            next.cards.push(pair);
+        }
+
+        let mut right_new: Vec<Card> = right_cards
+            .into_values()
+            .filter(|card| !paired_right.contains(&card.id))
+            .collect();
+        right_new.sort_by(|a, b| a.id.cmp(&b.id));
+
+        for right in right_new {
+            next.cards.push(SyncPair {
+                left: self.left.create(right.contents.clone(), dry_run)?,
+                right: side(&right),
+            });
+        }
+
+        Ok(next)
+    }
+
+    /// Copies the left card over the right one.
+    fn push_left(&mut self, left: &Card, right: &Card) -> Result<SyncPair> {
+        Ok(SyncPair {
+            left: side(left),
+            right: self
+                .right
+                .update(right, left.contents.clone(), self.dry_run)?,
+        })
+    }
+
+    /// Copies the right card over the left one.
+    fn push_right(&mut self, left: &Card, right: &Card) -> Result<SyncPair> {
+        Ok(SyncPair {
+            left: self
+                .left
+                .update(left, right.contents.clone(), self.dry_run)?,
+            right: side(right),
+        })
+    }
+
+    /// Settles a card changed on both sides according to the conflict
+    /// policy. A skipped conflict keeps `pair` as is, so it surfaces
+    /// again on the next run.
+    fn settle(&mut self, left: &Card, right: &Card, pair: SyncPair) -> Result<SyncPair> {
+        match self.conflict {
+            SyncConflictArg::Left => self.push_left(left, right),
+            SyncConflictArg::Right => self.push_right(left, right),
+            SyncConflictArg::Skip => {
+                warn!(
+                    "skipping conflicting cards `{}` and `{}`",
+                    left.id, right.id
+                );
+                self.conflicts.push(ConflictReport {
+                    left: left.id.clone(),
+                    right: right.id.clone(),
+                });
+                Ok(pair)
+            }
+        }
+    }
+}
+
+/// Current side of a card, as stored in the state.
+fn side(card: &Card) -> SyncSide {
+    SyncSide {
+        id: card.id.clone(),
+        version: version(card),
+    }
+}
+
+/// Outcome of a sync run.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SyncReport {
+    pub dry_run: bool,
+    pub left: SideReport,
+    pub right: SideReport,
+    pub conflicts: Vec<ConflictReport>,
+}
+
+/// Changes applied to one side of a sync. Ids of cards created during
+/// a dry run are unknown, hence empty.
+#[derive(Clone, Debug, Serialize)]
+pub struct SideReport {
+    pub account: String,
+    pub backend: String,
+    pub addressbook: String,
+    pub created: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+}
+
+/// A card pair left untouched because both sides changed.
+#[derive(Clone, Debug, Serialize)]
+pub struct ConflictReport {
+    pub left: String,
+    pub right: String,
+}
+
+impl SideReport {
+    fn new(account: String, backend: String, addressbook: String) -> Self {
+        Self {
+            account,
+            backend,
+            addressbook,
+            created: Vec::new(),
+            updated: Vec::new(),
+            deleted: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Display for SyncReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.dry_run {
+            writeln!(f, "Dry run, nothing applied")?;
+        }
+
+        for (label, side) in [("Left", &self.left), ("Right", &self.right)] {
+            writeln!(
+                f,
+                "{label} ({}, {}, {}): {} created, {} updated, {} deleted",
+                side.account,
+                side.backend,
+                side.addressbook,
+                side.created.len(),
+                side.updated.len(),
+                side.deleted.len(),
+            )?;
+        }
+
+        if !self.conflicts.is_empty() {
+            writeln!(f, "Conflicts skipped: {}", self.conflicts.len())?;
+            for conflict in &self.conflicts {
+                writeln!(f, "  {} <-> {}", conflict.left, conflict.right)?;
+            }
+        }
+
+        Ok(())
+    }
+}
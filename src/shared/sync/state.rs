@@ -0,0 +1,94 @@
+//! Persistent state of a sync pair: which left card maps onto which
+//! right card, and the version each side had when they last agreed.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::shared::{card::Card, hash::stable_hash};
+
+/// Every card pair known from the previous run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SyncState {
+    #[serde(default)]
+    pub cards: Vec<SyncPair>,
+}
+
+/// One card mirrored on both sides.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SyncPair {
+    pub left: SyncSide,
+    pub right: SyncSide,
+}
+
+/// One side of a [`SyncPair`]: the card id and its version at the last
+/// successful sync. An empty version never matches, so the card counts
+/// as changed on the next run.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SyncSide {
+    pub id: String,
+    pub version: String,
+}
+
+/// Reads the state file at `path`; a missing file is the default
+/// (empty) state of a first run.
+pub fn load_state<T: DeserializeOwned + Default>(path: &Path) -> Result<T> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .with_context(|| format!("Invalid state file `{}`", path.display())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => {
+            Err(err).with_context(|| format!("Cannot read state file `{}`", path.display()))
+        }
+    }
+}
+
+/// Writes `state` at `path`, creating its parent directories. The file
+/// is written aside then renamed, so an interrupted run never leaves a
+/// truncated state behind.
+pub fn save_state<T: Serialize>(state: &T, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Cannot create state dir `{}`", dir.display()))?;
+    }
+
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_vec_pretty(state)?)
+        .with_context(|| format!("Cannot write state file `{}`", tmp.display()))?;
+    fs::rename(&tmp, path)
+        .with_context(|| format!("Cannot write state file `{}`", path.display()))?;
+
+    Ok(())
+}
+
+/// Default state file of a `kind` command (`sync`, `copy`) under the
+/// user data dir. The file name hashes `sides` (account, backend and
+/// so on), so each pair gets its own state and swapping sides starts
+/// afresh.
+pub fn default_state_path(kind: &str, sides: &[&str]) -> Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| {
+        anyhow!("Cannot locate the user data dir; pass the state file with --state")
+    })?;
+
+    let name = format!("{}.json", stable_hash(sides));
+
+    Ok(dir.join("cardamum").join(kind).join(name))
+}
+
+/// Version of a card as seen by the sync: its entity tag when the
+/// backend exposes one, otherwise a hash of its contents (vdir).
+pub fn version(card: &Card) -> String {
+    if let Some(etag) = &card.etag {
+        return etag.clone();
+    }
+
+    stable_hash(&[&card.contents])
+}
@@ -2,18 +2,33 @@
 //! addressbook and card operations onto [`io_vdir::client::VdirClient`]
 //! calls against the configured home directory.
 
-use anyhow::{Result, bail};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, anyhow, bail};
 use io_vdir::{client::VdirClient, collection::VdirCollection, item::VdirItemKind, path::VdirPath};
 
 use crate::{
     config::VdirConfig,
+    project::vcard_matches,
     shared::{
         addressbook::{Addressbook, AddressbookDiff},
-        card::Card,
+        card::{Card, CardChanges, CardQuery},
         client::paginate,
+        hash::stable_hash,
     },
 };
 
+/// Number of change snapshots kept per collection, whoever asked for
+/// them: a token is rejected once 16 newer `card changes` calls ran
+/// against the collection.
+const MAX_CHANGE_SNAPSHOTS: usize = 16;
+
 /// Vdir backend of the shared-API client, wrapping the io-vdir
 /// filesystem client rooted at the account's home directory.
 pub struct VdirBackend {
@@ -120,6 +135,14 @@ impl VdirBackend {
         Ok(paginate(cards, page, page_size))
     }
 
+    /// Lists the vCard items inside `addressbook_id` matching `query`.
+    /// vdir has no index, so every item is parsed and matched.
+    pub fn search_cards(&mut self, addressbook_id: &str, query: &CardQuery) -> Result<Vec<Card>> {
+        let mut cards = self.list_cards(addressbook_id, None, None)?;
+        cards.retain(|card| vcard_matches(&card.contents, query));
+        Ok(cards)
+    }
+
     /// Fetches `card_id` from `addressbook_id`.
     pub fn get_card(&mut self, addressbook_id: &str, card_id: &str) -> Result<Card> {
         let path = self.addressbook_path(addressbook_id)?;
@@ -169,6 +192,56 @@ impl VdirBackend {
         Ok(())
     }
 
+    /// Lists the cards changed since the token `since`. vdir keeps no
+    /// change log, so each call snapshots the item modification times
+    /// under the user data dir, keyed by the returned token, and diffs
+    /// the collection against the snapshot of `since`: new items are
+    /// created, items with another mtime updated, missing ones
+    /// deleted.
+    pub fn card_changes(
+        &mut self,
+        addressbook_id: &str,
+        since: Option<&str>,
+    ) -> Result<CardChanges> {
+        let path = self.addressbook_path(addressbook_id)?;
+        let dir = snapshots_dir(path.as_str())?;
+
+        let mut previous = match since {
+            Some(token) => read_snapshot(&dir, token)?,
+            None => BTreeMap::new(),
+        };
+
+        let mut snapshot = BTreeMap::new();
+        for item in self.inner.list_items(path)? {
+            if item.kind != VdirItemKind::Vcard {
+                continue;
+            }
+            let Some(id) = item.id() else {
+                continue;
+            };
+
+            let mtime = fs::metadata(item.path.as_str())
+                .and_then(|meta| meta.modified())
+                .map(millis)
+                .with_context(|| format!("Cannot read mtime of `{}`", item.path.as_str()))?;
+
+            snapshot.insert(id.to_string(), mtime);
+        }
+
+        let mut changes = CardChanges::default();
+        for (id, mtime) in &snapshot {
+            match previous.remove(id) {
+                None => changes.created.push(id.clone()),
+                Some(prev) if prev != *mtime => changes.updated.push(id.clone()),
+                Some(_) => (),
+            }
+        }
+        changes.deleted = previous.into_keys().collect();
+        changes.token = write_snapshot(&dir, &snapshot)?;
+
+        Ok(changes)
+    }
+
     /// Resolves `addressbook_id` against the home directory, rejecting
     /// an empty id.
     fn addressbook_path(&self, addressbook_id: &str) -> Result<VdirPath> {
@@ -192,3 +265,76 @@ fn into_addressbook(collection: VdirCollection) -> Addressbook {
         color: collection.color,
     }
 }
+
+/// Directory of the change snapshots of the collection at `path`,
+/// under the user data dir.
+fn snapshots_dir(path: &str) -> Result<PathBuf> {
+    let dir = dirs::data_dir().ok_or_else(|| anyhow!("Cannot locate the user data dir"))?;
+
+    let key = stable_hash(&[path]);
+
+    Ok(dir.join("cardamum").join("vdir-changes").join(key))
+}
+
+/// Reads the snapshot of the change token `token`.
+fn read_snapshot(dir: &Path, token: &str) -> Result<BTreeMap<String, u64>> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("Invalid vdir change token `{token}`");
+    }
+
+    match fs::read(dir.join(format!("{token}.json"))) {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            bail!(
+                "Unknown or expired vdir change token `{token}` (only the last \
+                 {MAX_CHANGE_SNAPSHOTS} tokens of the addressbook are kept); restart without one"
+            )
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Stores `snapshot` under a fresh token (the current time in
+/// milliseconds), prunes the oldest snapshots, and returns the token.
+fn write_snapshot(dir: &Path, snapshot: &BTreeMap<String, u64>) -> Result<String> {
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Cannot create vdir changes dir `{}`", dir.display()))?;
+
+    let mut token = millis(SystemTime::now());
+    while dir.join(format!("{token}.json")).exists() {
+        token += 1;
+    }
+
+    let token = token.to_string();
+    fs::write(
+        dir.join(format!("{token}.json")),
+        serde_json::to_vec(snapshot)?,
+    )?;
+
+    let mut tokens: Vec<u64> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_suffix(".json")?
+                .parse()
+                .ok()
+        })
+        .collect();
+    tokens.sort_unstable();
+
+    let stale = tokens.len().saturating_sub(MAX_CHANGE_SNAPSHOTS);
+    for stale in &tokens[..stale] {
+        let _ = fs::remove_file(dir.join(format!("{stale}.json")));
+    }
+
+    Ok(token)
+}
+
+/// Milliseconds since the Unix epoch (0 for earlier times).
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -74,6 +74,8 @@ fn carddav_config(server: &Url, auth: CarddavAuthConfig) -> CarddavConfig {
         discover: None,
         server: Some(server.to_string()),
         home: None,
+        discovery_ttl: None,
+        vcard_version: None,
         tls: Default::default(),
         auth,
     }
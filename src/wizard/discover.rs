@@ -258,6 +258,7 @@ fn configure_local(input: &str) -> Result<Chosen> {
 
     Ok(Chosen::Vdir(VdirConfig {
         home_dir: raw.to_string(),
+        vcard_version: None,
     }))
 }
 
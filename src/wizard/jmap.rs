@@ -40,6 +40,7 @@ pub fn configure_discovered(email: &str, discovered: &Discovered) -> Result<Jmap
         server: server.to_string(),
         tls: Default::default(),
         alpn: io_jmap::client::JmapClientStd::default_alpn(),
+        discovery_ttl: None,
         auth,
     })
 }